@@ -0,0 +1,4 @@
+pub fn exec(files: &[&str], check: bool, width: usize) {
+    info!("[Fmt] Files=>{:?} check=>{}", files, check);
+    lox::cli::fmt::format_files(files, check, width);
+}
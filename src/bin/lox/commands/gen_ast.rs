@@ -1,3 +1,5 @@
+#![allow(dead_code, unused_variables)]
+
 use std::fs::File;
 use std::io::prelude::*;
 
@@ -12,7 +14,7 @@ pub fn exec(dist: &str) {
 }
 
 fn gen_ast(out_dit: &str) {
-    define_ast(out_dit, "Expr", &vec![
+    define_ast(out_dit, "Expr", &[
         "Binary   : Expr left, Token operator, Expr right",
         "Grouping : Expr expression",
         "Literal  : Object value",
@@ -20,12 +22,12 @@ fn gen_ast(out_dit: &str) {
     ]);
 }
 
-fn define_ast(out_dir: &str, base_name: &str, ast_types: &Vec<&str>) {
+fn define_ast(out_dir: &str, base_name: &str, ast_types: &[&str]) {
 //    let output_path=
 }
 
 fn write(name: &str, content: &str) -> std::io::Result<()> {
-    let mut file = File::open("name")?;
-    file.write(content.as_bytes())?;
+    let mut file = File::create(name)?;
+    file.write_all(content.as_bytes())?;
     Ok(())
 }
@@ -0,0 +1,4 @@
+pub fn exec(file: &str, format: &str) {
+    info!("[Highlight] File=>{} format=>{}", file, format);
+    lox::cli::highlight::highlight_file(file, format);
+}
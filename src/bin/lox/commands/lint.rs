@@ -0,0 +1,4 @@
+pub fn exec(files: &[&str], config: Option<&str>) {
+    info!("[Lint] Files=>{:?} config=>{:?}", files, config);
+    lox::cli::lint::lint_files(files, config);
+}
@@ -0,0 +1,4 @@
+pub fn exec() {
+    info!("[Lsp] Serving over stdio");
+    lox::cli::lsp::serve_stdio();
+}
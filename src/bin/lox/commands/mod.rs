@@ -1 +1,6 @@
-pub mod gen_ast;
\ No newline at end of file
+pub mod fmt;
+pub mod gen_ast;
+pub mod highlight;
+pub mod lint;
+pub mod lsp;
+pub mod run;
@@ -0,0 +1,4 @@
+pub fn exec(script: &str, include: &[&str], args: &[&str]) {
+    info!("[Run] Script=>{} Include=>{:?} Args=>{:?}", script, include, args);
+    lox::cli::run::from_file(script, include, args);
+}
@@ -1,8 +1,6 @@
-extern crate clap;
-
 mod commands;
 
-use clap::{Arg, App, SubCommand};
+use lox::cli::app::app;
 
 #[macro_use]
 extern crate log;
@@ -13,19 +11,7 @@ fn main() {
     #[cfg(not(feature = "pretty-env-logger"))]
         env_logger::init();
 
-    let matches = App::new("Lox Language")
-        .version("0.1")
-        .about("Lox language rust implementation")
-        .subcommand(
-            SubCommand::with_name("gen-ast")
-
-                .arg_from_usage(
-                    "-d, --dist=[PATH] 'Sets the input file to use'"
-                )
-                .about("Generate AST files automatically ")
-                .help("You should set destination dir")
-        )
-        .get_matches();
+    let matches = app().get_matches();
     if let Some(matches) = matches.subcommand_matches("gen-ast") {
         if matches.is_present("dist") {
             let dist = matches.value_of("dist").unwrap();
@@ -35,5 +21,37 @@ fn main() {
             error!("You should set destination dir");
         }
     }
+    if let Some(matches) = matches.subcommand_matches("run") {
+        let include: Vec<&str> = matches.values_of("include").map(Iterator::collect).unwrap_or_default();
+        let mut script = matches.values_of("script").unwrap();
+        let path = script.next().unwrap();
+        let args: Vec<&str> = script.collect();
+        commands::run::exec(path, &include, &args);
+    }
+    if let Some(matches) = matches.subcommand_matches("fmt") {
+        let files: Vec<&str> = matches.values_of("files").unwrap().collect();
+        let width = match matches.value_of("width").map(str::parse) {
+            Some(Ok(width)) => width,
+            Some(Err(_)) => {
+                error!("--width must be a number");
+                std::process::exit(64);
+            }
+            None => lox::frontend::formatter::DEFAULT_WIDTH,
+        };
+        commands::fmt::exec(&files, matches.is_present("check"), width);
+    }
+    if let Some(matches) = matches.subcommand_matches("lint") {
+        let files: Vec<&str> = matches.values_of("files").unwrap().collect();
+        commands::lint::exec(&files, matches.value_of("config"));
+    }
+    if let Some(matches) = matches.subcommand_matches("highlight") {
+        commands::highlight::exec(
+            matches.value_of("file").unwrap(),
+            matches.value_of("format").unwrap(),
+        );
+    }
+    if matches.subcommand_matches("lsp").is_some() {
+        commands::lsp::exec();
+    }
 //    info!("gen-ast : dist dir :[{}]", dist_dir);
-}
\ No newline at end of file
+}
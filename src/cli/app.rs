@@ -0,0 +1,101 @@
+//! Definition of the `lox` command line
+
+use clap::{App, AppSettings, Arg, SubCommand};
+
+/// The `lox` command line
+pub fn app() -> App<'static, 'static> {
+    App::new("Lox Language")
+        .version("0.1")
+        .about("Lox language rust implementation")
+        .subcommand(
+            SubCommand::with_name("gen-ast")
+
+                .arg_from_usage(
+                    "-d, --dist=[PATH] 'Sets the input file to use'"
+                )
+                .about("Generate AST files automatically ")
+                .help("You should set destination dir")
+        )
+        .subcommand(
+            SubCommand::with_name("run")
+                .about("Run a Lox script")
+                .setting(AppSettings::TrailingVarArg)
+                // the values after the script path are passed to it unchanged,
+                // even ones that look like options
+                .arg(
+                    Arg::with_name("script")
+                        .required(true)
+                        .multiple(true)
+                        .allow_hyphen_values(true)
+                        .help("Path of the script to run, then the arguments passed to it"),
+                )
+                .arg(
+                    Arg::with_name("include")
+                        .short("I")
+                        .long("include")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .help("Directory to search for imported scripts, after LOX_PATH"),
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("fmt")
+                .about("Format Lox source files in place")
+                .arg(Arg::with_name("files").required(true).multiple(true).help("Files to format"))
+                .arg_from_usage("--check 'Only report unformatted files, exiting with 1 if any'")
+                .arg_from_usage("-w, --width=[WIDTH] 'Maximum line width, 80 by default'")
+        )
+        .subcommand(
+            SubCommand::with_name("lint")
+                .about("Report likely mistakes in Lox source files")
+                .arg(Arg::with_name("files").required(true).multiple(true).help("Files to check"))
+                .arg_from_usage("-c, --config=[PATH] 'Rule config file, .loxlint by default'")
+        )
+        .subcommand(
+            SubCommand::with_name("highlight")
+                .about("Print a Lox file with syntax highlighting")
+                .arg(Arg::with_name("file").required(true).help("File to highlight"))
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .takes_value(true)
+                        .possible_values(&["ansi", "html"])
+                        .default_value("ansi")
+                        .help("ANSI coloured text or a standalone HTML page"),
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("lsp")
+                .about("Run the language server over stdin and stdout")
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_args(args: &[&str]) -> Vec<String> {
+        let matches = app()
+            .get_matches_from_safe(["lox", "run"].iter().chain(args))
+            .unwrap();
+        let run = matches.subcommand_matches("run").unwrap();
+        run.values_of("script").unwrap().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_script_arguments() {
+        assert_eq!(run_args(&["s.lox"]), ["s.lox"]);
+        assert_eq!(run_args(&["s.lox", "-v", "x", "--flag"]), ["s.lox", "-v", "x", "--flag"]);
+        // options after the script are the script's own
+        assert_eq!(run_args(&["-I", "lib", "s.lox", "-I", "x"]), ["s.lox", "-I", "x"]);
+        assert_eq!(run_args(&["s.lox", "--", "-v"]), ["s.lox", "--", "-v"]);
+        assert_eq!(run_args(&["--", "-s.lox", "-v"]), ["-s.lox", "-v"]);
+
+        let matches = app()
+            .get_matches_from_safe(vec!["lox", "run", "-I", "lib", "s.lox", "-I", "x"])
+            .unwrap();
+        let run = matches.subcommand_matches("run").unwrap();
+        assert_eq!(run.values_of("include").unwrap().collect::<Vec<_>>(), ["lib"]);
+    }
+}
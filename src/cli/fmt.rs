@@ -0,0 +1,41 @@
+use std::fs;
+
+use crate::cli::run::exit_code;
+use crate::frontend::formatter;
+use crate::result::Result;
+
+/// Formats the files in place. With `check`, only reports the files that
+/// are not formatted and exits with 1 if there are any.
+pub fn format_files(files: &[&str], check: bool, width: usize) {
+    let mut unformatted = 0;
+    for fname in files {
+        match format_file(fname, check, width) {
+            Ok(true) => (),
+            Ok(false) => {
+                println!("{} is not formatted", fname);
+                unformatted += 1;
+            }
+            Err(e) => {
+                eprintln!("{}: {}", fname, e);
+                std::process::exit(exit_code(&e));
+            }
+        }
+    }
+    if unformatted > 0 {
+        std::process::exit(1);
+    }
+}
+
+/// Whether the file was already formatted
+fn format_file(fname: &str, check: bool, width: usize) -> Result<bool> {
+    let source = fs::read_to_string(fname)?;
+    let formatted = formatter::format(&source, width)?;
+    if formatted == source {
+        return Ok(true);
+    }
+    if !check {
+        fs::write(fname, formatted)?;
+        return Ok(true);
+    }
+    Ok(false)
+}
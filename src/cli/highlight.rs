@@ -0,0 +1,24 @@
+use std::fs;
+
+use crate::cli::run::exit_code;
+use crate::frontend::highlight;
+use crate::result::Result;
+
+/// Prints the file highlighted as `html` or `ansi`
+pub fn highlight_file(fname: &str, format: &str) {
+    match render(fname, format) {
+        Ok(out) => print!("{}", out),
+        Err(e) => {
+            eprintln!("{}: {}", fname, e);
+            std::process::exit(exit_code(&e));
+        }
+    }
+}
+
+fn render(fname: &str, format: &str) -> Result<String> {
+    let source = fs::read_to_string(fname)?;
+    match format {
+        "html" => highlight::to_html(&source),
+        _ => highlight::to_ansi(&source),
+    }
+}
@@ -0,0 +1,50 @@
+use std::fs;
+use std::path::Path;
+
+use crate::cli::run::exit_code;
+use crate::frontend::lint::{lint, LintConfig};
+use crate::result::Result;
+
+/// Config file used when none is given
+const DEFAULT_CONFIG: &str = ".loxlint";
+
+/// Prints the warnings for the files and exits with 1 if there are any
+pub fn lint_files(files: &[&str], config: Option<&str>) {
+    let config = match load_config(config) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(exit_code(&e));
+        }
+    };
+    let mut warnings = 0;
+    for fname in files {
+        let result = fs::read_to_string(fname)
+            .map_err(Into::into)
+            .and_then(|source| lint(&source, &config));
+        match result {
+            Ok(lints) => {
+                for lint in lints.iter() {
+                    println!("{}:{}", fname, lint);
+                }
+                warnings += lints.len();
+            }
+            Err(e) => {
+                eprintln!("{}: {}", fname, e);
+                std::process::exit(exit_code(&e));
+            }
+        }
+    }
+    if warnings > 0 {
+        std::process::exit(1);
+    }
+}
+
+fn load_config(path: Option<&str>) -> Result<LintConfig> {
+    let path = match path {
+        Some(path) => path,
+        None if Path::new(DEFAULT_CONFIG).exists() => DEFAULT_CONFIG,
+        None => return Ok(LintConfig::default()),
+    };
+    LintConfig::parse(&fs::read_to_string(path)?)
+}
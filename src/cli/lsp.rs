@@ -0,0 +1,17 @@
+use std::io;
+
+use crate::lsp::server;
+
+/// Speaks LSP over stdin and stdout until the client exits
+pub fn serve_stdio() {
+    let stdin = io::stdin();
+    match server::serve(stdin.lock(), io::stdout()) {
+        Ok(true) => (),
+        // the client exited without asking for a shutdown
+        Ok(false) => std::process::exit(1),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(74);
+        }
+    }
+}
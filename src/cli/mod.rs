@@ -1,2 +1,6 @@
-use core;
+pub mod app;
+pub mod fmt;
+pub mod highlight;
+pub mod lint;
+pub mod lsp;
 pub mod run;
@@ -1,5 +1,50 @@
-use super::super::core;
+use std::thread;
 
-pub fn from_file(fname: &str) {
-    core::lox::Lox::run_file(fname);
+use crate::core::lox::Lox;
+use crate::result::Error;
+use crate::runtime::interpreter::STACK_SIZE;
+
+/// Runs a script, imports are searched for next to the importing script,
+/// then in the directories of `LOX_PATH`, then in the `include` ones.
+/// `args` are what the script gets from `args()`.
+pub fn from_file(fname: &str, include: &[&str], args: &[&str]) {
+    let fname = fname.to_string();
+    let include: Vec<String> = include.iter().map(|dir| dir.to_string()).collect();
+    let args = args.iter().map(|arg| arg.to_string()).collect();
+    // deep recursion needs more than the main thread's stack
+    thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || run(&fname, &include, args))
+        .expect("failed to start the interpreter thread")
+        .join()
+        .expect("the interpreter thread panicked");
+}
+
+fn run(fname: &str, include: &[String], args: Vec<String>) {
+    let mut lox = Lox::new();
+    lox.set_args(args);
+    if let Some(paths) = std::env::var_os("LOX_PATH") {
+        std::env::split_paths(&paths).for_each(|dir| lox.add_search_path(dir));
+    }
+    include.iter().for_each(|dir| lox.add_search_path(dir));
+    if let Err(e) = lox.run_file(fname) {
+        if let Error::Exit(code) = e {
+            std::process::exit(code);
+        }
+        eprintln!("{}", e);
+        for frame in lox.stack_trace() {
+            eprintln!("    at {}", frame);
+        }
+        std::process::exit(exit_code(&e));
+    }
+}
+
+/// Exit codes follow the BSD `sysexits.h` conventions
+pub(crate) fn exit_code(err: &Error) -> i32 {
+    match *err {
+        Error::Usage => 64,
+        Error::Lexical(..) | Error::Parse(..) => 65,
+        Error::IO(_) => 74,
+        _ => 70,
+    }
 }
@@ -1,19 +1,58 @@
-// use std::fs;
+use std::fs;
+use std::io;
 use std::path;
-#[derive(Debug)]
-pub struct Lox {}
+
+use crate::frontend::parser::Parser;
+use crate::frontend::scanner::Scanner;
+use crate::result::{Result, StackFrame};
+use crate::runtime::interpreter::Interpreter;
+
+#[derive(Default)]
+pub struct Lox {
+    interpreter: Interpreter,
+}
 
 impl Lox {
-    // add code here
-    pub fn run_file(filename: &str) {
+    pub fn new() -> Lox {
+        Lox::default()
+    }
+
+    pub fn run_file(&mut self, filename: &str) -> Result<()> {
         if !path::Path::new(filename).exists() {
-            eprintln!("Lox Program: path {} not exist!", filename);
-            std::process::exit(1)
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Lox Program: path {} not exist!", filename),
+            )
+            .into());
         }
+        let source = fs::read_to_string(filename)?;
+        self.interpreter.set_file(filename);
+        self.run(&source)
+    }
+
+    pub fn run(&mut self, source: &str) -> Result<()> {
+        let tokens = Scanner::new(source).scan_tokens()?;
+        let statements = Parser::new(tokens).parse()?;
+        self.interpreter.interpret(&statements)
+    }
+
+    /// Adds a directory to look for imported scripts in
+    pub fn add_search_path<P: Into<path::PathBuf>>(&mut self, dir: P) {
+        self.interpreter.add_search_path(dir);
     }
-    fn run(source: &str) {}
 
-    // pub fn new()->Result<Lox,&'static str>{
-    //   Ok(Lox{})
-    // }
+    /// Sets what `args()` returns to scripts
+    pub fn set_args(&mut self, args: Vec<String>) {
+        self.interpreter.set_args(args);
+    }
+
+    /// Where the runtime error the last run failed with was raised,
+    /// innermost call first.
+    ///
+    /// The error itself only carries a line, in whichever script raised
+    /// it. Embedders reporting where an error happened should read the
+    /// file, line and column of each call from this trace instead.
+    pub fn stack_trace(&self) -> &[StackFrame] {
+        self.interpreter.stack_trace()
+    }
 }
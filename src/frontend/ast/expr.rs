@@ -1,6 +1,6 @@
 // use super::super::token::TokenType;
 
-use crate::frontend::token::Token;
+use crate::frontend::token::{Token, TokenType};
 
 pub trait Boxer {
     // add code here
@@ -16,35 +16,262 @@ pub trait Boxer {
 pub enum Expr {
     Noop,
     BinaryExpr(Box<BinaryExpr>),
+    /// `and` / `or`, evaluated with short-circuiting
+    Logical(Box<BinaryExpr>),
+    /// `condition ? then : else`
+    Conditional(Box<ConditionalExpr>),
+    /// `a, b` evaluates both and yields `b`
+    Comma(Box<BinaryExpr>),
     Unary(Token, Box<Expr>),
-    Literal(String),
+    Literal(Token),
     Grouping(Box<Expr>),
+    Variable(Token),
+    Assign(Token, Box<Expr>),
+    /// `+=` and friends on a variable, field or element
+    CompoundAssign(Box<CompoundAssignExpr>),
+    /// `++` or `--` before or after a variable, field or element
+    Increment(Box<IncrementExpr>),
+    Call(Box<CallExpr>),
+    Get(Box<Expr>, Token),
+    Set(Box<SetExpr>),
+    Index(Box<IndexExpr>),
+    SetIndex(Box<SetIndexExpr>),
+    This(Token),
+    /// `super` keyword and the method name
+    Super(Token, Token),
+    /// `[` token and the elements
+    List(Token, Vec<Expr>),
+    /// `{` token and the key-value pairs in source order
+    Map(Token, Vec<(Expr, Expr)>),
+    /// first token of the string and its parts: string literals
+    /// alternating with the embedded expressions
+    Interpolation(Token, Vec<Expr>),
+    /// `yield` keyword and the yielded value, nil if left out
+    Yield(Token, Option<Box<Expr>>),
 }
 
 #[derive(PartialEq, Debug, Clone, Eq, Hash)]
 pub struct BinaryExpr {
-    op: Token,
-    lhs: Box<Expr>,
-    rhs: Box<Expr>,
+    pub op: Token,
+    pub lhs: Box<Expr>,
+    pub rhs: Box<Expr>,
 }
 
 impl Boxer for Expr {}
 
+impl Expr {
+    /// The token errors raised by the expression itself are reported at
+    pub fn token(&self) -> Option<&Token> {
+        match *self {
+            Expr::Noop => None,
+            Expr::BinaryExpr(ref b) | Expr::Logical(ref b) | Expr::Comma(ref b) => Some(&b.op),
+            Expr::Conditional(ref c) => Some(&c.question),
+            Expr::Grouping(ref e) => e.token(),
+            Expr::Call(ref call) => Some(&call.paren),
+            Expr::Get(_, ref name) => Some(name),
+            Expr::Set(ref set) => Some(&set.name),
+            Expr::Index(ref index) => Some(&index.bracket),
+            Expr::SetIndex(ref set) => Some(&set.bracket),
+            Expr::CompoundAssign(ref assign) => Some(&assign.op),
+            Expr::Increment(ref increment) => Some(&increment.op),
+            Expr::Super(_, ref method) => Some(method),
+            Expr::Unary(ref t, _)
+            | Expr::Literal(ref t)
+            | Expr::Variable(ref t)
+            | Expr::Assign(ref t, _)
+            | Expr::This(ref t)
+            | Expr::List(ref t, _)
+            | Expr::Map(ref t, _)
+            | Expr::Interpolation(ref t, _)
+            | Expr::Yield(ref t, _) => Some(t),
+        }
+    }
+}
+
 impl BinaryExpr {
     pub fn new(lhs: Box<Expr>, op: Token, rhs: Box<Expr>) -> Self {
         BinaryExpr { op, lhs, rhs }
     }
+
+    pub fn operator(&self) -> Option<BinaryOperator> {
+        BinaryOperator::from_token_type(self.op.tok_type)
+    }
+}
+
+impl Boxer for BinaryExpr {}
+
+#[derive(PartialEq, Debug, Clone, Eq, Hash)]
+pub struct ConditionalExpr {
+    pub condition: Expr,
+    /// the `?` token
+    pub question: Token,
+    pub then_branch: Expr,
+    pub else_branch: Expr,
+}
+
+impl ConditionalExpr {
+    pub fn new(condition: Expr, question: Token, then_branch: Expr, else_branch: Expr) -> Self {
+        ConditionalExpr {
+            condition,
+            question,
+            then_branch,
+            else_branch,
+        }
+    }
+}
+
+impl Boxer for ConditionalExpr {}
+
+#[derive(PartialEq, Debug, Clone, Eq, Hash)]
+pub struct CompoundAssignExpr {
+    /// a `Variable`, `Get` or `Index` expression, evaluated once
+    pub target: Expr,
+    /// the `+=` token
+    pub op: Token,
+    pub value: Expr,
+}
+
+impl CompoundAssignExpr {
+    pub fn new(target: Expr, op: Token, value: Expr) -> Self {
+        CompoundAssignExpr { target, op, value }
+    }
+
+    /// The operator combining the old value with the new one
+    pub fn operator(&self) -> Option<BinaryOperator> {
+        let op = match self.op.tok_type {
+            TokenType::PlusEqual => BinaryOperator::Plus,
+            TokenType::MinusEqual => BinaryOperator::Minus,
+            TokenType::StarEqual => BinaryOperator::Star,
+            TokenType::SlashEqual => BinaryOperator::Slash,
+            TokenType::PercentEqual => BinaryOperator::Percent,
+            _ => return None,
+        };
+        Some(op)
+    }
+}
+
+impl Boxer for CompoundAssignExpr {}
+
+#[derive(PartialEq, Debug, Clone, Eq, Hash)]
+pub struct IncrementExpr {
+    /// a `Variable`, `Get` or `Index` expression, evaluated once
+    pub target: Expr,
+    /// the `++` or `--` token
+    pub op: Token,
+    /// `++x` gives the new value, `x++` the old one
+    pub prefix: bool,
+}
+
+impl IncrementExpr {
+    pub fn new(target: Expr, op: Token, prefix: bool) -> Self {
+        IncrementExpr { target, op, prefix }
+    }
+
+    /// What is added to the target
+    pub fn delta(&self) -> f64 {
+        match self.op.tok_type {
+            TokenType::MinusMinus => -1.0,
+            _ => 1.0,
+        }
+    }
 }
 
-impl Boxer for BinaryExpr {
+impl Boxer for IncrementExpr {}
+
+#[derive(PartialEq, Debug, Clone, Eq, Hash)]
+pub struct CallExpr {
+    pub callee: Box<Expr>,
+    /// closing paren, used to report errors
+    pub paren: Token,
+    pub args: Vec<Expr>,
+}
+
+impl CallExpr {
+    pub fn new(callee: Box<Expr>, paren: Token, args: Vec<Expr>) -> Self {
+        CallExpr {
+            callee,
+            paren,
+            args,
+        }
+    }
+}
+
+impl Boxer for CallExpr {}
+
+#[derive(PartialEq, Debug, Clone, Eq, Hash)]
+pub struct SetExpr {
+    pub object: Box<Expr>,
+    pub name: Token,
+    pub value: Box<Expr>,
+}
+
+impl SetExpr {
+    pub fn new(object: Box<Expr>, name: Token, value: Box<Expr>) -> Self {
+        SetExpr {
+            object,
+            name,
+            value,
+        }
+    }
+}
+
+impl Boxer for SetExpr {}
+
+#[derive(PartialEq, Debug, Clone, Eq, Hash)]
+pub struct IndexExpr {
+    pub object: Box<Expr>,
+    /// closing bracket, used to report errors
+    pub bracket: Token,
+    pub index: Box<Expr>,
+}
+
+impl IndexExpr {
+    pub fn new(object: Box<Expr>, bracket: Token, index: Box<Expr>) -> Self {
+        IndexExpr {
+            object,
+            bracket,
+            index,
+        }
+    }
+}
+
+impl Boxer for IndexExpr {}
+
+#[derive(PartialEq, Debug, Clone, Eq, Hash)]
+pub struct SetIndexExpr {
+    pub object: Box<Expr>,
+    pub bracket: Token,
+    pub index: Box<Expr>,
+    pub value: Box<Expr>,
 }
 
+impl SetIndexExpr {
+    pub fn new(object: Box<Expr>, bracket: Token, index: Box<Expr>, value: Box<Expr>) -> Self {
+        SetIndexExpr {
+            object,
+            bracket,
+            index,
+            value,
+        }
+    }
+}
+
+impl Boxer for SetIndexExpr {}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum BinaryOperator {
     Plus,
     Minus,
     Slash,
     Star,
+    Percent,
+    StarStar,
+    TildeSlash,
+    Ampersand,
+    Pipe,
+    Caret,
+    LessLess,
+    GreaterGreater,
     Equal,
     BangEqual,
     Greater,
@@ -54,14 +281,47 @@ pub enum BinaryOperator {
 }
 
 impl BinaryOperator {
-    pub fn to_string(&self) -> &str {
-        match *self {
-            BinaryOperator::Equal => "=",
-            BinaryOperator::BangEqual => "==",
+    pub fn from_token_type(t: TokenType) -> Option<BinaryOperator> {
+        let op = match t {
+            TokenType::PLUS => BinaryOperator::Plus,
+            TokenType::MINUS => BinaryOperator::Minus,
+            TokenType::SLASH => BinaryOperator::Slash,
+            TokenType::STAR => BinaryOperator::Star,
+            TokenType::PERCENT => BinaryOperator::Percent,
+            TokenType::StarStar => BinaryOperator::StarStar,
+            TokenType::TildeSlash => BinaryOperator::TildeSlash,
+            TokenType::AMPERSAND => BinaryOperator::Ampersand,
+            TokenType::PIPE => BinaryOperator::Pipe,
+            TokenType::CARET => BinaryOperator::Caret,
+            TokenType::LessLess => BinaryOperator::LessLess,
+            TokenType::GreaterGreater => BinaryOperator::GreaterGreater,
+            TokenType::EqualEqual => BinaryOperator::Equal,
+            TokenType::BangEqual => BinaryOperator::BangEqual,
+            TokenType::GREATER => BinaryOperator::Greater,
+            TokenType::GreaterEqual => BinaryOperator::GreaterEqual,
+            TokenType::LESS => BinaryOperator::Less,
+            TokenType::LessEqual => BinaryOperator::LessEqual,
+            _ => return None,
+        };
+        Some(op)
+    }
+
+    pub fn to_string(self) -> &'static str {
+        match self {
+            BinaryOperator::Equal => "==",
+            BinaryOperator::BangEqual => "!=",
             BinaryOperator::Plus => "+",
             BinaryOperator::Minus => "-",
             BinaryOperator::Star => "*",
             BinaryOperator::Slash => "/",
+            BinaryOperator::Percent => "%",
+            BinaryOperator::StarStar => "**",
+            BinaryOperator::TildeSlash => "~/",
+            BinaryOperator::Ampersand => "&",
+            BinaryOperator::Pipe => "|",
+            BinaryOperator::Caret => "^",
+            BinaryOperator::LessLess => "<<",
+            BinaryOperator::GreaterGreater => ">>",
             BinaryOperator::Greater => ">",
             BinaryOperator::GreaterEqual => ">=",
             BinaryOperator::Less => "<",
@@ -0,0 +1,178 @@
+use std::rc::Rc;
+
+use crate::frontend::ast::expr::{Boxer, Expr};
+use crate::frontend::token::Token;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Stmt {
+    Expression(Expr),
+    Print(Expr),
+    Var(Token, Option<Expr>),
+    Block(Vec<Stmt>),
+    If(Box<IfStmt>),
+    While(Box<WhileStmt>),
+    ForIn(Box<ForInStmt>),
+    Function(Rc<FunctionDecl>),
+    /// `return` keyword and the optional value
+    Return(Token, Option<Expr>),
+    Class(Box<ClassDecl>),
+    Break(Token),
+    /// `throw` keyword and the thrown value
+    Throw(Token, Expr),
+    Try(Box<TryStmt>),
+    Import(Box<ImportStmt>),
+}
+
+impl Boxer for Stmt {}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IfStmt {
+    pub condition: Expr,
+    pub then_branch: Stmt,
+    pub else_branch: Option<Stmt>,
+}
+
+impl IfStmt {
+    pub fn new(condition: Expr, then_branch: Stmt, else_branch: Option<Stmt>) -> Self {
+        IfStmt {
+            condition,
+            then_branch,
+            else_branch,
+        }
+    }
+}
+
+impl Boxer for IfStmt {}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WhileStmt {
+    pub condition: Expr,
+    pub body: Stmt,
+}
+
+impl WhileStmt {
+    pub fn new(condition: Expr, body: Stmt) -> Self {
+        WhileStmt { condition, body }
+    }
+}
+
+impl Boxer for WhileStmt {}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ForInStmt {
+    /// the element, or the index or key and the element
+    pub variables: Vec<Token>,
+    /// the `in` keyword
+    pub keyword: Token,
+    pub iterable: Expr,
+    pub body: Stmt,
+}
+
+impl ForInStmt {
+    pub fn new(variables: Vec<Token>, keyword: Token, iterable: Expr, body: Stmt) -> Self {
+        ForInStmt {
+            variables,
+            keyword,
+            iterable,
+            body,
+        }
+    }
+}
+
+impl Boxer for ForInStmt {}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TryStmt {
+    pub body: Vec<Stmt>,
+    /// the name the error is bound to and the handler
+    pub catch: Option<(Token, Vec<Stmt>)>,
+    /// runs however the rest of the statement is left
+    pub finally: Option<Vec<Stmt>>,
+}
+
+impl TryStmt {
+    pub fn new(
+        body: Vec<Stmt>,
+        catch: Option<(Token, Vec<Stmt>)>,
+        finally: Option<Vec<Stmt>>,
+    ) -> Self {
+        TryStmt {
+            body,
+            catch,
+            finally,
+        }
+    }
+}
+
+impl Boxer for TryStmt {}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ImportStmt {
+    /// `import` or `from`
+    pub keyword: Token,
+    /// the string naming the script
+    pub path: Token,
+    /// the name of the module, for `import ... as`
+    pub alias: Option<Token>,
+    /// the names taken from the module, for `from ... import`
+    pub names: Vec<Token>,
+}
+
+impl ImportStmt {
+    pub fn new(keyword: Token, path: Token, alias: Option<Token>, names: Vec<Token>) -> Self {
+        ImportStmt {
+            keyword,
+            path,
+            alias,
+            names,
+        }
+    }
+}
+
+impl Boxer for ImportStmt {}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FunctionDecl {
+    pub name: Token,
+    pub params: Vec<Token>,
+    pub body: Vec<Stmt>,
+    /// text of the `///` comments preceding the declaration
+    pub doc: Option<String>,
+    /// declared with `fun*`, calls return a generator
+    pub generator: bool,
+}
+
+impl FunctionDecl {
+    pub fn new(name: Token, params: Vec<Token>, body: Vec<Stmt>) -> Self {
+        FunctionDecl {
+            name,
+            params,
+            body,
+            doc: None,
+            generator: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ClassDecl {
+    pub name: Token,
+    /// always an `Expr::Variable` when present
+    pub superclass: Option<Expr>,
+    pub methods: Vec<Rc<FunctionDecl>>,
+    /// text of the `///` comments preceding the declaration
+    pub doc: Option<String>,
+}
+
+impl ClassDecl {
+    pub fn new(name: Token, superclass: Option<Expr>, methods: Vec<Rc<FunctionDecl>>) -> Self {
+        ClassDecl {
+            name,
+            superclass,
+            methods,
+            doc: None,
+        }
+    }
+}
+
+impl Boxer for ClassDecl {}
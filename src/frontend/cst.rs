@@ -0,0 +1,488 @@
+//! A lossless concrete syntax tree.
+//!
+//! Every token keeps the whitespace and comments around it as trivia, so
+//! printing a `SyntaxTree` gives back the exact source it was parsed from.
+//! The `Parser` records where each grammar production starts and ends
+//! while it builds the AST; those events are replayed here to build the tree.
+
+use std::fmt;
+
+use crate::frontend::ast::stmt::Stmt;
+use crate::frontend::parser::Parser;
+use crate::frontend::scanner::Scanner;
+use crate::frontend::token::Token;
+use crate::result::Result;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SyntaxKind {
+    Program,
+
+    // declarations and statements
+    ClassDecl,
+    /// functions and methods
+    FunDecl,
+    ParamList,
+    VarDecl,
+    ImportStmt,
+    ExprStmt,
+    PrintStmt,
+    IfStmt,
+    WhileStmt,
+    ForStmt,
+    ForInStmt,
+    ReturnStmt,
+    BreakStmt,
+    ThrowStmt,
+    TryStmt,
+    CatchClause,
+    Block,
+
+    // expressions
+    CommaExpr,
+    AssignExpr,
+    ConditionalExpr,
+    LogicalExpr,
+    BinaryExpr,
+    UnaryExpr,
+    /// prefix or postfix `++` and `--`
+    IncrementExpr,
+    CallExpr,
+    ArgList,
+    GetExpr,
+    IndexExpr,
+    GroupingExpr,
+    LiteralExpr,
+    VariableExpr,
+    ThisExpr,
+    SuperExpr,
+    ListExpr,
+    MapExpr,
+    MapEntry,
+    InterpolationExpr,
+    /// `yield` and the yielded value
+    YieldExpr,
+}
+
+/// What the parser saw, in source order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// Opens a node, or nothing if `kind` is `None`: a checkpoint the parser
+    /// didn't wrap. A node wrapped around one that already started at the
+    /// same place is a later `Start`, `forward_parent` events ahead.
+    Start {
+        kind: Option<SyntaxKind>,
+        forward_parent: Option<usize>,
+    },
+    /// index of the consumed token in the parser's token list
+    Token(usize),
+    Finish,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TriviaKind {
+    Whitespace,
+    Newline,
+    LineComment,
+    BlockComment,
+    DocComment,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub text: String,
+}
+
+impl Trivia {
+    pub fn is_comment(&self) -> bool {
+        match self.kind {
+            TriviaKind::LineComment | TriviaKind::BlockComment | TriviaKind::DocComment => true,
+            TriviaKind::Whitespace | TriviaKind::Newline => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SyntaxToken {
+    pub token: Token,
+    /// the token exactly as written in the source
+    pub text: String,
+    /// trivia since the end of the previous line
+    pub leading: Vec<Trivia>,
+    /// trivia up to the end of the token's line
+    pub trailing: Vec<Trivia>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SyntaxNode {
+    pub kind: SyntaxKind,
+    pub children: Vec<SyntaxElement>,
+}
+
+impl SyntaxNode {
+    pub fn new(kind: SyntaxKind) -> SyntaxNode {
+        SyntaxNode {
+            kind,
+            children: vec![],
+        }
+    }
+
+    /// All tokens of the node, in source order
+    pub fn tokens(&self) -> Vec<&SyntaxToken> {
+        let mut tokens = vec![];
+        self.collect_tokens(&mut tokens);
+        tokens
+    }
+
+    fn collect_tokens<'a>(&'a self, tokens: &mut Vec<&'a SyntaxToken>) {
+        for child in self.children.iter() {
+            match *child {
+                SyntaxElement::Node(ref node) => node.collect_tokens(tokens),
+                SyntaxElement::Token(ref token) => tokens.push(token),
+            }
+        }
+    }
+
+    pub fn child_nodes(&self) -> impl Iterator<Item = &SyntaxNode> {
+        self.children.iter().filter_map(|child| match *child {
+            SyntaxElement::Node(ref node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    pub fn child_tokens(&self) -> impl Iterator<Item = &SyntaxToken> {
+        self.children.iter().filter_map(|child| match *child {
+            SyntaxElement::Token(ref token) => Some(token),
+            SyntaxElement::Node(_) => None,
+        })
+    }
+
+    pub fn first_token(&self) -> Option<&SyntaxToken> {
+        self.children.iter().find_map(|child| match *child {
+            SyntaxElement::Node(ref node) => node.first_token(),
+            SyntaxElement::Token(ref token) => Some(token),
+        })
+    }
+}
+
+impl fmt::Display for Trivia {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl fmt::Display for SyntaxToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for trivia in self.leading.iter() {
+            write!(f, "{}", trivia)?;
+        }
+        f.write_str(&self.text)?;
+        for trivia in self.trailing.iter() {
+            write!(f, "{}", trivia)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for child in self.children.iter() {
+            match *child {
+                SyntaxElement::Node(ref node) => write!(f, "{}", node)?,
+                SyntaxElement::Token(ref token) => write!(f, "{}", token)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A parsed program that can be printed back byte-for-byte
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SyntaxTree {
+    pub root: SyntaxNode,
+    /// the AST built by the same parse as the tree
+    statements: Vec<Stmt>,
+}
+
+impl SyntaxTree {
+    pub fn parse(source: &str) -> Result<SyntaxTree> {
+        let tokens = Scanner::new(source).scan_tokens()?;
+        let mut parser = Parser::new(tokens);
+        let statements = parser.parse()?;
+        let (tokens, events) = parser.into_events();
+        Ok(SyntaxTree {
+            root: build(source, &tokens, &events),
+            statements,
+        })
+    }
+
+    /// The AST of the program
+    pub fn ast(&self) -> &[Stmt] {
+        &self.statements
+    }
+}
+
+impl fmt::Display for SyntaxTree {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.root.fmt(f)
+    }
+}
+
+/// Replays parser events into a tree, attaching the source between
+/// tokens to them as trivia
+pub fn build(source: &str, tokens: &[Token], events: &[Event]) -> SyntaxNode {
+    let mut consumed = events.iter().filter_map(|event| match *event {
+        Event::Token(i) => Some(&tokens[i]),
+        _ => None,
+    });
+    let mut syntax_tokens = vec![];
+    let mut prev_end = 0;
+    if let Some(first) = consumed.next() {
+        syntax_tokens.push(syntax_token(
+            source,
+            first,
+            lex_trivia(&source[..first.span.start]),
+        ));
+        prev_end = first.span.end;
+    }
+    for token in consumed {
+        let mut trailing = lex_trivia(&source[prev_end..token.span.start]);
+        let split = trailing
+            .iter()
+            .position(|t| t.kind == TriviaKind::Newline)
+            .unwrap_or(trailing.len());
+        let leading = trailing.split_off(split);
+        syntax_tokens.last_mut().unwrap().trailing = trailing;
+        syntax_tokens.push(syntax_token(source, token, leading));
+        prev_end = token.span.end;
+    }
+    if let Some(last) = syntax_tokens.last_mut() {
+        last.trailing.extend(lex_trivia(&source[prev_end..]));
+    }
+
+    let mut syntax_tokens = syntax_tokens.into_iter();
+    let mut stack: Vec<SyntaxNode> = vec![];
+    let mut root = SyntaxNode::new(SyntaxKind::Program);
+    // forward parents are opened with their first child, and skipped after
+    let mut opened = vec![false; events.len()];
+    for (i, event) in events.iter().enumerate() {
+        match *event {
+            Event::Start { kind: None, .. } => (),
+            Event::Start { .. } if opened[i] => (),
+            Event::Start {
+                kind: Some(kind),
+                mut forward_parent,
+            } => {
+                let mut kinds = vec![kind];
+                let mut at = i;
+                while let Some(distance) = forward_parent {
+                    at += distance;
+                    opened[at] = true;
+                    match events[at] {
+                        Event::Start {
+                            kind: Some(kind),
+                            forward_parent: next,
+                        } => {
+                            kinds.push(kind);
+                            forward_parent = next;
+                        }
+                        _ => unreachable!("forward parents are wrapped nodes"),
+                    }
+                }
+                // outermost first
+                stack.extend(kinds.into_iter().rev().map(SyntaxNode::new));
+            }
+            Event::Token(_) => {
+                let token = syntax_tokens.next().unwrap();
+                stack
+                    .last_mut()
+                    .expect("tokens are consumed inside a node")
+                    .children
+                    .push(SyntaxElement::Token(token));
+            }
+            Event::Finish => {
+                let node = stack.pop().expect("unbalanced parser events");
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(SyntaxElement::Node(node)),
+                    None => root = node,
+                }
+            }
+        }
+    }
+    root
+}
+
+fn syntax_token(source: &str, token: &Token, leading: Vec<Trivia>) -> SyntaxToken {
+    SyntaxToken {
+        token: token.clone(),
+        text: source[token.span.start..token.span.end].to_string(),
+        leading,
+        trailing: vec![],
+    }
+}
+
+/// Splits the text between two tokens into whitespace, newlines and comments
+pub fn lex_trivia(text: &str) -> Vec<Trivia> {
+    let mut trivia = vec![];
+    let mut rest = text;
+    while !rest.is_empty() {
+        let (kind, len) = if rest.starts_with('\n') {
+            (TriviaKind::Newline, 1)
+        } else if rest.starts_with("/*") {
+            (TriviaKind::BlockComment, block_comment_len(rest))
+        } else if rest.starts_with("//") {
+            let len = rest.find('\n').unwrap_or(rest.len());
+            let kind = if rest.starts_with("///") && !rest.starts_with("////") {
+                TriviaKind::DocComment
+            } else {
+                TriviaKind::LineComment
+            };
+            (kind, len)
+        } else {
+            let len = rest.find(['\n', '/']).unwrap_or(rest.len());
+            (TriviaKind::Whitespace, len)
+        };
+        trivia.push(Trivia {
+            kind,
+            text: rest[..len].to_string(),
+        });
+        rest = &rest[len..];
+    }
+    trivia
+}
+
+fn block_comment_len(text: &str) -> usize {
+    let mut depth = 0;
+    let mut i = 0;
+    while i < text.len() {
+        if text[i..].starts_with("/*") {
+            depth += 1;
+            i += 2;
+        } else if text[i..].starts_with("*/") {
+            depth -= 1;
+            i += 2;
+            if depth == 0 {
+                return i;
+            }
+        } else {
+            i += text[i..].chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    text.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::token::TokenType;
+
+    const SOURCE: &str = r#"// leading comment
+/// Greets.
+fun greet(name) {   // trailing comment
+    /* block /* nested */ */
+    print "hi ${name}!";  
+}
+
+class A < B { init() { this.x = [1, 2,]; } }
+var m = {"a": 1, "b": 0x10};
+for (var i = 0; i < 3; i = i + 1) { if (!m.has("c") and i >= 1) break; }
+print """raw
+  text""";
+greet(-m["a"] * (2 + 3));
+// the end
+"#;
+
+    #[test]
+    fn test_round_trip() {
+        let sources = [
+            SOURCE,
+            "",
+            "  \n// only a comment",
+            "print 1;\r\nprint 2; // crlf\r\n",
+            "var x = 1;",
+        ];
+        for source in sources.iter() {
+            let tree = SyntaxTree::parse(source).unwrap();
+            assert_eq!(tree.to_string(), *source);
+        }
+    }
+
+    #[test]
+    fn test_ast_from_tree() {
+        let tree = SyntaxTree::parse(SOURCE).unwrap();
+        let tokens = Scanner::new(SOURCE).scan_tokens().unwrap();
+        let expected = Parser::new(tokens).parse().unwrap();
+        assert_eq!(tree.ast(), &expected[..]);
+    }
+
+    #[test]
+    fn test_tree_shape() {
+        let tree = SyntaxTree::parse("fun f(a) { return a; } // done\n").unwrap();
+        let root = &tree.root;
+        assert_eq!(root.kind, SyntaxKind::Program);
+
+        let kinds: Vec<_> = root.child_nodes().map(|n| n.kind).collect();
+        assert_eq!(kinds, vec![SyntaxKind::FunDecl]);
+        let fun = root.child_nodes().next().unwrap();
+        let kinds: Vec<_> = fun.child_nodes().map(|n| n.kind).collect();
+        assert_eq!(kinds, vec![SyntaxKind::ParamList, SyntaxKind::Block]);
+
+        let close = fun.tokens().into_iter().last().unwrap();
+        assert_eq!(close.text, "}");
+        assert_eq!(close.trailing[1].kind, TriviaKind::LineComment);
+        let eof = root.child_tokens().last().unwrap();
+        assert_eq!(eof.token.tok_type, TokenType::EOF);
+        assert_eq!(eof.leading[0].kind, TriviaKind::Newline);
+    }
+
+    /// The nodes of a tree as nested `Kind(children)`, tokens as their text
+    fn shape(node: &SyntaxNode) -> String {
+        let children: Vec<String> = node
+            .children
+            .iter()
+            .map(|child| match *child {
+                SyntaxElement::Node(ref node) => shape(node),
+                SyntaxElement::Token(ref token) => token.text.clone(),
+            })
+            .collect();
+        format!("{:?}({})", node.kind, children.join(" "))
+    }
+
+    #[test]
+    fn test_nested_wraps() {
+        // nodes wrapped again at the same place, and checkpoints never wrapped
+        let tree = SyntaxTree::parse("x = a - b - c, f(1).g[2];").unwrap();
+        let stmt = tree.root.child_nodes().next().unwrap();
+        assert_eq!(
+            shape(stmt),
+            "ExprStmt(CommaExpr(AssignExpr(VariableExpr(x) = \
+             BinaryExpr(BinaryExpr(VariableExpr(a) - VariableExpr(b)) - VariableExpr(c))) , \
+             IndexExpr(GetExpr(CallExpr(VariableExpr(f) ArgList(( LiteralExpr(1) ))) . g) \
+             [ LiteralExpr(2) ])) ;)"
+        );
+    }
+
+    #[test]
+    fn test_lex_trivia() {
+        let kinds: Vec<_> = lex_trivia(" \t/* a */// b\n/// c")
+            .into_iter()
+            .map(|t| t.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TriviaKind::Whitespace,
+                TriviaKind::BlockComment,
+                TriviaKind::LineComment,
+                TriviaKind::Newline,
+                TriviaKind::DocComment,
+            ]
+        );
+    }
+}
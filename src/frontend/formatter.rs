@@ -0,0 +1,607 @@
+//! Reprints programs in the canonical Lox style.
+//!
+//! The formatter walks the `SyntaxTree`, so comments survive formatting:
+//! they are written next to the token they were attached to. Blocks are
+//! indented by four spaces, binary operators are surrounded by spaces and
+//! argument, parameter, list and map literals that don't fit in the line
+//! width are broken into one item per line.
+
+use crate::frontend::cst::{SyntaxElement, SyntaxKind, SyntaxNode, SyntaxToken, SyntaxTree};
+use crate::frontend::cst::{Trivia, TriviaKind};
+use crate::result::Result;
+
+pub const DEFAULT_WIDTH: usize = 80;
+
+const INDENT: &str = "    ";
+
+/// Formats `source`, breaking lines longer than `width` where possible
+pub fn format(source: &str, width: usize) -> Result<String> {
+    let tree = SyntaxTree::parse(source)?;
+    let mut formatter = Formatter::new(width);
+    formatter.program(&tree.root);
+    Ok(formatter.out)
+}
+
+struct Formatter {
+    out: String,
+    width: usize,
+    indent: usize,
+    at_line_start: bool,
+    /// set after a line comment, nothing else may follow on that line
+    needs_newline: bool,
+}
+
+impl Formatter {
+    fn new(width: usize) -> Formatter {
+        Formatter {
+            out: String::new(),
+            width,
+            indent: 0,
+            at_line_start: true,
+            needs_newline: false,
+        }
+    }
+
+    fn program(&mut self, node: &SyntaxNode) {
+        for child in node.children.iter() {
+            match *child {
+                SyntaxElement::Node(ref stmt) => self.statement(stmt),
+                // EOF, only its comments are left to write
+                SyntaxElement::Token(ref eof) => self.token(eof),
+            }
+        }
+        if !self.at_line_start {
+            self.newline();
+        }
+    }
+
+    fn statement(&mut self, node: &SyntaxNode) {
+        if !self.at_line_start {
+            self.newline();
+        }
+        self.statement_inline(node);
+        self.newline();
+    }
+
+    /// A statement without the line break around it
+    fn statement_inline(&mut self, node: &SyntaxNode) {
+        match node.kind {
+            SyntaxKind::ClassDecl => {
+                let mut members = vec![];
+                for child in node.children.iter() {
+                    match *child {
+                        SyntaxElement::Node(ref method) => members.push(method),
+                        SyntaxElement::Token(ref t) if t.text == "{" => {
+                            self.space();
+                            self.token(t);
+                        }
+                        SyntaxElement::Token(ref t) if t.text == "}" => {
+                            self.body(&members, t);
+                        }
+                        SyntaxElement::Token(ref t) => {
+                            self.space();
+                            self.token(t);
+                        }
+                    }
+                }
+            }
+            SyntaxKind::FunDecl => {
+                // `fun* gen()` but `*method()`
+                let generator = node.child_tokens().any(|t| t.text == "*");
+                let function = node.child_tokens().any(|t| t.text == "fun");
+                for child in node.children.iter() {
+                    match *child {
+                        SyntaxElement::Token(ref t) if t.text == "fun" => {
+                            self.token(t);
+                            if !generator {
+                                self.space();
+                            }
+                        }
+                        SyntaxElement::Token(ref t) if t.text == "*" => {
+                            self.token(t);
+                            if function {
+                                self.space();
+                            }
+                        }
+                        SyntaxElement::Token(ref name) => self.token(name),
+                        SyntaxElement::Node(ref params) if params.kind == SyntaxKind::ParamList => {
+                            self.delimited(params)
+                        }
+                        SyntaxElement::Node(ref body) => {
+                            self.space();
+                            self.statement_inline(body);
+                        }
+                    }
+                }
+            }
+            SyntaxKind::Block => {
+                let (open, close) = match (node.children.first(), node.children.last()) {
+                    (Some(SyntaxElement::Token(open)), Some(SyntaxElement::Token(close))) => {
+                        (open, close)
+                    }
+                    _ => unreachable!("blocks are delimited by braces"),
+                };
+                self.token(open);
+                let statements: Vec<_> = node.child_nodes().collect();
+                self.body(&statements, close);
+            }
+            SyntaxKind::IfStmt
+            | SyntaxKind::WhileStmt
+            | SyntaxKind::ForStmt
+            | SyntaxKind::ForInStmt
+            | SyntaxKind::TryStmt
+            | SyntaxKind::CatchClause => self.control_flow(node),
+            // expression statements, var, print, return and break
+            _ => self.spaced(node),
+        }
+    }
+
+    /// Indented statements or methods followed by the closing brace
+    fn body(&mut self, items: &[&SyntaxNode], close: &SyntaxToken) {
+        self.indent += 1;
+        for item in items {
+            self.statement(item);
+        }
+        let has_comments = close.leading.iter().any(Trivia::is_comment);
+        if has_comments {
+            if !self.at_line_start {
+                self.newline();
+            }
+            self.leading(&close.leading);
+        }
+        self.indent -= 1;
+        if (!items.is_empty() || has_comments) && !self.at_line_start {
+            self.newline();
+        }
+        self.text(&close.text);
+        self.trailing(&close.trailing);
+    }
+
+    /// `if`, `while`, `for` and `try`: keyword, parenthesized header and
+    /// body
+    fn control_flow(&mut self, node: &SyntaxNode) {
+        let mut in_header = false;
+        let mut after_semicolon = false;
+        for child in node.children.iter() {
+            match *child {
+                SyntaxElement::Token(ref t) => match t.text.as_str() {
+                    "(" if !in_header => {
+                        self.space();
+                        self.token(t);
+                        in_header = true;
+                    }
+                    ")" if in_header => {
+                        self.token(t);
+                        in_header = false;
+                    }
+                    ";" => {
+                        self.token(t);
+                        after_semicolon = true;
+                    }
+                    "else" | "finally" => {
+                        self.space();
+                        self.token(t);
+                    }
+                    // the variables of a `for (k, v in m)` loop
+                    "," => {
+                        self.token(t);
+                        self.space();
+                    }
+                    "in" => {
+                        self.space();
+                        self.token(t);
+                        after_semicolon = true;
+                    }
+                    _ => self.token(t),
+                },
+                SyntaxElement::Node(ref n) if in_header => {
+                    if after_semicolon {
+                        self.space();
+                    }
+                    if is_statement(n.kind) {
+                        // the initializer of a `for` loop
+                        self.statement_inline(n);
+                        after_semicolon = true;
+                    } else {
+                        self.expr(n);
+                    }
+                }
+                SyntaxElement::Node(ref n) => {
+                    self.space();
+                    self.statement_inline(n);
+                }
+            }
+        }
+    }
+
+    /// Writes the tokens and expressions of `node` separated by single
+    /// spaces, except before `;` and `,`
+    fn spaced(&mut self, node: &SyntaxNode) {
+        for (i, child) in node.children.iter().enumerate() {
+            match *child {
+                SyntaxElement::Token(ref t) => {
+                    if i > 0 && t.text != ";" && t.text != "," {
+                        self.space();
+                    }
+                    self.token(t);
+                }
+                SyntaxElement::Node(ref n) => {
+                    if i > 0 {
+                        self.space();
+                    }
+                    self.expr(n);
+                }
+            }
+        }
+    }
+
+    fn expr(&mut self, node: &SyntaxNode) {
+        match node.kind {
+            SyntaxKind::CommaExpr
+            | SyntaxKind::AssignExpr
+            | SyntaxKind::ConditionalExpr
+            | SyntaxKind::LogicalExpr
+            | SyntaxKind::BinaryExpr
+            | SyntaxKind::YieldExpr => self.spaced(node),
+            SyntaxKind::MapEntry => {
+                for (i, child) in node.children.iter().enumerate() {
+                    match *child {
+                        SyntaxElement::Token(ref colon) => self.token(colon),
+                        SyntaxElement::Node(ref n) => {
+                            if i > 0 {
+                                self.space();
+                            }
+                            self.expr(n);
+                        }
+                    }
+                }
+            }
+            SyntaxKind::ArgList | SyntaxKind::ListExpr | SyntaxKind::MapExpr => {
+                self.delimited(node)
+            }
+            // unary, call, get, index, grouping, super, interpolation,
+            // literals and names are written without spaces
+            _ => {
+                for child in node.children.iter() {
+                    match *child {
+                        SyntaxElement::Token(ref t) => self.token(t),
+                        SyntaxElement::Node(ref n) => self.expr(n),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Comma separated items between brackets, on one line if they fit
+    fn delimited(&mut self, node: &SyntaxNode) {
+        let children = &node.children;
+        let (open, close) = match (children.first(), children.last()) {
+            (Some(SyntaxElement::Token(open)), Some(SyntaxElement::Token(close))) => (open, close),
+            _ => unreachable!("delimited nodes start and end with a token"),
+        };
+        let mut items: Vec<(&SyntaxElement, Option<&SyntaxToken>)> = vec![];
+        for child in children[1..children.len() - 1].iter() {
+            match *child {
+                SyntaxElement::Token(ref comma) if comma.text == "," => {
+                    if let Some(item) = items.last_mut() {
+                        item.1 = Some(comma);
+                    }
+                }
+                ref item => items.push((item, None)),
+            }
+        }
+        // calls and parameter lists don't allow a trailing comma
+        let trailing_comma = match node.kind {
+            SyntaxKind::ListExpr | SyntaxKind::MapExpr => ",",
+            _ => "",
+        };
+
+        let flat = {
+            let mut flat = Formatter::new(usize::MAX);
+            flat.indent = self.indent;
+            flat.at_line_start = false;
+            flat.delimited_flat(open, &items, close);
+            flat.out
+        };
+        // leave room for the `;` or `,` that usually follows
+        if !flat.contains('\n') && self.column() + flat.chars().count() < self.width {
+            self.delimited_flat(open, &items, close);
+            return;
+        }
+
+        self.token(open);
+        self.indent += 1;
+        for (i, &(item, comma)) in items.iter().enumerate() {
+            self.newline();
+            self.element(item);
+            match comma {
+                Some(comma) if i + 1 < items.len() || !trailing_comma.is_empty() => {
+                    self.token(comma)
+                }
+                Some(comma) => self.comments(comma),
+                None if i + 1 < items.len() => self.text(","),
+                None => self.text(trailing_comma),
+            }
+        }
+        self.leading(&close.leading);
+        self.indent -= 1;
+        self.newline();
+        self.text(&close.text);
+        self.trailing(&close.trailing);
+    }
+
+    fn delimited_flat(
+        &mut self,
+        open: &SyntaxToken,
+        items: &[(&SyntaxElement, Option<&SyntaxToken>)],
+        close: &SyntaxToken,
+    ) {
+        self.token(open);
+        for (i, &(item, comma)) in items.iter().enumerate() {
+            if i > 0 {
+                self.space();
+            }
+            self.element(item);
+            match comma {
+                Some(comma) if i + 1 < items.len() => self.token(comma),
+                Some(comma) => self.comments(comma),
+                None => (),
+            }
+        }
+        self.token(close);
+    }
+
+    fn element(&mut self, element: &SyntaxElement) {
+        match *element {
+            SyntaxElement::Token(ref t) => self.token(t),
+            SyntaxElement::Node(ref n) => self.expr(n),
+        }
+    }
+
+    fn token(&mut self, t: &SyntaxToken) {
+        // `- -a` must not turn into `--a`
+        if let Some(c @ ('-' | '+')) = self.out.chars().last() {
+            if t.text.starts_with(c) {
+                self.space();
+            }
+        }
+        self.leading(&t.leading);
+        self.text(&t.text);
+        self.trailing(&t.trailing);
+    }
+
+    /// Only the comments of a token that is dropped
+    fn comments(&mut self, t: &SyntaxToken) {
+        self.leading(&t.leading);
+        self.trailing(&t.trailing);
+    }
+
+    fn leading(&mut self, trivia: &[Trivia]) {
+        let mut newlines = 0;
+        for (i, t) in trivia.iter().enumerate() {
+            match t.kind {
+                TriviaKind::Newline => newlines += 1,
+                TriviaKind::Whitespace => (),
+                TriviaKind::LineComment | TriviaKind::DocComment => {
+                    self.separate(newlines);
+                    self.text(&t.text);
+                    self.newline();
+                    newlines = 0;
+                }
+                TriviaKind::BlockComment => {
+                    self.separate(newlines);
+                    self.text(&t.text);
+                    let own_line = trivia[i + 1..]
+                        .iter()
+                        .find(|t| t.kind != TriviaKind::Whitespace)
+                        .is_some_and(|t| t.kind == TriviaKind::Newline);
+                    if own_line {
+                        self.newline();
+                    } else {
+                        self.space();
+                    }
+                    newlines = 0;
+                }
+            }
+        }
+        self.separate(newlines);
+    }
+
+    /// Keeps one blank line where the source had any, but not at the
+    /// start of a file or block
+    fn separate(&mut self, newlines: usize) {
+        if newlines < 2 || !self.at_line_start || self.needs_newline {
+            return;
+        }
+        let after_open = self.out.trim_end().ends_with(['{', '(', '[']);
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") && !after_open {
+            self.out.push('\n');
+        }
+    }
+
+    fn trailing(&mut self, trivia: &[Trivia]) {
+        for t in trivia.iter().filter(|t| t.is_comment()) {
+            self.space();
+            self.text(&t.text);
+            if t.kind != TriviaKind::BlockComment {
+                self.needs_newline = true;
+            }
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        if self.needs_newline {
+            self.newline();
+        }
+        if self.at_line_start {
+            for _ in 0..self.indent {
+                self.out.push_str(INDENT);
+            }
+            self.at_line_start = false;
+        }
+        self.out.push_str(text);
+    }
+
+    fn space(&mut self) {
+        if !self.at_line_start && !self.needs_newline && !self.out.ends_with(' ') {
+            self.out.push(' ');
+        }
+    }
+
+    fn newline(&mut self) {
+        let len = self.out.trim_end_matches(' ').len();
+        self.out.truncate(len);
+        if !self.out.is_empty() {
+            self.out.push('\n');
+        }
+        self.at_line_start = true;
+        self.needs_newline = false;
+    }
+
+    fn column(&self) -> usize {
+        if self.at_line_start {
+            return self.indent * INDENT.len();
+        }
+        let line_start = self.out.rfind('\n').map_or(0, |i| i + 1);
+        self.out[line_start..].chars().count()
+    }
+}
+
+fn is_statement(kind: SyntaxKind) -> bool {
+    matches!(kind, SyntaxKind::VarDecl | SyntaxKind::ExprStmt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fmt(source: &str) -> String {
+        format(source, DEFAULT_WIDTH).unwrap()
+    }
+
+    #[test]
+    fn test_canonical_style() {
+        let source = "var  a=1+2*-3 ;
+fun add(a,b){return a+b;}
+fun *gen(a){var x=yield a;x=yield;yield x+1;}
+class Pair<Base{init(a,b){this.a=a;this.b=b;}
+sum(){return this.a+this.b;}
+* both(){yield this.a;}}
+if(a>=1 and !false)print a;else{print\"no\";}
+for(var i=0;i<3;i=i+1){if(i==1)break;}
+for(;;){break;}
+for(k,v in{\"a\":1})print k;
+while(a<10)a=a+1;
+print [1,2,3][0]+{\"k\":[ ]}.len();
+print \"sum: ${add( 1,2 )}\";
+try{throw  Error(\"x\");}catch(e){print e;}finally{}
+{}
+";
+        let expected = "var a = 1 + 2 * -3;
+fun add(a, b) {
+    return a + b;
+}
+fun* gen(a) {
+    var x = yield a;
+    x = yield;
+    yield x + 1;
+}
+class Pair < Base {
+    init(a, b) {
+        this.a = a;
+        this.b = b;
+    }
+    sum() {
+        return this.a + this.b;
+    }
+    *both() {
+        yield this.a;
+    }
+}
+if (a >= 1 and !false) print a; else {
+    print \"no\";
+}
+for (var i = 0; i < 3; i = i + 1) {
+    if (i == 1) break;
+}
+for (;;) {
+    break;
+}
+for (k, v in {\"a\": 1}) print k;
+while (a < 10) a = a + 1;
+print [1, 2, 3][0] + {\"k\": []}.len();
+print \"sum: ${add(1, 2)}\";
+try {
+    throw Error(\"x\");
+} catch (e) {
+    print e;
+} finally {}
+{}
+";
+        assert_eq!(fmt(source), expected);
+        assert_eq!(fmt(expected), expected);
+        // negations stay apart from decrements
+        assert_eq!(fmt("print - -a+ ++b;x --;"), "print - -a + ++b;\nx--;\n");
+    }
+
+    #[test]
+    fn test_preserves_comments() {
+        let source = "// header
+
+/// Says hi.
+fun hi( ) { // opening
+    /* before */ print 1;
+
+
+    print 2; // after
+    // last
+}
+var x = [1, // one
+  2];
+// trailing
+";
+        let expected = "// header
+
+/// Says hi.
+fun hi() { // opening
+    /* before */ print 1;
+
+    print 2; // after
+    // last
+}
+var x = [
+    1, // one
+    2,
+];
+// trailing
+";
+        assert_eq!(fmt(source), expected);
+        assert_eq!(fmt(expected), expected);
+    }
+
+    #[test]
+    fn test_wraps_long_calls() {
+        let source = "print compute(first_argument, second_argument, third_argument, fourth_argument, [1, 2]);";
+        let expected = "print compute(
+    first_argument,
+    second_argument,
+    third_argument,
+    fourth_argument,
+    [1, 2]
+);
+";
+        assert_eq!(fmt(source), expected);
+        assert_eq!(fmt(expected), expected);
+        assert_eq!(format(source, 100).unwrap(), format!("{}\n", source));
+    }
+
+    #[test]
+    fn test_empty_and_invalid() {
+        assert_eq!(fmt(""), "");
+        assert_eq!(fmt("\n\n"), "");
+        assert!(format("print 1", DEFAULT_WIDTH).is_err());
+    }
+}
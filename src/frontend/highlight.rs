@@ -0,0 +1,272 @@
+//! Syntax highlighting for terminals and web pages.
+//!
+//! Tokens are classified by their `TokenType`; the text the `Scanner`
+//! skips between them is split into whitespace and comments. Programs
+//! don't need to parse to be highlighted, only to scan.
+
+use crate::frontend::cst::{lex_trivia, TriviaKind};
+use crate::frontend::scanner::Scanner;
+use crate::frontend::token::TokenType;
+use crate::result::Result;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Highlight {
+    Keyword,
+    String,
+    Number,
+    /// `true`, `false` and `nil`
+    Constant,
+    Operator,
+    Punctuation,
+    Identifier,
+    Comment,
+    Whitespace,
+}
+
+impl Highlight {
+    pub fn of(tok_type: TokenType) -> Highlight {
+        match tok_type {
+            TokenType::LeftParen
+            | TokenType::RightParen
+            | TokenType::LeftBrace
+            | TokenType::RightBrace
+            | TokenType::LeftBracket
+            | TokenType::RightBracket
+            | TokenType::COLON
+            | TokenType::COMMA
+            | TokenType::DOT
+            | TokenType::SEMICOLON => Highlight::Punctuation,
+            TokenType::MINUS
+            | TokenType::PLUS
+            | TokenType::SLASH
+            | TokenType::STAR
+            | TokenType::StarStar
+            | TokenType::PERCENT
+            | TokenType::TildeSlash
+            | TokenType::AMPERSAND
+            | TokenType::PIPE
+            | TokenType::CARET
+            | TokenType::TILDE
+            | TokenType::GreaterGreater
+            | TokenType::LessLess
+            | TokenType::BANG
+            | TokenType::BangEqual
+            | TokenType::EQUAL
+            | TokenType::PlusEqual
+            | TokenType::MinusEqual
+            | TokenType::StarEqual
+            | TokenType::SlashEqual
+            | TokenType::PercentEqual
+            | TokenType::PlusPlus
+            | TokenType::MinusMinus
+            | TokenType::EqualEqual
+            | TokenType::GREATER
+            | TokenType::GreaterEqual
+            | TokenType::LESS
+            | TokenType::LessEqual
+            | TokenType::QUESTION => Highlight::Operator,
+            TokenType::IDENTIFIER => Highlight::Identifier,
+            TokenType::STRING | TokenType::INTERPOLATION => Highlight::String,
+            TokenType::NUMBER => Highlight::Number,
+            TokenType::DocComment => Highlight::Comment,
+            TokenType::FALSE | TokenType::NIL | TokenType::TRUE => Highlight::Constant,
+            TokenType::AND
+            | TokenType::AS
+            | TokenType::BREAK
+            | TokenType::CATCH
+            | TokenType::CLASS
+            | TokenType::ELSE
+            | TokenType::FINALLY
+            | TokenType::FUN
+            | TokenType::FOR
+            | TokenType::FROM
+            | TokenType::IF
+            | TokenType::IMPORT
+            | TokenType::IN
+            | TokenType::OR
+            | TokenType::PRINT
+            | TokenType::RETURN
+            | TokenType::SUPER
+            | TokenType::THIS
+            | TokenType::THROW
+            | TokenType::TRY
+            | TokenType::VAR
+            | TokenType::WHILE
+            | TokenType::YIELD => Highlight::Keyword,
+            TokenType::EOF => Highlight::Whitespace,
+        }
+    }
+
+    /// CSS class of the HTML output
+    pub fn css_class(self) -> &'static str {
+        match self {
+            Highlight::Keyword => "kw",
+            Highlight::String => "str",
+            Highlight::Number => "num",
+            Highlight::Constant => "const",
+            Highlight::Operator => "op",
+            Highlight::Punctuation => "punct",
+            Highlight::Identifier => "ident",
+            Highlight::Comment => "comment",
+            Highlight::Whitespace => "",
+        }
+    }
+
+    /// SGR escape code of the ANSI output, empty for the default colour
+    fn ansi(self) -> &'static str {
+        match self {
+            Highlight::Keyword => "\x1b[35m",
+            Highlight::String => "\x1b[32m",
+            Highlight::Number | Highlight::Constant => "\x1b[33m",
+            Highlight::Operator => "\x1b[36m",
+            Highlight::Comment => "\x1b[90m",
+            Highlight::Punctuation | Highlight::Identifier | Highlight::Whitespace => "",
+        }
+    }
+}
+
+const ANSI_RESET: &str = "\x1b[0m";
+
+const CSS: &str = "pre.lox { background: #fafafa; color: #383a42; padding: 1em; }
+.lox .kw { color: #a626a4; font-weight: bold; }
+.lox .str { color: #50a14f; }
+.lox .num, .lox .const { color: #986801; }
+.lox .op { color: #0184bc; }
+.lox .comment { color: #a0a1a7; font-style: italic; }";
+
+/// Splits `source` into highlighted pieces that together are the source
+pub fn highlight(source: &str) -> Result<Vec<(Highlight, &str)>> {
+    let tokens = Scanner::new(source).scan_tokens()?;
+    let mut pieces = vec![];
+    let mut end = 0;
+    for t in tokens.iter() {
+        gap(&source[end..t.span.start], &mut pieces);
+        if t.span.end > t.span.start {
+            pieces.push((Highlight::of(t.tok_type), &source[t.span.start..t.span.end]));
+        }
+        end = t.span.end;
+    }
+    gap(&source[end..], &mut pieces);
+    Ok(pieces)
+}
+
+fn gap<'a>(text: &'a str, pieces: &mut Vec<(Highlight, &'a str)>) {
+    let mut start = 0;
+    for trivia in lex_trivia(text) {
+        let len = trivia.text.len();
+        let highlight = match trivia.kind {
+            TriviaKind::Whitespace | TriviaKind::Newline => Highlight::Whitespace,
+            TriviaKind::LineComment | TriviaKind::BlockComment | TriviaKind::DocComment => {
+                Highlight::Comment
+            }
+        };
+        pieces.push((highlight, &text[start..start + len]));
+        start += len;
+    }
+}
+
+/// Source with ANSI colour escapes for terminals
+pub fn to_ansi(source: &str) -> Result<String> {
+    let mut out = String::with_capacity(source.len() * 2);
+    for (highlight, text) in highlight(source)? {
+        let code = highlight.ansi();
+        if code.is_empty() {
+            out.push_str(text);
+        } else {
+            out.push_str(code);
+            out.push_str(text);
+            out.push_str(ANSI_RESET);
+        }
+    }
+    Ok(out)
+}
+
+/// A standalone HTML page, tokens are `<span>`s with a CSS class
+pub fn to_html(source: &str) -> Result<String> {
+    let mut code = String::with_capacity(source.len() * 2);
+    for (highlight, text) in highlight(source)? {
+        let class = highlight.css_class();
+        if class.is_empty() {
+            code.push_str(&escape_html(text));
+        } else {
+            code.push_str(&format!(
+                "<span class=\"{}\">{}</span>",
+                class,
+                escape_html(text)
+            ));
+        }
+    }
+    Ok(format!(
+        "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<style>
+{}
+</style>
+</head>
+<body>
+<pre class=\"lox\"><code>{}</code></pre>
+</body>
+</html>
+",
+        CSS, code
+    ))
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_highlight() {
+        let source = "/// doc\nvar s = \"a${1}\"; // note\nif (!nil) print s <= 2;";
+        let pieces = highlight(source).unwrap();
+        let joined: String = pieces.iter().map(|p| p.1).collect();
+        assert_eq!(joined, source);
+
+        let of = |text: &str| pieces.iter().find(|p| p.1 == text).unwrap().0;
+        assert_eq!(of("/// doc"), Highlight::Comment);
+        assert_eq!(of("var"), Highlight::Keyword);
+        assert_eq!(of("\"a${"), Highlight::String);
+        assert_eq!(of("1"), Highlight::Number);
+        assert_eq!(of("}\""), Highlight::String);
+        assert_eq!(of("// note"), Highlight::Comment);
+        assert_eq!(of("nil"), Highlight::Constant);
+        assert_eq!(of("<="), Highlight::Operator);
+        assert_eq!(of("s"), Highlight::Identifier);
+        assert_eq!(of(";"), Highlight::Punctuation);
+    }
+
+    #[test]
+    fn test_outputs() {
+        let ansi = to_ansi("print 1 < x;").unwrap();
+        assert_eq!(
+            ansi,
+            "\x1b[35mprint\x1b[0m \x1b[33m1\x1b[0m \x1b[36m<\x1b[0m x;"
+        );
+
+        let html = to_html("print \"<&>\";").unwrap();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains(
+            "<code><span class=\"kw\">print</span> \
+             <span class=\"str\">&quot;&lt;&amp;&gt;&quot;</span>\
+             <span class=\"punct\">;</span></code>"
+        ));
+        assert!(to_html("print \"open").is_err());
+    }
+}
@@ -1,3 +1,5 @@
+#![allow(non_upper_case_globals)]
+
 use super::token::TokenType;
 use std::collections::HashMap;
 
@@ -5,21 +7,31 @@ lazy_static! {
     pub static ref LoxKeywords: HashMap<&'static str, TokenType> = {
         let mut keywords = HashMap::new();
         keywords.insert("and", TokenType::AND);
+        keywords.insert("as", TokenType::AS);
+        keywords.insert("break", TokenType::BREAK);
+        keywords.insert("catch", TokenType::CATCH);
         keywords.insert("class", TokenType::CLASS);
         keywords.insert("else", TokenType::ELSE);
         keywords.insert("false", TokenType::FALSE);
+        keywords.insert("finally", TokenType::FINALLY);
         keywords.insert("for", TokenType::FOR);
         keywords.insert("fun", TokenType::FUN);
+        keywords.insert("from", TokenType::FROM);
         keywords.insert("if", TokenType::IF);
+        keywords.insert("import", TokenType::IMPORT);
+        keywords.insert("in", TokenType::IN);
         keywords.insert("nil", TokenType::NIL);
         keywords.insert("or", TokenType::OR);
         keywords.insert("print", TokenType::PRINT);
         keywords.insert("return", TokenType::RETURN);
         keywords.insert("super", TokenType::SUPER);
         keywords.insert("this", TokenType::THIS);
+        keywords.insert("throw", TokenType::THROW);
         keywords.insert("true", TokenType::TRUE);
+        keywords.insert("try", TokenType::TRY);
         keywords.insert("var", TokenType::VAR);
         keywords.insert("while", TokenType::WHILE);
+        keywords.insert("yield", TokenType::YIELD);
         keywords
     };
 }
@@ -0,0 +1,641 @@
+//! Static analysis warnings.
+//!
+//! Most rules run over the AST. Empty blocks and the inline
+//! `// lox-allow: rule` comments are found on the `SyntaxTree`, as the AST
+//! keeps neither block positions nor comments.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io;
+
+use crate::frontend::ast::expr::{BinaryOperator, Expr};
+use crate::frontend::ast::stmt::{FunctionDecl, Stmt};
+use crate::frontend::cst::{SyntaxKind, SyntaxNode, SyntaxTree, TriviaKind};
+use crate::frontend::token::{Token, TokenType};
+use crate::result::Result;
+
+/// Prefix of the comments that silence rules on their line
+const ALLOW: &str = "lox-allow:";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Rule {
+    UnusedVariable,
+    UnusedParameter,
+    /// statements after `return` or `break`
+    UnreachableCode,
+    Shadowing,
+    AssignmentInCondition,
+    /// `==` or `!=` whose result is known from the operand types
+    LiteralComparison,
+    EmptyBlock,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 7] = [
+        Rule::UnusedVariable,
+        Rule::UnusedParameter,
+        Rule::UnreachableCode,
+        Rule::Shadowing,
+        Rule::AssignmentInCondition,
+        Rule::LiteralComparison,
+        Rule::EmptyBlock,
+    ];
+
+    pub fn id(self) -> &'static str {
+        match self {
+            Rule::UnusedVariable => "unused-variable",
+            Rule::UnusedParameter => "unused-parameter",
+            Rule::UnreachableCode => "unreachable-code",
+            Rule::Shadowing => "shadowing",
+            Rule::AssignmentInCondition => "assignment-in-condition",
+            Rule::LiteralComparison => "literal-comparison",
+            Rule::EmptyBlock => "empty-block",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Rule> {
+        Rule::ALL.iter().cloned().find(|rule| rule.id() == id)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lint {
+    pub rule: Rule,
+    pub line: usize,
+    pub pos: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: warning[{}]: {}",
+            self.line,
+            self.pos,
+            self.rule.id(),
+            self.message
+        )
+    }
+}
+
+/// Which rules are enabled, all of them by default
+#[derive(Debug, Clone, Default)]
+pub struct LintConfig {
+    disabled: HashSet<Rule>,
+}
+
+impl LintConfig {
+    /// Reads a config file of `rule-id = on|off` lines, `#` starts a comment
+    pub fn parse(text: &str) -> Result<LintConfig> {
+        let mut config = LintConfig::default();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let invalid = |msg: String| -> crate::result::Error {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("lint config line {}: {}", i + 1, msg),
+                )
+                .into()
+            };
+            let mut parts = line.splitn(2, '=').map(str::trim);
+            let id = parts.next().unwrap();
+            let rule =
+                Rule::from_id(id).ok_or_else(|| invalid(format!("unknown rule `{}`", id)))?;
+            match parts.next() {
+                Some("on") => config.set(rule, true),
+                Some("off") => config.set(rule, false),
+                _ => return Err(invalid(format!("expect `{} = on` or `{} = off`", id, id))),
+            }
+        }
+        Ok(config)
+    }
+
+    pub fn set(&mut self, rule: Rule, enabled: bool) {
+        if enabled {
+            self.disabled.remove(&rule);
+        } else {
+            self.disabled.insert(rule);
+        }
+    }
+
+    pub fn is_enabled(&self, rule: Rule) -> bool {
+        !self.disabled.contains(&rule)
+    }
+}
+
+/// Warnings for `source`, ordered by position
+pub fn lint(source: &str, config: &LintConfig) -> Result<Vec<Lint>> {
+    let tree = SyntaxTree::parse(source)?;
+
+    let mut linter = Linter::default();
+    linter.push_scope();
+    linter.statements(tree.ast());
+    linter.pop_scope();
+    empty_blocks(&tree.root, None, &mut linter.lints);
+
+    let allowed = inline_allows(&tree);
+    let mut lints: Vec<Lint> = linter
+        .lints
+        .into_iter()
+        .filter(|lint| config.is_enabled(lint.rule))
+        .filter(|lint| {
+            allowed
+                .get(&lint.line)
+                .is_none_or(|rules| !rules.contains(lint.rule.id()))
+        })
+        .collect();
+    lints.sort_by_key(|lint| (lint.line, lint.pos));
+    Ok(lints)
+}
+
+/// Rules named in `// lox-allow: a, b` comments, by the line they apply
+/// to: the comment's own line, or the next one for comments on a line
+/// of their own
+fn inline_allows(tree: &SyntaxTree) -> HashMap<usize, HashSet<String>> {
+    let mut allowed: HashMap<usize, HashSet<String>> = HashMap::new();
+    for t in tree.root.tokens() {
+        for trivia in t.leading.iter().chain(t.trailing.iter()) {
+            if trivia.kind != TriviaKind::LineComment {
+                continue;
+            }
+            let comment = trivia.text.trim_start_matches('/').trim_start();
+            if let Some(rules) = comment.strip_prefix(ALLOW) {
+                allowed.entry(t.token.line).or_default().extend(
+                    rules
+                        .split(|c: char| c == ',' || c.is_whitespace())
+                        .filter(|id| !id.is_empty())
+                        .map(str::to_string),
+                );
+            }
+        }
+    }
+    allowed
+}
+
+/// Blocks without statements or comments; function bodies are left
+/// alone, empty ones are usually stubs
+fn empty_blocks(node: &SyntaxNode, parent: Option<SyntaxKind>, lints: &mut Vec<Lint>) {
+    if node.kind == SyntaxKind::Block
+        && parent != Some(SyntaxKind::FunDecl)
+        && node.child_nodes().next().is_none()
+    {
+        let tokens = node.tokens();
+        let commented = tokens[0].trailing.iter().any(|t| t.is_comment())
+            || tokens[1].leading.iter().any(|t| t.is_comment());
+        if !commented {
+            lints.push(lint_at(
+                &tokens[0].token,
+                Rule::EmptyBlock,
+                "empty block".into(),
+            ));
+        }
+    }
+    for child in node.child_nodes() {
+        empty_blocks(child, Some(node.kind), lints);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum VarKind {
+    Global,
+    Local,
+    Parameter,
+    /// functions, classes and caught errors, never reported as unused
+    Declaration,
+}
+
+#[derive(Debug)]
+struct Var {
+    name: Token,
+    kind: VarKind,
+    used: bool,
+}
+
+#[derive(Debug, Default)]
+struct Linter {
+    scopes: Vec<Vec<Var>>,
+    lints: Vec<Lint>,
+}
+
+impl Linter {
+    fn push_scope(&mut self) {
+        self.scopes.push(vec![]);
+    }
+
+    fn pop_scope(&mut self) {
+        let scope = self.scopes.pop().unwrap_or_default();
+        for var in scope.into_iter().filter(|v| !v.used) {
+            if var.name.val.starts_with('_') {
+                continue;
+            }
+            let (rule, what) = match var.kind {
+                VarKind::Local => (Rule::UnusedVariable, "variable"),
+                VarKind::Parameter => (Rule::UnusedParameter, "parameter"),
+                VarKind::Global | VarKind::Declaration => continue,
+            };
+            let message = format!("unused {} `{}`", what, var.name.val);
+            self.lints.push(lint_at(&var.name, rule, message));
+        }
+    }
+
+    fn declare(&mut self, name: &Token, kind: VarKind) {
+        let kind = match kind {
+            VarKind::Local if self.scopes.len() == 1 => VarKind::Global,
+            kind => kind,
+        };
+        if kind == VarKind::Local || kind == VarKind::Parameter {
+            let (_, outer) = self.scopes.split_last().unwrap();
+            let shadowed = outer
+                .iter()
+                .rev()
+                .flat_map(|scope| scope.iter().rev())
+                .find(|var| var.name.val == name.val);
+            if let Some(shadowed) = shadowed {
+                let message = format!(
+                    "`{}` shadows the variable declared on line {}",
+                    name.val, shadowed.name.line
+                );
+                self.lints.push(lint_at(name, Rule::Shadowing, message));
+            }
+        }
+        self.scopes.last_mut().unwrap().push(Var {
+            name: name.clone(),
+            kind,
+            used: false,
+        });
+    }
+
+    /// Marks the innermost variable called `name` as read
+    fn read(&mut self, name: &Token) {
+        let var = self
+            .scopes
+            .iter_mut()
+            .rev()
+            .flat_map(|scope| scope.iter_mut().rev())
+            .find(|var| var.name.val == name.val);
+        if let Some(var) = var {
+            var.used = true;
+        }
+    }
+
+    fn statements(&mut self, statements: &[Stmt]) {
+        let mut exit: Option<&str> = None;
+        for stmt in statements {
+            if let Some(keyword) = exit.take() {
+                if let Some(t) = stmt_token(stmt) {
+                    let message = format!("unreachable code after `{}`", keyword);
+                    self.lints.push(lint_at(t, Rule::UnreachableCode, message));
+                }
+            }
+            exit = match *stmt {
+                Stmt::Return(..) => Some("return"),
+                Stmt::Break(_) => Some("break"),
+                Stmt::Throw(..) => Some("throw"),
+                _ => None,
+            };
+            self.stmt(stmt);
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match *stmt {
+            Stmt::Expression(ref expr) | Stmt::Print(ref expr) => self.expr(expr),
+            Stmt::Var(ref name, ref initializer) => {
+                if let Some(ref initializer) = *initializer {
+                    self.expr(initializer);
+                }
+                self.declare(name, VarKind::Local);
+            }
+            Stmt::Block(ref statements) => {
+                self.push_scope();
+                self.statements(statements);
+                self.pop_scope();
+            }
+            Stmt::If(ref stmt) => {
+                self.condition(&stmt.condition);
+                self.stmt(&stmt.then_branch);
+                if let Some(ref else_branch) = stmt.else_branch {
+                    self.stmt(else_branch);
+                }
+            }
+            Stmt::While(ref stmt) => {
+                self.condition(&stmt.condition);
+                self.stmt(&stmt.body);
+            }
+            Stmt::ForIn(ref stmt) => {
+                self.expr(&stmt.iterable);
+                self.push_scope();
+                // loops often only need one of the two variables
+                for name in stmt.variables.iter() {
+                    self.declare(name, VarKind::Declaration);
+                }
+                self.stmt(&stmt.body);
+                self.pop_scope();
+            }
+            Stmt::Function(ref decl) => {
+                self.declare(&decl.name, VarKind::Declaration);
+                self.function(decl);
+            }
+            Stmt::Return(_, ref value) => {
+                if let Some(ref value) = *value {
+                    self.expr(value);
+                }
+            }
+            Stmt::Class(ref class) => {
+                self.declare(&class.name, VarKind::Declaration);
+                if let Some(ref superclass) = class.superclass {
+                    self.expr(superclass);
+                }
+                for method in class.methods.iter() {
+                    self.function(method);
+                }
+            }
+            Stmt::Break(_) => (),
+            Stmt::Throw(_, ref value) => self.expr(value),
+            Stmt::Import(ref import) => {
+                for name in import.alias.iter().chain(import.names.iter()) {
+                    self.declare(name, VarKind::Local);
+                }
+            }
+            Stmt::Try(ref stmt) => {
+                self.push_scope();
+                self.statements(&stmt.body);
+                self.pop_scope();
+                if let Some((ref name, ref handler)) = stmt.catch {
+                    self.push_scope();
+                    self.declare(name, VarKind::Declaration);
+                    self.statements(handler);
+                    self.pop_scope();
+                }
+                if let Some(ref finally) = stmt.finally {
+                    self.push_scope();
+                    self.statements(finally);
+                    self.pop_scope();
+                }
+            }
+        }
+    }
+
+    fn function(&mut self, decl: &FunctionDecl) {
+        self.push_scope();
+        for param in decl.params.iter() {
+            self.declare(param, VarKind::Parameter);
+        }
+        self.statements(&decl.body);
+        self.pop_scope();
+    }
+
+    fn condition(&mut self, condition: &Expr) {
+        if let Some(t) = assignment(condition) {
+            let message = "assignment used as a condition, did you mean `==`?".to_string();
+            self.lints
+                .push(lint_at(t, Rule::AssignmentInCondition, message));
+        }
+        self.expr(condition);
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match *expr {
+            Expr::BinaryExpr(ref binary) => {
+                self.comparison(&binary.op, &binary.lhs, &binary.rhs);
+                self.expr(&binary.lhs);
+                self.expr(&binary.rhs);
+            }
+            Expr::Logical(ref binary) | Expr::Comma(ref binary) => {
+                self.expr(&binary.lhs);
+                self.expr(&binary.rhs);
+            }
+            Expr::Conditional(ref conditional) => {
+                self.condition(&conditional.condition);
+                self.expr(&conditional.then_branch);
+                self.expr(&conditional.else_branch);
+            }
+            Expr::Unary(_, ref e) | Expr::Grouping(ref e) | Expr::Get(ref e, _) => self.expr(e),
+            Expr::Variable(ref name) => self.read(name),
+            Expr::Assign(_, ref value) => self.expr(value),
+            Expr::CompoundAssign(ref assign) => {
+                self.expr(&assign.target);
+                self.expr(&assign.value);
+            }
+            Expr::Increment(ref increment) => self.expr(&increment.target),
+            Expr::Call(ref call) => {
+                self.expr(&call.callee);
+                call.args.iter().for_each(|arg| self.expr(arg));
+            }
+            Expr::Set(ref set) => {
+                self.expr(&set.object);
+                self.expr(&set.value);
+            }
+            Expr::Index(ref index) => {
+                self.expr(&index.object);
+                self.expr(&index.index);
+            }
+            Expr::SetIndex(ref set) => {
+                self.expr(&set.object);
+                self.expr(&set.index);
+                self.expr(&set.value);
+            }
+            Expr::List(_, ref exprs) | Expr::Interpolation(_, ref exprs) => {
+                exprs.iter().for_each(|e| self.expr(e))
+            }
+            Expr::Map(_, ref entries) => {
+                for (key, value) in entries.iter() {
+                    self.expr(key);
+                    self.expr(value);
+                }
+            }
+            Expr::Yield(_, ref value) => {
+                if let Some(ref value) = *value {
+                    self.expr(value);
+                }
+            }
+            Expr::Noop | Expr::Literal(_) | Expr::This(_) | Expr::Super(..) => (),
+        }
+    }
+
+    /// `==` and `!=` whose result doesn't depend on the values
+    fn comparison(&mut self, op: &Token, lhs: &Expr, rhs: &Expr) {
+        let result = match BinaryOperator::from_token_type(op.tok_type) {
+            Some(BinaryOperator::Equal) => "false",
+            Some(BinaryOperator::BangEqual) => "true",
+            _ => return,
+        };
+        let message = match (literal_type(lhs), literal_type(rhs)) {
+            (Some(t), _) | (_, Some(t)) if t == "list" || t == "map" => format!(
+                "a new {} literal is never equal to another value, this is always {}",
+                t, result
+            ),
+            (Some(l), Some(r)) if l != r => {
+                format!("comparing {} with {} is always {}", l, r, result)
+            }
+            _ => return,
+        };
+        self.lints
+            .push(lint_at(op, Rule::LiteralComparison, message));
+    }
+}
+
+fn lint_at(t: &Token, rule: Rule, message: String) -> Lint {
+    Lint {
+        rule,
+        line: t.line,
+        pos: t.pos,
+        message,
+    }
+}
+
+/// The token of an assignment at the top of a condition
+fn assignment(expr: &Expr) -> Option<&Token> {
+    match *expr {
+        Expr::Assign(ref name, _) => Some(name),
+        Expr::Set(ref set) => Some(&set.name),
+        Expr::SetIndex(ref set) => Some(&set.bracket),
+        Expr::CompoundAssign(ref assign) => Some(&assign.op),
+        Expr::Grouping(ref e) => assignment(e),
+        Expr::Logical(ref binary) => assignment(&binary.lhs).or_else(|| assignment(&binary.rhs)),
+        _ => None,
+    }
+}
+
+/// The type of a literal, nothing for expressions only known at runtime
+fn literal_type(expr: &Expr) -> Option<&'static str> {
+    match *expr {
+        Expr::Literal(ref t) => match t.tok_type {
+            TokenType::NIL => Some("nil"),
+            TokenType::TRUE | TokenType::FALSE => Some("bool"),
+            TokenType::NUMBER => Some("number"),
+            TokenType::STRING => Some("string"),
+            _ => None,
+        },
+        Expr::Interpolation(..) => Some("string"),
+        Expr::List(..) => Some("list"),
+        Expr::Map(..) => Some("map"),
+        Expr::Grouping(ref e) => literal_type(e),
+        _ => None,
+    }
+}
+
+/// A token to report a statement at
+fn stmt_token(stmt: &Stmt) -> Option<&Token> {
+    match *stmt {
+        Stmt::Expression(ref e) | Stmt::Print(ref e) => expr_token(e),
+        Stmt::Var(ref name, _) => Some(name),
+        Stmt::Block(ref statements) => statements.first().and_then(stmt_token),
+        Stmt::If(ref stmt) => expr_token(&stmt.condition),
+        Stmt::While(ref stmt) => expr_token(&stmt.condition),
+        Stmt::ForIn(ref stmt) => stmt.variables.first(),
+        Stmt::Function(ref decl) => Some(&decl.name),
+        Stmt::Return(ref keyword, _) | Stmt::Break(ref keyword) | Stmt::Throw(ref keyword, _) => {
+            Some(keyword)
+        }
+        Stmt::Try(ref stmt) => stmt.body.first().and_then(stmt_token),
+        Stmt::Import(ref import) => Some(&import.keyword),
+        Stmt::Class(ref class) => Some(&class.name),
+    }
+}
+
+/// The leftmost token of an expression
+fn expr_token(expr: &Expr) -> Option<&Token> {
+    match *expr {
+        Expr::Noop => None,
+        Expr::BinaryExpr(ref b) | Expr::Logical(ref b) | Expr::Comma(ref b) => {
+            expr_token(&b.lhs)
+        }
+        Expr::Conditional(ref c) => expr_token(&c.condition),
+        Expr::Grouping(ref e) | Expr::Get(ref e, _) => expr_token(e),
+        Expr::Call(ref call) => expr_token(&call.callee),
+        Expr::Set(ref set) => expr_token(&set.object),
+        Expr::Index(ref index) => expr_token(&index.object),
+        Expr::SetIndex(ref set) => expr_token(&set.object),
+        Expr::CompoundAssign(ref assign) => expr_token(&assign.target),
+        Expr::Increment(ref increment) if increment.prefix => Some(&increment.op),
+        Expr::Increment(ref increment) => expr_token(&increment.target),
+        Expr::Unary(ref t, _)
+        | Expr::Literal(ref t)
+        | Expr::Variable(ref t)
+        | Expr::Assign(ref t, _)
+        | Expr::This(ref t)
+        | Expr::Super(ref t, _)
+        | Expr::List(ref t, _)
+        | Expr::Map(ref t, _)
+        | Expr::Interpolation(ref t, _)
+        | Expr::Yield(ref t, _) => Some(t),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(source: &str) -> Vec<(Rule, usize)> {
+        lint(source, &LintConfig::default())
+            .unwrap()
+            .into_iter()
+            .map(|lint| (lint.rule, lint.line))
+            .collect()
+    }
+
+    #[test]
+    fn test_unused_and_shadowing() {
+        let source = "var g = 1;
+fun f(a, unused, _ignored) {
+    var local = a;
+    var g = 2;
+    print g;
+    fun inner() { return local; }
+    return inner;
+}";
+        assert_eq!(
+            rules(source),
+            vec![(Rule::UnusedParameter, 2), (Rule::Shadowing, 4)]
+        );
+    }
+
+    #[test]
+    fn test_unreachable_and_conditions() {
+        let source = "fun f(x) {
+    while (x = 1) { break; print x; }
+    if (x == \"1\" or x != []) return x;
+    return nil;
+    print x;
+}
+try { throw x; print x; } catch (e) {}
+if (true) {}
+if (false) { /* todo */ }";
+        assert_eq!(
+            rules(source),
+            vec![
+                (Rule::AssignmentInCondition, 2),
+                (Rule::UnreachableCode, 2),
+                (Rule::LiteralComparison, 3),
+                (Rule::UnreachableCode, 5),
+                (Rule::UnreachableCode, 7),
+                (Rule::EmptyBlock, 7),
+                (Rule::EmptyBlock, 8),
+            ]
+        );
+        // only literals of known types are compared
+        assert!(rules("print 1 == 1; print \"a\" == \"${1}\";").is_empty());
+        assert_eq!(rules("print nil != 0;"), vec![(Rule::LiteralComparison, 1)]);
+    }
+
+    #[test]
+    fn test_config_and_inline_allows() {
+        let source = "{
+    var a = 1; // lox-allow: unused-variable
+    // lox-allow: unused-variable, shadowing
+    var b = 2;
+    var c = 3;
+}";
+        assert_eq!(rules(source), vec![(Rule::UnusedVariable, 5)]);
+
+        let config = LintConfig::parse("# quiet\nunused-variable = off\n").unwrap();
+        assert!(lint(source, &config).unwrap().is_empty());
+        assert!(LintConfig::parse("no-such-rule = off").is_err());
+        assert!(LintConfig::parse("shadowing = maybe").is_err());
+    }
+}
@@ -1,5 +1,9 @@
 pub mod ast;
+pub mod cst;
+pub mod formatter;
+pub mod highlight;
 pub mod keywords;
+pub mod lint;
 pub mod parser;
 pub mod scanner;
 pub mod token;
@@ -1,38 +1,764 @@
-use crate::frontend::ast::expr::{BinaryExpr, Boxer, Expr};
+use std::collections::HashMap;
+use std::mem;
+use std::rc::Rc;
+
+use crate::frontend::ast::expr::{
+    BinaryExpr, Boxer, CallExpr, CompoundAssignExpr, ConditionalExpr, Expr, IncrementExpr,
+    IndexExpr, SetExpr, SetIndexExpr,
+};
+use crate::frontend::ast::stmt::{
+    ClassDecl, ForInStmt, FunctionDecl, IfStmt, ImportStmt, Stmt, TryStmt, WhileStmt,
+};
+use crate::frontend::cst::{Event, SyntaxKind};
 use crate::frontend::token::{Token, TokenType};
 use crate::result::{Error, Result};
 
+/// Lox functions accept at most this many arguments
+const MAX_ARGS: usize = 255;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Assoc {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OperatorKind {
+    Binary,
+    /// `and` / `or`, which short-circuit
+    Logical,
+    /// `?`, followed by the branches
+    Conditional,
+}
+
+/// An infix operator and how tightly it binds its operands
+#[derive(Debug, Clone, Copy)]
+struct Operator {
+    tok_type: TokenType,
+    power: u8,
+    assoc: Assoc,
+    kind: OperatorKind,
+}
+
+impl Operator {
+    const fn new(tok_type: TokenType, power: u8, assoc: Assoc, kind: OperatorKind) -> Operator {
+        Operator {
+            tok_type,
+            power,
+            assoc,
+            kind,
+        }
+    }
+
+    fn of(tok_type: TokenType) -> Option<Operator> {
+        OPERATORS.iter().find(|op| op.tok_type == tok_type).cloned()
+    }
+}
+
+/// Infix operators from the loosest to the tightest binding
+const OPERATORS: &[Operator] = &[
+    Operator::new(TokenType::QUESTION, 1, Assoc::Right, OperatorKind::Conditional),
+    Operator::new(TokenType::OR, 2, Assoc::Left, OperatorKind::Logical),
+    Operator::new(TokenType::AND, 3, Assoc::Left, OperatorKind::Logical),
+    Operator::new(TokenType::EqualEqual, 4, Assoc::Left, OperatorKind::Binary),
+    Operator::new(TokenType::BangEqual, 4, Assoc::Left, OperatorKind::Binary),
+    Operator::new(TokenType::GREATER, 5, Assoc::Left, OperatorKind::Binary),
+    Operator::new(TokenType::GreaterEqual, 5, Assoc::Left, OperatorKind::Binary),
+    Operator::new(TokenType::LESS, 5, Assoc::Left, OperatorKind::Binary),
+    Operator::new(TokenType::LessEqual, 5, Assoc::Left, OperatorKind::Binary),
+    Operator::new(TokenType::PIPE, 6, Assoc::Left, OperatorKind::Binary),
+    Operator::new(TokenType::CARET, 7, Assoc::Left, OperatorKind::Binary),
+    Operator::new(TokenType::AMPERSAND, 8, Assoc::Left, OperatorKind::Binary),
+    Operator::new(TokenType::LessLess, 9, Assoc::Left, OperatorKind::Binary),
+    Operator::new(TokenType::GreaterGreater, 9, Assoc::Left, OperatorKind::Binary),
+    Operator::new(TokenType::PLUS, 10, Assoc::Left, OperatorKind::Binary),
+    Operator::new(TokenType::MINUS, 10, Assoc::Left, OperatorKind::Binary),
+    Operator::new(TokenType::STAR, 11, Assoc::Left, OperatorKind::Binary),
+    Operator::new(TokenType::SLASH, 11, Assoc::Left, OperatorKind::Binary),
+    Operator::new(TokenType::PERCENT, 11, Assoc::Left, OperatorKind::Binary),
+    Operator::new(TokenType::TildeSlash, 11, Assoc::Left, OperatorKind::Binary),
+    // binds tighter than a prefix operator on its left, `-2 ** 2` is -4
+    Operator::new(TokenType::StarStar, 13, Assoc::Right, OperatorKind::Binary),
+];
+
+/// Operators of `target op= value`, e.g. `+=`
+const COMPOUND_ASSIGNMENTS: [TokenType; 5] = [
+    TokenType::PlusEqual,
+    TokenType::MinusEqual,
+    TokenType::StarEqual,
+    TokenType::SlashEqual,
+    TokenType::PercentEqual,
+];
+
+/// Tokens after a `yield` that has no value, as in `x = yield;`
+const YIELD_ENDS: [TokenType; 7] = [
+    TokenType::SEMICOLON,
+    TokenType::COMMA,
+    TokenType::COLON,
+    TokenType::RightParen,
+    TokenType::RightBracket,
+    TokenType::RightBrace,
+    TokenType::EOF,
+];
+
+/// Prefix and postfix `++` and `--`
+const INCREMENTS: [TokenType; 2] = [TokenType::PlusPlus, TokenType::MinusMinus];
+
+/// Operands of prefix `!`, `-` and `~` only take operators binding
+/// tighter than this
+const UNARY_POWER: u8 = 12;
+
 #[derive(Debug)]
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
+    loop_depth: usize,
+    function_depth: usize,
+    /// whether the innermost function is a generator
+    in_generator: bool,
+    // doc comments, keyed by the index of the token following them
+    docs: HashMap<usize, String>,
+    // the shape of the parse, used to build a `SyntaxTree`
+    events: Vec<Event>,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Parser {
-        Parser { tokens, current: 0 }
+        let mut docs = HashMap::new();
+        let mut doc_lines: Vec<String> = vec![];
+        let mut kept = Vec::with_capacity(tokens.len());
+        for t in tokens {
+            if t.tok_type == TokenType::DocComment {
+                doc_lines.push(t.val);
+                continue;
+            }
+            if !doc_lines.is_empty() {
+                docs.insert(kept.len(), doc_lines.join("\n"));
+                doc_lines.clear();
+            }
+            kept.push(t);
+        }
+
+        Parser {
+            tokens: kept,
+            current: 0,
+            loop_depth: 0,
+            function_depth: 0,
+            in_generator: false,
+            docs,
+            events: vec![],
+        }
+    }
+
+    /// The tokens and events of a finished parse
+    pub fn into_events(self) -> (Vec<Token>, Vec<Event>) {
+        (self.tokens, self.events)
+    }
+
+    /// program → declaration* EOF ;
+    pub fn parse(&mut self) -> Result<Vec<Stmt>> {
+        let start = self.checkpoint();
+        let mut statements = vec![];
+        while !self.is_at_end() {
+            statements.push(self.declaration()?);
+        }
+        if self.peek().is_some() {
+            self.events.push(Event::Token(self.current));
+        }
+        self.wrap(start, SyntaxKind::Program);
+        Ok(statements)
+    }
+
+    /// declaration → classDecl | funDecl | varDecl | importStmt | statement ;
+    fn declaration(&mut self) -> Result<Stmt> {
+        let doc = self.doc_comment();
+        let start = self.checkpoint();
+        if self.match_type(&[TokenType::CLASS]) {
+            let class = self.class_declaration(doc)?;
+            self.wrap(start, SyntaxKind::ClassDecl);
+            return Ok(class);
+        }
+        if self.match_type(&[TokenType::FUN]) {
+            let mut function = self.function("function")?;
+            function.doc = doc;
+            self.wrap(start, SyntaxKind::FunDecl);
+            return Ok(Stmt::Function(Rc::new(function)));
+        }
+        if self.match_type(&[TokenType::VAR]) {
+            let var = self.var_declaration()?;
+            self.wrap(start, SyntaxKind::VarDecl);
+            return Ok(var);
+        }
+        if self.match_type(&[TokenType::IMPORT, TokenType::FROM]) {
+            let import = self.import_statement()?;
+            self.wrap(start, SyntaxKind::ImportStmt);
+            return Ok(import);
+        }
+        self.statement()
+    }
+
+    /// importStmt → "import" STRING "as" IDENTIFIER ";"
+    ///            | "from" STRING "import" IDENTIFIER ( "," IDENTIFIER )* ";" ;
+    fn import_statement(&mut self) -> Result<Stmt> {
+        let keyword = self.previous().unwrap().clone();
+        let path = self.expect_next(&[TokenType::STRING], "expect module path")?;
+        let (alias, names) = if keyword.tok_type == TokenType::IMPORT {
+            self.expect_next(&[TokenType::AS], "expect 'as' after module path")?;
+            let alias = self.expect_next(&[TokenType::IDENTIFIER], "expect module name")?;
+            (Some(alias), vec![])
+        } else {
+            self.expect_next(&[TokenType::IMPORT], "expect 'import' after module path")?;
+            let mut names = vec![];
+            loop {
+                names.push(self.expect_next(&[TokenType::IDENTIFIER], "expect imported name")?);
+                if !self.match_type(&[TokenType::COMMA]) {
+                    break;
+                }
+            }
+            (None, names)
+        };
+        self.expect_next(&[TokenType::SEMICOLON], "expect ';' after import")?;
+        Ok(Stmt::Import(ImportStmt::new(keyword, path, alias, names).boxed()))
+    }
+
+    /// classDecl → "class" IDENTIFIER ( "<" IDENTIFIER )? "{" function* "}" ;
+    fn class_declaration(&mut self, doc: Option<String>) -> Result<Stmt> {
+        let name = self.expect_next(&[TokenType::IDENTIFIER], "expect class name")?;
+
+        let superclass = if self.match_type(&[TokenType::LESS]) {
+            let name = self.expect_next(&[TokenType::IDENTIFIER], "expect superclass name")?;
+            Some(Expr::Variable(name))
+        } else {
+            None
+        };
+
+        self.expect_next(&[TokenType::LeftBrace], "expect '{' before class body")?;
+        let mut methods = vec![];
+        while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
+            let doc = self.doc_comment();
+            let start = self.checkpoint();
+            let mut method = self.function("method")?;
+            method.doc = doc;
+            self.wrap(start, SyntaxKind::FunDecl);
+            methods.push(Rc::new(method));
+        }
+        self.expect_next(&[TokenType::RightBrace], "expect '}' after class body")?;
+
+        let mut class = ClassDecl::new(name, superclass, methods);
+        class.doc = doc;
+        Ok(Stmt::Class(class.boxed()))
+    }
+
+    /// The `///` comment right before the current token, if any
+    fn doc_comment(&self) -> Option<String> {
+        self.docs.get(&self.current).cloned()
+    }
+
+    /// function → "*"? IDENTIFIER "(" parameters? ")" block ;
+    ///
+    /// a `*` before the name makes a generator
+    fn function(&mut self, kind: &str) -> Result<FunctionDecl> {
+        let generator = self.match_type(&[TokenType::STAR]);
+        let name = self.expect_next(&[TokenType::IDENTIFIER], &format!("expect {} name", kind))?;
+        let start = self.checkpoint();
+        self.expect_next(
+            &[TokenType::LeftParen],
+            &format!("expect '(' after {} name", kind),
+        )?;
+
+        let mut params = vec![];
+        if !self.check(&TokenType::RightParen) {
+            loop {
+                if params.len() >= MAX_ARGS {
+                    return Err(self.error_at_peek("too many parameters"));
+                }
+                params.push(self.expect_next(&[TokenType::IDENTIFIER], "expect parameter name")?);
+                if !self.match_type(&[TokenType::COMMA]) {
+                    break;
+                }
+            }
+        }
+        self.expect_next(&[TokenType::RightParen], "expect ')' after parameters")?;
+        self.wrap(start, SyntaxKind::ParamList);
+        let start = self.checkpoint();
+        self.expect_next(
+            &[TokenType::LeftBrace],
+            &format!("expect '{{' before {} body", kind),
+        )?;
+
+        // loops do not extend into nested functions
+        let loop_depth = self.loop_depth;
+        self.loop_depth = 0;
+        self.function_depth += 1;
+        let in_generator = mem::replace(&mut self.in_generator, generator);
+        let body = self.block();
+        self.in_generator = in_generator;
+        self.function_depth -= 1;
+        self.loop_depth = loop_depth;
+        let body = body?;
+        self.wrap(start, SyntaxKind::Block);
+        let mut function = FunctionDecl::new(name, params, body);
+        function.generator = generator;
+        Ok(function)
+    }
+
+    /// varDecl → "var" IDENTIFIER ( "=" assignment )? ";" ;
+    ///
+    /// the initializer stops at a comma, so `var x = 1, y = 2;` is an error
+    /// rather than setting the global `y`
+    fn var_declaration(&mut self) -> Result<Stmt> {
+        let name = self.expect_next(&[TokenType::IDENTIFIER], "expect variable name")?;
+        let initializer = if self.match_type(&[TokenType::EQUAL]) {
+            Some(self.assignment()?)
+        } else {
+            None
+        };
+        self.expect_next(
+            &[TokenType::SEMICOLON],
+            "expect ';' after variable declaration",
+        )?;
+        Ok(Stmt::Var(name, initializer))
+    }
+
+    /// statement → exprStmt | forStmt | forInStmt | ifStmt | printStmt
+    ///           | returnStmt | whileStmt | breakStmt | throwStmt | tryStmt
+    ///           | block ;
+    fn statement(&mut self) -> Result<Stmt> {
+        let start = self.checkpoint();
+        let (stmt, kind) = if self.match_type(&[TokenType::FOR]) {
+            if self.looks_like_for_in() {
+                (self.for_in_statement()?, SyntaxKind::ForInStmt)
+            } else {
+                (self.for_statement()?, SyntaxKind::ForStmt)
+            }
+        } else if self.match_type(&[TokenType::IF]) {
+            (self.if_statement()?, SyntaxKind::IfStmt)
+        } else if self.match_type(&[TokenType::PRINT]) {
+            let value = self.expressions()?;
+            self.expect_next(&[TokenType::SEMICOLON], "expect ';' after value")?;
+            (Stmt::Print(value), SyntaxKind::PrintStmt)
+        } else if self.match_type(&[TokenType::RETURN]) {
+            (self.return_statement()?, SyntaxKind::ReturnStmt)
+        } else if self.match_type(&[TokenType::WHILE]) {
+            (self.while_statement()?, SyntaxKind::WhileStmt)
+        } else if self.match_type(&[TokenType::BREAK]) {
+            (self.break_statement()?, SyntaxKind::BreakStmt)
+        } else if self.match_type(&[TokenType::THROW]) {
+            let keyword = self.previous().unwrap().clone();
+            let value = self.expressions()?;
+            self.expect_next(&[TokenType::SEMICOLON], "expect ';' after thrown value")?;
+            (Stmt::Throw(keyword, value), SyntaxKind::ThrowStmt)
+        } else if self.match_type(&[TokenType::TRY]) {
+            (self.try_statement()?, SyntaxKind::TryStmt)
+        } else if self.check(&TokenType::LeftBrace) && !self.looks_like_map() {
+            self.advance();
+            (Stmt::Block(self.block()?), SyntaxKind::Block)
+        } else {
+            return self.expression_statement();
+        };
+        self.wrap(start, kind);
+        Ok(stmt)
+    }
+
+    /// forStmt → "for" "(" ( varDecl | exprStmt | ";" )
+    ///           expression? ";" expression? ")" statement ;
+    ///
+    /// desugared into a `while` loop wrapped in a block
+    fn for_statement(&mut self) -> Result<Stmt> {
+        self.expect_next(&[TokenType::LeftParen], "expect '(' after 'for'")?;
+
+        let initializer = if self.match_type(&[TokenType::SEMICOLON]) {
+            None
+        } else if self.check(&TokenType::VAR) {
+            let start = self.checkpoint();
+            self.advance();
+            let var = self.var_declaration()?;
+            self.wrap(start, SyntaxKind::VarDecl);
+            Some(var)
+        } else {
+            Some(self.expression_statement()?)
+        };
+
+        let condition = if self.check(&TokenType::SEMICOLON) {
+            None
+        } else {
+            Some(self.expressions()?)
+        };
+        let semicolon =
+            self.expect_next(&[TokenType::SEMICOLON], "expect ';' after loop condition")?;
+
+        let increment = if self.check(&TokenType::RightParen) {
+            None
+        } else {
+            Some(self.expressions()?)
+        };
+        self.expect_next(&[TokenType::RightParen], "expect ')' after for clauses")?;
+
+        let mut body = self.loop_body()?;
+
+        if let Some(increment) = increment {
+            body = Stmt::Block(vec![body, Stmt::Expression(increment)]);
+        }
+        let condition = condition.unwrap_or_else(|| {
+            Expr::Literal(Token::new(
+                TokenType::TRUE,
+                semicolon.line,
+                "true".to_string(),
+                semicolon.pos,
+            ))
+        });
+        body = Stmt::While(WhileStmt::new(condition, body).boxed());
+
+        if let Some(initializer) = initializer {
+            body = Stmt::Block(vec![initializer, body]);
+        }
+        Ok(body)
+    }
+
+    /// Whether the `for` just matched is followed by `(x in` or `(k, v in`
+    fn looks_like_for_in(&self) -> bool {
+        let nth = |n: usize| self.tokens.get(self.current + n).map(|t| t.tok_type);
+        nth(0) == Some(TokenType::LeftParen)
+            && nth(1) == Some(TokenType::IDENTIFIER)
+            && (nth(2) == Some(TokenType::IN)
+                || nth(2) == Some(TokenType::COMMA)
+                    && nth(3) == Some(TokenType::IDENTIFIER)
+                    && nth(4) == Some(TokenType::IN))
+    }
+
+    /// forInStmt → "for" "(" IDENTIFIER ( "," IDENTIFIER )? "in" expression ")"
+    ///             statement ;
+    fn for_in_statement(&mut self) -> Result<Stmt> {
+        self.expect_next(&[TokenType::LeftParen], "expect '(' after 'for'")?;
+        let mut variables = vec![self.expect_next(&[TokenType::IDENTIFIER], "expect variable name")?];
+        if self.match_type(&[TokenType::COMMA]) {
+            variables.push(self.expect_next(&[TokenType::IDENTIFIER], "expect variable name")?);
+        }
+        let keyword = self.expect_next(&[TokenType::IN], "expect 'in' after loop variables")?;
+        let iterable = self.expressions()?;
+        self.expect_next(&[TokenType::RightParen], "expect ')' after iterated value")?;
+        let body = self.loop_body()?;
+        Ok(Stmt::ForIn(
+            ForInStmt::new(variables, keyword, iterable, body).boxed(),
+        ))
+    }
+
+    /// ifStmt → "if" "(" expression ")" statement ( "else" statement )? ;
+    fn if_statement(&mut self) -> Result<Stmt> {
+        self.expect_next(&[TokenType::LeftParen], "expect '(' after 'if'")?;
+        let condition = self.expressions()?;
+        self.expect_next(&[TokenType::RightParen], "expect ')' after if condition")?;
+
+        let then_branch = self.statement()?;
+        let else_branch = if self.match_type(&[TokenType::ELSE]) {
+            Some(self.statement()?)
+        } else {
+            None
+        };
+        Ok(Stmt::If(
+            IfStmt::new(condition, then_branch, else_branch).boxed(),
+        ))
+    }
+
+    /// returnStmt → "return" expression? ";" ;
+    fn return_statement(&mut self) -> Result<Stmt> {
+        let keyword = self.previous().unwrap().clone();
+        if self.function_depth == 0 {
+            return Err(self.error_at(&keyword, "can't return from top-level code"));
+        }
+        let value = if self.check(&TokenType::SEMICOLON) {
+            None
+        } else {
+            Some(self.expressions()?)
+        };
+        self.expect_next(&[TokenType::SEMICOLON], "expect ';' after return value")?;
+        Ok(Stmt::Return(keyword, value))
+    }
+
+    /// whileStmt → "while" "(" expression ")" statement ;
+    fn while_statement(&mut self) -> Result<Stmt> {
+        self.expect_next(&[TokenType::LeftParen], "expect '(' after 'while'")?;
+        let condition = self.expressions()?;
+        self.expect_next(&[TokenType::RightParen], "expect ')' after condition")?;
+        let body = self.loop_body()?;
+        Ok(Stmt::While(WhileStmt::new(condition, body).boxed()))
+    }
+
+    fn loop_body(&mut self) -> Result<Stmt> {
+        self.loop_depth += 1;
+        let body = self.statement();
+        self.loop_depth -= 1;
+        body
+    }
+
+    /// breakStmt → "break" ";" ;
+    fn break_statement(&mut self) -> Result<Stmt> {
+        let keyword = self.previous().unwrap().clone();
+        if self.loop_depth == 0 {
+            return Err(self.error_at(&keyword, "can't use 'break' outside of a loop"));
+        }
+        self.expect_next(&[TokenType::SEMICOLON], "expect ';' after 'break'")?;
+        Ok(Stmt::Break(keyword))
+    }
+
+    /// tryStmt → "try" block ( "catch" "(" IDENTIFIER ")" block )?
+    ///           ( "finally" block )? ;
+    ///
+    /// with at least one of `catch` and `finally`
+    fn try_statement(&mut self) -> Result<Stmt> {
+        let keyword = self.previous().unwrap().clone();
+        let body = self.wrapped_block("try")?;
+
+        let start = self.checkpoint();
+        let catch = if self.match_type(&[TokenType::CATCH]) {
+            self.expect_next(&[TokenType::LeftParen], "expect '(' after 'catch'")?;
+            let name = self.expect_next(&[TokenType::IDENTIFIER], "expect error name")?;
+            self.expect_next(&[TokenType::RightParen], "expect ')' after error name")?;
+            let handler = self.wrapped_block("catch")?;
+            self.wrap(start, SyntaxKind::CatchClause);
+            Some((name, handler))
+        } else {
+            None
+        };
+
+        let finally = if self.match_type(&[TokenType::FINALLY]) {
+            Some(self.wrapped_block("finally")?)
+        } else {
+            None
+        };
+        if catch.is_none() && finally.is_none() {
+            return Err(self.error_at(&keyword, "expect 'catch' or 'finally' after 'try' block"));
+        }
+        Ok(Stmt::Try(TryStmt::new(body, catch, finally).boxed()))
+    }
+
+    /// A block that must follow the keyword `after`
+    fn wrapped_block(&mut self, after: &str) -> Result<Vec<Stmt>> {
+        let start = self.checkpoint();
+        self.expect_next(
+            &[TokenType::LeftBrace],
+            &format!("expect '{{' after '{}'", after),
+        )?;
+        let statements = self.block()?;
+        self.wrap(start, SyntaxKind::Block);
+        Ok(statements)
     }
 
+    /// block → "{" declaration* "}" ;
+    fn block(&mut self) -> Result<Vec<Stmt>> {
+        let mut statements = vec![];
+        while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
+            statements.push(self.declaration()?);
+        }
+        self.expect_next(&[TokenType::RightBrace], "expect '}' after block")?;
+        Ok(statements)
+    }
+
+    /// A `{` in statement position opens a block unless a `:` outside any
+    /// brackets follows it before the first statement ends, e.g.
+    /// `{"a" + "b": 1}.len();`. The `:` of a `? :` conditional doesn't count.
+    fn looks_like_map(&self) -> bool {
+        let mut depth = 0;
+        let mut conditionals = 0;
+        for t in self.tokens[self.current + 1..].iter() {
+            match t.tok_type {
+                TokenType::LeftParen | TokenType::LeftBracket | TokenType::LeftBrace => {
+                    depth += 1
+                }
+                TokenType::RightParen | TokenType::RightBracket | TokenType::RightBrace
+                    if depth > 0 =>
+                {
+                    depth -= 1
+                }
+                _ if depth > 0 => (),
+                TokenType::QUESTION => conditionals += 1,
+                TokenType::COLON if conditionals > 0 => conditionals -= 1,
+                TokenType::COLON => return true,
+                TokenType::SEMICOLON
+                | TokenType::RightParen
+                | TokenType::RightBracket
+                | TokenType::RightBrace
+                | TokenType::EOF => return false,
+                _ => (),
+            }
+        }
+        false
+    }
+
+    /// exprStmt → expression ";" ;
+    fn expression_statement(&mut self) -> Result<Stmt> {
+        let start = self.checkpoint();
+        let expr = self.expressions()?;
+        self.expect_next(&[TokenType::SEMICOLON], "expect ';' after expression")?;
+        self.wrap(start, SyntaxKind::ExprStmt);
+        Ok(Stmt::Expression(expr))
+    }
+
+    /// expression → assignment ( "," assignment )* ;
     fn expressions(&mut self) -> Result<Expr> {
-        self.equality()
+        let mut start = self.checkpoint();
+        let mut expr = self.assignment()?;
+        while self.match_type(&[TokenType::COMMA]) {
+            let comma = self.previous().unwrap().clone();
+            let right = self.assignment()?;
+            start = self.wrap(start, SyntaxKind::CommaExpr);
+            expr = Expr::Comma(BinaryExpr::new(expr.boxed(), comma, right.boxed()).boxed());
+        }
+        Ok(expr)
+    }
+
+    /// assignment → ( call "." )? IDENTIFIER assign_op assignment
+    ///            | call "[" expression "]" assign_op assignment
+    ///            | yield | binary ;
+    /// assign_op  → "=" | "+=" | "-=" | "*=" | "/=" | "%=" ;
+    ///
+    /// an operand of the comma operator, arguments and elements of list
+    /// and map literals are assignments
+    fn assignment(&mut self) -> Result<Expr> {
+        if self.check(&TokenType::YIELD) {
+            return self.yield_expr();
+        }
+        let start = self.checkpoint();
+        let expr = self.binary(0)?;
+
+        if self.match_type(&[TokenType::EQUAL]) {
+            let equals = self.previous().unwrap().clone();
+            let value = self.assignment()?.boxed();
+            self.wrap(start, SyntaxKind::AssignExpr);
+
+            return match expr {
+                Expr::Variable(name) => Ok(Expr::Assign(name, value)),
+                Expr::Get(object, name) => Ok(Expr::Set(SetExpr::new(object, name, value).boxed())),
+                Expr::Index(index) => {
+                    let IndexExpr {
+                        object,
+                        bracket,
+                        index,
+                    } = *index;
+                    Ok(Expr::SetIndex(
+                        SetIndexExpr::new(object, bracket, index, value).boxed(),
+                    ))
+                }
+                _ => Err(self.error_at(&equals, "invalid assignment target")),
+            };
+        }
+        if self.match_type(&COMPOUND_ASSIGNMENTS) {
+            let op = self.previous().unwrap().clone();
+            let value = self.assignment()?;
+            self.wrap(start, SyntaxKind::AssignExpr);
+
+            return match expr {
+                Expr::Variable(_) | Expr::Get(..) | Expr::Index(_) => Ok(Expr::CompoundAssign(
+                    CompoundAssignExpr::new(expr, op, value).boxed(),
+                )),
+                _ => Err(self.error_at(&op, "invalid assignment target")),
+            };
+        }
+        Ok(expr)
     }
 
-    /// equality ->  comparison ( "=" | "==") comparison
-    fn equality(&mut self) -> Result<Expr> {
-        let mut expr = self.comparison()?;
+    /// yield → "yield" assignment? ;
+    ///
+    /// binds like an assignment, so it needs parentheses to be an operand
+    /// of another operator, as in `1 + (yield)`
+    fn yield_expr(&mut self) -> Result<Expr> {
+        let start = self.checkpoint();
+        let keyword = self.advance().unwrap().clone();
+        if !self.in_generator {
+            return Err(self.error_at(&keyword, "can't use 'yield' outside of a generator"));
+        }
+        let value = match self.peek().map(|t| t.tok_type) {
+            Some(t) if !YIELD_ENDS.contains(&t) => Some(self.assignment()?.boxed()),
+            _ => None,
+        };
+        self.wrap(start, SyntaxKind::YieldExpr);
+        Ok(Expr::Yield(keyword, value))
+    }
 
-        let types = vec![TokenType::BangEqual, TokenType::EqualEqual];
+    /// binary → unary ( OPERATOR unary | "?" expression ":" ( binary | yield ) )* ;
+    ///
+    /// Parses operators binding tighter than `min_power`, by precedence
+    /// climbing over `OPERATORS`
+    fn binary(&mut self, min_power: u8) -> Result<Expr> {
+        let mut start = self.checkpoint();
+        let mut expr = self.unary()?;
+        while let Some(operator) = self.peek().and_then(|t| Operator::of(t.tok_type)) {
+            if operator.power < min_power {
+                break;
+            }
+            let op = self.advance().unwrap().clone();
+            let then_branch = match operator.kind {
+                OperatorKind::Conditional => {
+                    let then_branch = self.expressions()?;
+                    self.expect_next(&[TokenType::COLON], "expect ':' after then branch")?;
+                    Some(then_branch)
+                }
+                _ => None,
+            };
+            let right = match operator.assoc {
+                // the else branch can be a `yield` like the then branch
+                _ if then_branch.is_some() && self.check(&TokenType::YIELD) => self.yield_expr()?,
+                Assoc::Left => self.binary(operator.power + 1)?,
+                Assoc::Right => self.binary(operator.power)?,
+            };
+            expr = match (operator.kind, then_branch) {
+                (OperatorKind::Conditional, Some(then_branch)) => {
+                    start = self.wrap(start, SyntaxKind::ConditionalExpr);
+                    Expr::Conditional(ConditionalExpr::new(expr, op, then_branch, right).boxed())
+                }
+                (OperatorKind::Logical, _) => {
+                    start = self.wrap(start, SyntaxKind::LogicalExpr);
+                    Expr::Logical(BinaryExpr::new(expr.boxed(), op, right.boxed()).boxed())
+                }
+                _ => {
+                    start = self.wrap(start, SyntaxKind::BinaryExpr);
+                    Expr::BinaryExpr(BinaryExpr::new(expr.boxed(), op, right.boxed()).boxed())
+                }
+            };
+        }
+        Ok(expr)
+    }
 
-        while self.match_type(&types) {
+    /// unary → ( "!" | "-" | "~" ) unary | ( "++" | "--" ) call | postfix ;
+    fn unary(&mut self) -> Result<Expr> {
+        let start = self.checkpoint();
+        if self.match_type(&[TokenType::BANG, TokenType::MINUS, TokenType::TILDE]) {
             let op = self.previous().unwrap().clone();
-            let right = self.comparison()?;
-            let binary_expr = BinaryExpr::new(expr.boxed(), op, right.boxed()).boxed();
-            expr = Expr::BinaryExpr(binary_expr)
+            let right = self.binary(UNARY_POWER)?;
+            self.wrap(start, SyntaxKind::UnaryExpr);
+            return Ok(Expr::Unary(op, right.boxed()));
+        }
+        if self.match_type(&INCREMENTS) {
+            let op = self.previous().unwrap().clone();
+            let target = self.call()?;
+            self.wrap(start, SyntaxKind::IncrementExpr);
+            return self.increment(target, op, true);
+        }
+        self.postfix()
+    }
+
+    /// postfix → call ( "++" | "--" )? ;
+    fn postfix(&mut self) -> Result<Expr> {
+        let start = self.checkpoint();
+        let expr = self.call()?;
+        if self.match_type(&INCREMENTS) {
+            let op = self.previous().unwrap().clone();
+            self.wrap(start, SyntaxKind::IncrementExpr);
+            return self.increment(expr, op, false);
         }
         Ok(expr)
     }
 
-    fn match_type(&mut self, types: &Vec<TokenType>) -> bool {
+    fn increment(&self, target: Expr, op: Token, prefix: bool) -> Result<Expr> {
+        match target {
+            Expr::Variable(_) | Expr::Get(..) | Expr::Index(_) => Ok(Expr::Increment(
+                IncrementExpr::new(target, op, prefix).boxed(),
+            )),
+            _ => Err(self.error_at(&op, "invalid increment target")),
+        }
+    }
+
+    fn match_type(&mut self, types: &[TokenType]) -> bool {
         for t in types.iter() {
             if self.check(t) {
                 self.advance();
@@ -42,36 +768,82 @@ impl Parser {
         false
     }
 
-    fn expect_next(&mut self, types: &Vec<TokenType>) -> Result<&Token> {
+    fn expect_next(&mut self, types: &[TokenType], msg: &str) -> Result<Token> {
         if self.match_type(types) {
-            return Ok(self.peek().unwrap());
+            return Ok(self.previous().unwrap().clone());
         }
 
-        Err(self.peek_error())
+        Err(self.error_at_peek(msg))
     }
 
-    fn peek_error(&mut self) -> Error {
+    fn error_at_peek(&mut self, msg: &str) -> Error {
         // peek for EOF and unexpected tokens
         let pk = self.peek().cloned();
 
-        if pk.is_none() {
-            return self.eof();
-        }
-
-        if let Some(tkn) = pk {
-            return self.unexpected(&tkn);
+        match pk {
+            Some(ref tkn) if tkn.tok_type != TokenType::EOF => self.error_at(tkn, msg),
+            _ => self.eof(msg),
         }
-        unreachable!()
     }
 
     fn advance(&mut self) -> Option<&Token> {
         if !self.is_at_end() {
-            self.advance();
+            self.events.push(Event::Token(self.current));
             self.current += 1;
         }
         self.previous()
     }
 
+    /// Where a node may start, a `Start` event that `wrap` fills in
+    fn checkpoint(&mut self) -> usize {
+        self.events.push(Event::Start {
+            kind: None,
+            forward_parent: None,
+        });
+        self.events.len() - 1
+    }
+
+    /// Puts everything parsed since `checkpoint` into a node of `kind`.
+    /// Returns the node's `Start`, which wrapping again is quicker from
+    /// than the checkpoint, having no forward parents to follow.
+    fn wrap(&mut self, checkpoint: usize, kind: SyntaxKind) -> usize {
+        let mut start = checkpoint;
+        loop {
+            match self.events[start] {
+                Event::Start { kind: None, .. } => {
+                    self.events[start] = Event::Start {
+                        kind: Some(kind),
+                        forward_parent: None,
+                    };
+                    break;
+                }
+                Event::Start {
+                    forward_parent: Some(distance),
+                    ..
+                } => start += distance,
+                Event::Start {
+                    kind: Some(child),
+                    forward_parent: None,
+                } => {
+                    let parent = self.events.len();
+                    self.events[start] = Event::Start {
+                        kind: Some(child),
+                        forward_parent: Some(parent - start),
+                    };
+                    self.events.push(Event::Start {
+                        kind: Some(kind),
+                        forward_parent: None,
+                    });
+                    start = parent;
+                    break;
+                }
+                _ => unreachable!("checkpoints are start events"),
+            }
+        }
+        self.events.push(Event::Finish);
+        start
+    }
+
     fn previous(&self) -> Option<&Token> {
         self.tokens.get(self.current - 1)
     }
@@ -84,64 +856,89 @@ impl Parser {
     }
 
     fn is_at_end(&self) -> bool {
-        self.peek().is_none()
+        match self.peek() {
+            Some(t) => t.tok_type == TokenType::EOF,
+            None => true,
+        }
     }
 
     fn peek(&self) -> Option<&Token> {
         self.tokens.get(self.current)
     }
 
-    // comparison → addition ( ( ">" | ">=" | "<" | "<=" ) addition )* ;
-    fn comparison(&mut self) -> Result<Expr> {
-        let types = vec![
-            TokenType::GREATER,
-            TokenType::GreaterEqual,
-            TokenType::LESS,
-            TokenType::LessEqual,
-        ];
-
-        let mut expr = self.addition()?;
-        while self.match_type(&types) {
-            let op = self.previous().unwrap().clone();
-            let right = self.addition()?;
-            let e = BinaryExpr::new(expr.boxed(), op, right.boxed()).boxed();
-            expr = Expr::BinaryExpr(e);
+    /// call → primary ( "(" arguments? ")" | "." IDENTIFIER | "[" expression "]" )* ;
+    fn call(&mut self) -> Result<Expr> {
+        let mut start = self.checkpoint();
+        let mut expr = self.primary()?;
+        loop {
+            let args = self.checkpoint();
+            if self.match_type(&[TokenType::LeftParen]) {
+                expr = self.finish_call(expr, args)?;
+                start = self.wrap(start, SyntaxKind::CallExpr);
+            } else if self.match_type(&[TokenType::DOT]) {
+                let name =
+                    self.expect_next(&[TokenType::IDENTIFIER], "expect property name after '.'")?;
+                expr = Expr::Get(expr.boxed(), name);
+                start = self.wrap(start, SyntaxKind::GetExpr);
+            } else if self.match_type(&[TokenType::LeftBracket]) {
+                let index = self.expressions()?;
+                let bracket =
+                    self.expect_next(&[TokenType::RightBracket], "expect ']' after index")?;
+                expr = Expr::Index(IndexExpr::new(expr.boxed(), bracket, index.boxed()).boxed());
+                start = self.wrap(start, SyntaxKind::IndexExpr);
+            } else {
+                break;
+            }
         }
         Ok(expr)
     }
 
-    fn addition(&mut self) -> Result<Expr> {
-        let mut expr = self.mutilplication()?;
-        let types = vec![TokenType::MINUS, TokenType::PLUS];
-
-        while self.match_type(&types) {
-            let operator = self.previous().unwrap().clone();
-            let right = self.mutilplication()?.boxed();
-            let binary_expr = BinaryExpr::new(expr.boxed(), operator, right).boxed();
-            expr = Expr::BinaryExpr(binary_expr);
+    /// arguments → expression ( "," expression )* ;
+    fn finish_call(&mut self, callee: Expr, args_start: usize) -> Result<Expr> {
+        let mut args = vec![];
+        if !self.check(&TokenType::RightParen) {
+            loop {
+                if args.len() >= MAX_ARGS {
+                    return Err(self.error_at_peek("too many arguments"));
+                }
+                args.push(self.assignment()?);
+                if !self.match_type(&[TokenType::COMMA]) {
+                    break;
+                }
+            }
         }
-        Ok(expr)
+        let paren = self.expect_next(&[TokenType::RightParen], "expect ')' after arguments")?;
+        self.wrap(args_start, SyntaxKind::ArgList);
+        Ok(Expr::Call(
+            CallExpr::new(callee.boxed(), paren, args).boxed(),
+        ))
     }
 
-    fn mutilplication(&mut self) -> Result<Expr> {
-        unimplemented!()
-    }
-
-    /// unary → ( "!" | "-" ) unary
-    ///         | primary ;
-    fn unray(&mut self) -> Result<Expr> {
-        if self.match_type(&vec![TokenType::BANG, TokenType::MINUS]) {
-            let op = self.previous().unwrap().clone();
-            let right = self.unray()?;
-            return Ok(Expr::Unary(op, right.boxed()));
-        }
-        self.primary()
+    /// primary → NUMBER | STRING | "false" | "true" | "nil" | "this"
+    ///         | IDENTIFIER | "super" "." IDENTIFIER
+    ///         | "(" expression ")" | interpolation | list | map ;
+    fn primary(&mut self) -> Result<Expr> {
+        let t = match self.peek() {
+            Some(t) if t.tok_type != TokenType::EOF => t.clone(),
+            _ => return Err(self.eof("expect expression")),
+        };
+        let start = self.checkpoint();
+        let expr = self.primary_expr(t)?;
+        let kind = match expr {
+            Expr::Literal(_) => SyntaxKind::LiteralExpr,
+            Expr::This(_) => SyntaxKind::ThisExpr,
+            Expr::Variable(_) => SyntaxKind::VariableExpr,
+            Expr::Super(..) => SyntaxKind::SuperExpr,
+            Expr::Grouping(_) => SyntaxKind::GroupingExpr,
+            Expr::Interpolation(..) => SyntaxKind::InterpolationExpr,
+            Expr::List(..) => SyntaxKind::ListExpr,
+            _ => SyntaxKind::MapExpr,
+        };
+        self.wrap(start, kind);
+        Ok(expr)
     }
 
-    /// primary → NUMBER | STRING | "false" | "true" | "nil"
-    ///                  | "(" expression ")" ;
-    fn primary(&mut self) -> Result<Expr> {
-        let t = self.peek().unwrap().clone();
+    fn primary_expr(&mut self, t: Token) -> Result<Expr> {
         match t.tok_type {
             TokenType::FALSE
             | TokenType::TRUE
@@ -149,27 +946,370 @@ impl Parser {
             | TokenType::NUMBER
             | TokenType::STRING => {
                 self.advance();
-                Ok(Expr::Literal(t.val.clone()))
+                Ok(Expr::Literal(t))
+            }
+            TokenType::THIS => {
+                self.advance();
+                Ok(Expr::This(t))
+            }
+            TokenType::IDENTIFIER => {
+                self.advance();
+                Ok(Expr::Variable(t))
+            }
+            TokenType::SUPER => {
+                self.advance();
+                self.expect_next(&[TokenType::DOT], "expect '.' after 'super'")?;
+                let method =
+                    self.expect_next(&[TokenType::IDENTIFIER], "expect superclass method name")?;
+                Ok(Expr::Super(t, method))
             }
             TokenType::LeftParen => {
+                self.advance();
                 let expr = self.expressions()?;
-                let _ = self.expect_next(&vec![TokenType::RightParen]);
+                self.expect_next(&[TokenType::RightParen], "expect ')' after expression")?;
                 Ok(Expr::Grouping(expr.boxed()))
             }
-            _ => unreachable!(),
+            TokenType::INTERPOLATION => self.interpolation(t),
+            TokenType::LeftBracket => {
+                self.advance();
+                self.list(t)
+            }
+            TokenType::LeftBrace => {
+                self.advance();
+                self.map(t)
+            }
+            TokenType::YIELD => Err(self.error_at(
+                &t,
+                "'yield' must be in parentheses to be an operand",
+            )),
+            _ => Err(self.unexpected(&t)),
+        }
+    }
+
+    /// interpolation → ( INTERPOLATION expression )+ STRING ;
+    fn interpolation(&mut self, start: Token) -> Result<Expr> {
+        let mut parts = vec![];
+        while self.match_type(&[TokenType::INTERPOLATION]) {
+            let text = self.previous().unwrap().clone();
+            if !text.val.is_empty() {
+                parts.push(Expr::Literal(text));
+            }
+            parts.push(self.expressions()?);
+        }
+        let end = self.expect_next(&[TokenType::STRING], "expect '}' after interpolation")?;
+        if !end.val.is_empty() {
+            parts.push(Expr::Literal(end));
+        }
+        Ok(Expr::Interpolation(start, parts))
+    }
+
+    /// list → "[" ( expression ( "," expression )* ","? )? "]" ;
+    fn list(&mut self, bracket: Token) -> Result<Expr> {
+        let mut elements = vec![];
+        while !self.check(&TokenType::RightBracket) {
+            elements.push(self.assignment()?);
+            if !self.match_type(&[TokenType::COMMA]) {
+                break;
+            }
+        }
+        self.expect_next(&[TokenType::RightBracket], "expect ']' after list elements")?;
+        Ok(Expr::List(bracket, elements))
+    }
+
+    /// map → "{" ( entry ( "," entry )* ","? )? "}" ;
+    /// entry → expression ":" expression ;
+    fn map(&mut self, brace: Token) -> Result<Expr> {
+        let mut entries = vec![];
+        while !self.check(&TokenType::RightBrace) {
+            let start = self.checkpoint();
+            let key = self.assignment()?;
+            self.expect_next(&[TokenType::COLON], "expect ':' after map key")?;
+            let value = self.assignment()?;
+            self.wrap(start, SyntaxKind::MapEntry);
+            entries.push((key, value));
+            if !self.match_type(&[TokenType::COMMA]) {
+                break;
+            }
         }
+        self.expect_next(&[TokenType::RightBrace], "expect '}' after map entries")?;
+        Ok(Expr::Map(brace, entries))
+    }
+
+    fn error_at(&self, t: &Token, msg: &str) -> Error {
+        Error::Parse(t.line as u64, t.pos as u64, msg.to_string(), t.to_string())
     }
 
     fn unexpected(&mut self, t: &Token) -> Error {
-        Error::Parse(
-            t.line as u64,
-            t.pos as u64,
-            "unexpected token".to_string(),
-            t.to_string(),
-        )
+        self.error_at(t, "unexpected token")
+    }
+
+    fn eof(&self, msg: &str) -> Error {
+        let line = self.tokens.last().map_or(0, |t| t.line as u64);
+        Error::Parse(line, 0, msg.to_string(), "unexpected EOF".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::scanner::Scanner;
+
+    fn parse(src: &str) -> Result<Vec<Stmt>> {
+        Parser::new(Scanner::new(src).scan_tokens()?).parse()
+    }
+
+    /// Parenthesized form of an expression statement's operators
+    fn grouped(src: &str) -> String {
+        fn show(expr: &Expr) -> String {
+            match *expr {
+                Expr::BinaryExpr(ref b) | Expr::Logical(ref b) | Expr::Comma(ref b) => {
+                    format!("({} {} {})", show(&b.lhs), b.op.val, show(&b.rhs))
+                }
+                Expr::Conditional(ref c) => format!(
+                    "({} ? {} : {})",
+                    show(&c.condition),
+                    show(&c.then_branch),
+                    show(&c.else_branch)
+                ),
+                Expr::Assign(ref name, ref value) => format!("({} = {})", name.val, show(value)),
+                Expr::Unary(ref op, ref e) => format!("({}{})", op.val, show(e)),
+                Expr::Grouping(ref e) => show(e),
+                Expr::Literal(ref t) | Expr::Variable(ref t) => t.val.clone(),
+                ref e => panic!("unexpected expression {:?}", e),
+            }
+        }
+        match parse(src).unwrap()[0] {
+            Stmt::Expression(ref e) => show(e),
+            ref s => panic!("expected an expression, got {:?}", s),
+        }
+    }
+
+    #[test]
+    fn test_operator_precedence() {
+        assert_eq!(grouped("1 - 2 - 3;"), "((1 - 2) - 3)");
+        assert_eq!(grouped("a + b * c / d;"), "(a + ((b * c) / d))");
+        assert_eq!(grouped("-a * -b;"), "((-a) * (-b))");
+        assert_eq!(grouped("!a == b;"), "((!a) == b)");
+        assert_eq!(
+            grouped("a or b and c == d < e + f;"),
+            "(a or (b and (c == (d < (e + f)))))"
+        );
+        assert_eq!(grouped("(a or b) and c;"), "((a or b) and c)");
+    }
+
+    #[test]
+    fn test_arithmetic_and_bitwise_precedence() {
+        assert_eq!(grouped("2 ** 3 ** 2;"), "(2 ** (3 ** 2))");
+        assert_eq!(grouped("-2 ** 2;"), "(-(2 ** 2))");
+        assert_eq!(grouped("2 ** -1 * 3;"), "((2 ** (-1)) * 3)");
+        assert_eq!(grouped("a % b ~/ c + d;"), "(((a % b) ~/ c) + d)");
+        assert_eq!(
+            grouped("a | b ^ c & d << e + f;"),
+            "(a | (b ^ (c & (d << (e + f)))))"
+        );
+        assert_eq!(grouped("a & 1 == 0;"), "((a & 1) == 0)");
+        assert_eq!(grouped("~a >> 1;"), "((~a) >> 1)");
+    }
+
+    #[test]
+    fn test_compound_assignment() {
+        match parse("a.b[i] += c = d;").unwrap()[0] {
+            Stmt::Expression(Expr::CompoundAssign(ref assign)) => {
+                assert_eq!(assign.op.tok_type, TokenType::PlusEqual);
+                assert!(matches!(assign.target, Expr::Index(_)));
+                assert!(matches!(assign.value, Expr::Assign(..)));
+            }
+            ref s => panic!("expected a compound assignment, got {:?}", s),
+        }
+        assert!(parse("a + b -= 1;").is_err());
+        assert!(parse("f() %= 1;").is_err());
+    }
+
+    #[test]
+    fn test_increments() {
+        match parse("-a.b[i]++;").unwrap()[0] {
+            Stmt::Expression(Expr::Unary(_, ref e)) => match **e {
+                Expr::Increment(ref increment) => {
+                    assert!(!increment.prefix);
+                    assert_eq!(increment.delta(), 1.0);
+                    assert!(matches!(increment.target, Expr::Index(_)));
+                }
+                ref e => panic!("expected an increment, got {:?}", e),
+            },
+            ref s => panic!("expected a negated increment, got {:?}", s),
+        }
+        match parse("--x ** 2;").unwrap()[0] {
+            Stmt::Expression(Expr::BinaryExpr(ref power)) => match *power.lhs {
+                Expr::Increment(ref increment) => {
+                    assert!(increment.prefix);
+                    assert_eq!(increment.delta(), -1.0);
+                }
+                ref e => panic!("expected an increment, got {:?}", e),
+            },
+            ref s => panic!("expected a power, got {:?}", s),
+        }
+        assert!(parse("f()++;").is_err());
+        assert!(parse("++(a + b);").is_err());
+        assert!(parse("++a++;").is_err());
+    }
+
+    #[test]
+    fn test_conditional_and_comma() {
+        assert_eq!(grouped("a ? b : c ? d : e;"), "(a ? b : (c ? d : e))");
+        assert_eq!(grouped("a ? b ? c : d : e;"), "(a ? (b ? c : d) : e)");
+        assert_eq!(grouped("a or b ? c + 1 : d;"), "((a or b) ? (c + 1) : d)");
+        assert_eq!(grouped("a = b ? c : d;"), "(a = (b ? c : d))");
+        assert_eq!(grouped("a ? b, c : d;"), "(a ? (b , c) : d)");
+        assert_eq!(grouped("a = 1, b = 2, c;"), "(((a = 1) , (b = 2)) , c)");
+        assert!(parse("a ? b;").is_err());
+
+        // a comma doesn't continue a variable's initializer
+        assert!(parse("var x = 1, y = 2;").is_err());
+        assert!(parse("for (var i = 0, j = 1; i < 2;) {}").is_err());
+        match parse("var x = (1, 2);").unwrap()[0] {
+            Stmt::Var(_, Some(Expr::Grouping(ref e))) => assert!(matches!(**e, Expr::Comma(_))),
+            ref s => panic!("expected a variable, got {:?}", s),
+        }
+    }
+
+    #[test]
+    fn test_try_statement() {
+        match parse("try { throw 1; } catch (e) {} finally { print 2; }").unwrap()[0] {
+            Stmt::Try(ref stmt) => {
+                assert!(matches!(stmt.body[0], Stmt::Throw(..)));
+                assert_eq!(stmt.catch.as_ref().unwrap().0.val, "e");
+                assert_eq!(stmt.finally.as_ref().unwrap().len(), 1);
+            }
+            ref s => panic!("expected a try statement, got {:?}", s),
+        }
+        assert!(parse("try {} finally {}").is_ok());
+        assert!(parse("try {}").is_err());
+        assert!(parse("try {} catch {}").is_err());
+        assert!(parse("try print 1; catch (e) {}").is_err());
+    }
+
+    #[test]
+    fn test_for_in_statement() {
+        match parse("for (k, v in m) break;").unwrap()[0] {
+            Stmt::ForIn(ref stmt) => {
+                let names: Vec<&str> = stmt.variables.iter().map(|t| t.val.as_str()).collect();
+                assert_eq!(names, ["k", "v"]);
+                assert!(matches!(stmt.iterable, Expr::Variable(_)));
+                assert!(matches!(stmt.body, Stmt::Break(_)));
+            }
+            ref s => panic!("expected a for-in loop, got {:?}", s),
+        }
+        assert!(matches!(parse("for (x in [1]) {}").unwrap()[0], Stmt::ForIn(_)));
+        // the three-clause loop still takes a comma expression
+        assert!(matches!(parse("for (i, j; i;) {}").unwrap()[0], Stmt::Block(_)));
+        assert!(parse("for (x in) {}").is_err());
+        assert!(parse("for (a, b, c in l) {}").is_err());
     }
 
-    fn eof(&self) -> Error {
-        Error::Parse(0, 0, "".to_string(), "unexpected EOF".to_string())
+    #[test]
+    fn test_generators() {
+        let src = "fun* g() { yield; var x = yield 1; x = yield x, 2; return yield; }";
+        let decl = match parse(src).unwrap()[0] {
+            Stmt::Function(ref decl) => decl.clone(),
+            ref s => panic!("expected a function, got {:?}", s),
+        };
+        assert!(decl.generator);
+        // whether each yield has a value
+        let values: Vec<bool> = decl
+            .body
+            .iter()
+            .map(|stmt| match *stmt {
+                Stmt::Expression(Expr::Comma(ref comma)) => match *comma.lhs {
+                    Expr::Assign(_, ref value) => &**value,
+                    ref e => panic!("expected an assignment, got {:?}", e),
+                },
+                Stmt::Expression(ref e)
+                | Stmt::Var(_, Some(ref e))
+                | Stmt::Return(_, Some(ref e)) => e,
+                ref s => panic!("expected a yield, got {:?}", s),
+            })
+            .map(|e| match *e {
+                Expr::Yield(_, ref value) => value.is_some(),
+                ref e => panic!("expected a yield, got {:?}", e),
+            })
+            .collect();
+        assert_eq!(values, [false, true, true, false]);
+        match parse("class C { *items() {} }").unwrap()[0] {
+            Stmt::Class(ref class) => assert!(class.methods[0].generator),
+            ref s => panic!("expected a class, got {:?}", s),
+        }
+        assert!(parse("yield 1;").is_err());
+        assert!(parse("fun f() { yield 1; }").is_err());
+        assert!(parse("fun* g() { fun f() { yield 1; } }").is_err());
+        for src in &[
+            "print yield 1;",
+            "o.x = yield v;",
+            "a[i] = yield v;",
+            "a[i] += yield;",
+            "f(yield 1, [yield], {yield 2: yield});",
+            "var x = c ? yield 1 : yield 2;",
+            "var x = 1 + (yield 1);",
+        ] {
+            assert!(parse(&format!("fun* g() {{ {} }}", src)).is_ok(), "{}", src);
+        }
+        assert!(parse("fun* g() { var x = 1 + yield 1; }").is_err());
+    }
+
+    #[test]
+    fn test_import_statement() {
+        match parse("import \"lib.lox\" as lib;").unwrap()[0] {
+            Stmt::Import(ref stmt) => {
+                assert_eq!(stmt.path.val, "lib.lox");
+                assert_eq!(stmt.alias.as_ref().unwrap().val, "lib");
+                assert!(stmt.names.is_empty());
+            }
+            ref s => panic!("expected an import, got {:?}", s),
+        }
+        match parse("from \"lib.lox\" import a, b;").unwrap()[0] {
+            Stmt::Import(ref stmt) => {
+                assert!(stmt.alias.is_none());
+                let names: Vec<&str> = stmt.names.iter().map(|t| t.val.as_str()).collect();
+                assert_eq!(names, ["a", "b"]);
+            }
+            ref s => panic!("expected an import, got {:?}", s),
+        }
+        assert!(parse("import \"lib.lox\";").is_err());
+        assert!(parse("import lib as lib;").is_err());
+        assert!(parse("from \"lib.lox\" import;").is_err());
+    }
+
+    #[test]
+    fn test_doc_comments() {
+        let src = "
+            /// Breakfast, the most important meal.
+            /// Serves one.
+            class Breakfast {
+                /// Fries eggs.
+                cook() {}
+                serve(who) { /// ignored
+                    print who;
+                }
+            }
+            /// Not attached to anything but a variable.
+            var a = 1;
+            /// Adds one.
+            fun inc(x) { return x + 1; }
+        ";
+        let statements = parse(src).unwrap();
+        match statements[0] {
+            Stmt::Class(ref class) => {
+                assert_eq!(
+                    class.doc.as_ref().unwrap(),
+                    "Breakfast, the most important meal.\nServes one."
+                );
+                assert_eq!(class.methods[0].doc.as_ref().unwrap(), "Fries eggs.");
+                assert_eq!(class.methods[1].doc, None);
+            }
+            ref s => panic!("expected a class, got {:?}", s),
+        }
+        match statements[2] {
+            Stmt::Function(ref f) => assert_eq!(f.doc.as_ref().unwrap(), "Adds one."),
+            ref s => panic!("expected a function, got {:?}", s),
+        }
     }
 }
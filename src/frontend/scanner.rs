@@ -1,20 +1,59 @@
 use super::keywords::LoxKeywords;
-use super::token::{Token, TokenType};
+use super::token::{Span, Token, TokenType};
+use crate::result::{Error, Result};
 
 #[derive(Debug)]
 pub struct Scanner<'a> {
     _source: &'a str,
     peeks: &'a str,
+    // byte offset of every char in `peeks`, followed by `peeks.len()`
+    offsets: Vec<usize>,
     tokens: Vec<Token>,
     start: usize,
+    start_line: usize,
+    start_pos: usize,
     line: usize,
     inline_offset: usize,
     current: usize,
     count: usize,
+    // `${` of every string interpolation still being scanned, innermost last
+    interpolations: Vec<Interpolation>,
+}
+
+/// An open `${ ... }` inside a string literal
+#[derive(Debug)]
+struct Interpolation {
+    line: usize,
+    pos: usize,
+    // `{` opened inside the interpolation and not closed yet
+    depth: usize,
 }
 
 pub fn is_digit(c: &str) -> bool {
-    c.parse::<f64>().is_ok()
+    is_digit_of(c, 10)
+}
+
+fn is_digit_of(c: &str, radix: u32) -> bool {
+    let mut chars = c.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => c.is_digit(radix),
+        _ => false,
+    }
+}
+
+/// Parses the lexeme of a NUMBER token
+pub fn parse_number(lexeme: &str) -> Option<f64> {
+    let digits = lexeme.replace('_', "");
+    let radix = match digits.get(..2) {
+        Some("0x") => 16,
+        Some("0o") => 8,
+        Some("0b") => 2,
+        _ => return digits.parse().ok(),
+    };
+    digits[2..].chars().try_fold(0.0, |n, c| {
+        c.to_digit(radix)
+            .map(|d| n * f64::from(radix) + f64::from(d))
+    })
 }
 
 pub fn is_alphanumeric(s: &str) -> bool {
@@ -22,55 +61,133 @@ pub fn is_alphanumeric(s: &str) -> bool {
 }
 
 impl<'a> Scanner<'a> {
-    pub fn new(source: &str) -> Scanner {
+    pub fn new(source: &str) -> Scanner<'_> {
+        let mut offsets: Vec<usize> = source.char_indices().map(|(i, _)| i).collect();
+        offsets.push(source.len());
         Scanner {
             _source: source,
             peeks: source,
+            count: offsets.len() - 1,
+            offsets,
             start: 0,
+            start_line: 1,
+            start_pos: 1,
             current: 0,
             line: 1,
             tokens: vec![],
-            count: source.len(),
-            inline_offset: 1,
+            inline_offset: 0,
+            interpolations: vec![],
         }
     }
 
     fn slice(&self, start: usize, end: usize) -> &str {
-        &self.peeks[start..end]
+        &self.peeks[self.offsets[start]..self.offsets[end]]
     }
 
-    fn scan_tokens(&mut self) -> Vec<Token> {
+    pub fn scan_tokens(&mut self) -> Result<Vec<Token>> {
         while !self.is_at_end() {
             self.start = self.current;
+            self.start_line = self.line;
+            self.start_pos = self.inline_offset + 1;
             self.advance();
-            self.scan_token();
+            self.scan_token()?;
         }
+        if let Some(interpolation) = self.interpolations.pop() {
+            return Err(Error::Lexical(
+                interpolation.line as u64,
+                interpolation.pos as u64,
+                "unterminated string interpolation".to_string(),
+                "${".to_string(),
+            ));
+        }
+        self.start = self.current;
         self.create_token(
             TokenType::EOF,
             self.line,
             "\0".to_string(),
-            self.inline_offset,
+            self.inline_offset + 1,
         );
-        self.tokens.clone()
+        Ok(self.tokens.clone())
     }
 
     fn is_at_end(&self) -> bool {
         self.current >= self.count
     }
 
-    fn scan_token(&mut self) {
+    fn scan_token(&mut self) -> Result<()> {
         let c = self.current_char();
         match c {
             "(" => self.add_token(TokenType::LeftParen),
             ")" => self.add_token(TokenType::RightParen),
-            "{" => self.add_token(TokenType::LeftParen),
-            "}" => self.add_token(TokenType::RightParen),
+            "{" => {
+                if let Some(interpolation) = self.interpolations.last_mut() {
+                    interpolation.depth += 1;
+                }
+                self.add_token(TokenType::LeftBrace)
+            }
+            "}" => match self.interpolations.last_mut() {
+                // closes `${`, the rest of the string follows
+                Some(interpolation) if interpolation.depth == 0 => {
+                    self.interpolations.pop();
+                    return self.scan_string();
+                }
+                Some(interpolation) => {
+                    interpolation.depth -= 1;
+                    self.add_token(TokenType::RightBrace)
+                }
+                None => self.add_token(TokenType::RightBrace),
+            },
+            "[" => self.add_token(TokenType::LeftBracket),
+            "]" => self.add_token(TokenType::RightBracket),
+            ":" => self.add_token(TokenType::COLON),
             "," => self.add_token(TokenType::COMMA),
+            "?" => self.add_token(TokenType::QUESTION),
             "." => self.add_token(TokenType::DOT),
-            "-" => self.add_token(TokenType::MINUS),
-            "+" => self.add_token(TokenType::PLUS),
+            "-" => {
+                if self.match_str("=") {
+                    self.add_token(TokenType::MinusEqual)
+                } else if self.match_str("-") {
+                    self.add_token(TokenType::MinusMinus)
+                } else {
+                    self.add_token(TokenType::MINUS)
+                }
+            }
+            "+" => {
+                if self.match_str("=") {
+                    self.add_token(TokenType::PlusEqual)
+                } else if self.match_str("+") {
+                    self.add_token(TokenType::PlusPlus)
+                } else {
+                    self.add_token(TokenType::PLUS)
+                }
+            }
             ";" => self.add_token(TokenType::SEMICOLON),
-            "*" => self.add_token(TokenType::STAR),
+            "%" => {
+                if self.match_str("=") {
+                    self.add_token(TokenType::PercentEqual)
+                } else {
+                    self.add_token(TokenType::PERCENT)
+                }
+            }
+            "&" => self.add_token(TokenType::AMPERSAND),
+            "|" => self.add_token(TokenType::PIPE),
+            "^" => self.add_token(TokenType::CARET),
+            "*" => {
+                if self.match_str("*") {
+                    self.add_token(TokenType::StarStar)
+                } else if self.match_str("=") {
+                    self.add_token(TokenType::StarEqual)
+                } else {
+                    self.add_token(TokenType::STAR)
+                }
+            }
+            "~" => {
+                if self.match_str("/") {
+                    self.add_token(TokenType::TildeSlash)
+                } else {
+                    self.add_token(TokenType::TILDE)
+                }
+            }
             "!" => {
                 if self.match_str("=") {
                     self.add_token(TokenType::BangEqual)
@@ -88,6 +205,8 @@ impl<'a> Scanner<'a> {
             ">" => {
                 if self.match_str("=") {
                     self.add_token(TokenType::GreaterEqual)
+                } else if self.match_str(">") {
+                    self.add_token(TokenType::GreaterGreater)
                 } else {
                     self.add_token(TokenType::GREATER)
                 }
@@ -95,64 +214,234 @@ impl<'a> Scanner<'a> {
             "<" => {
                 if self.match_str("=") {
                     self.add_token(TokenType::LessEqual)
+                } else if self.match_str("<") {
+                    self.add_token(TokenType::LessLess)
                 } else {
-                    self.add_token(TokenType::LessEqual)
+                    self.add_token(TokenType::LESS)
                 }
             }
             "/" => {
                 if self.match_str("/") {
-                    self.scan_comment();
+                    if self.peek_next() == Some("/")
+                        && self.peek(Some(self.current + 2)) != Some("/")
+                    {
+                        self.scan_doc_comment();
+                    } else {
+                        self.scan_comment();
+                    }
+                } else if self.match_str("*") {
+                    return self.scan_block_comment();
+                } else if self.match_str("=") {
+                    self.add_token(TokenType::SlashEqual);
                 } else {
                     self.add_token(TokenType::SLASH);
                 }
             }
             // resolve string literals*
-            "\"" => self.scan_string(),
+            "\"" => {
+                if self.quotes_ahead(2) {
+                    return self.scan_raw_string();
+                }
+                return self.scan_string();
+            }
             // numeric char
-            c if is_digit(c) => self.scan_number(),
+            c if is_digit(c) => return self.scan_number(),
             c if is_alphanumeric(c) => self.scan_identifier(),
             // ignore whitespace
             " " | "\r" | "\t" => (),
             "\n" => self.scan_new_line(),
-            _ => (),
+            c => {
+                let near = c.to_string();
+                return Err(self.error(self.start_pos, "unexpected character", near));
+            }
+        }
+        Ok(())
+    }
+
+    /// STRING → '"' ( <any char except '"' or '\\'> | escape )* '"' ;
+    ///
+    /// A string containing `${` is split into an INTERPOLATION token for
+    /// the text before it, the tokens of the embedded expression, and
+    /// the rest of the string starting after the matching `}`.
+    fn scan_string(&mut self) -> Result<()> {
+        let mut value = String::new();
+        while !self.is_at_end() && self.peek_next().unwrap() != "\"" {
+            self.advance();
+            match self.current_char() {
+                "\n" => {
+                    value.push('\n');
+                    self.scan_new_line();
+                }
+                "\\" => value.push(self.scan_escape()?),
+                "$" => {
+                    if self.match_str("{") {
+                        self.start_interpolation(value);
+                        return Ok(());
+                    }
+                    value.push('$');
+                }
+                c => value.push_str(c),
+            }
+        }
+
+        if self.is_at_end() {
+            return Err(self.error_at_start("unterminated string"));
         }
+
+        // consume the closing '"'
+        self.advance();
+
+        self.create_token(TokenType::STRING, self.start_line, value, self.start_pos);
+        Ok(())
     }
 
-    fn scan_string(&mut self) {
-        // consume the '"'
+    /// escape → "\\" ( "n" | "t" | "r" | "0" | "\"" | "\\" | "$" | "u{" HEX+ "}" ) ;
+    fn scan_escape(&mut self) -> Result<char> {
+        let pos = self.inline_offset;
+        if self.is_at_end() {
+            return Err(self.error_at_start("unterminated string"));
+        }
         self.advance();
+        let c = match self.current_char() {
+            "n" => '\n',
+            "t" => '\t',
+            "r" => '\r',
+            "0" => '\0',
+            "\"" => '"',
+            "\\" => '\\',
+            "$" => '$',
+            "u" => return self.scan_unicode_escape(pos),
+            c => {
+                let near = format!("\\{}", c);
+                return Err(self.error(pos, "invalid escape sequence", near));
+            }
+        };
+        Ok(c)
+    }
 
-        while !self.is_at_end() {
-            let s = self.peek(None).unwrap();
-            if s == "\"" {
-                break;
+    fn start_interpolation(&mut self, value: String) {
+        self.create_token(
+            TokenType::INTERPOLATION,
+            self.start_line,
+            value,
+            self.start_pos,
+        );
+        self.interpolations.push(Interpolation {
+            line: self.line,
+            pos: self.inline_offset - 1,
+            depth: 0,
+        });
+    }
+
+    /// `\u{...}` takes one to six hex digits naming a Unicode scalar value
+    fn scan_unicode_escape(&mut self, pos: usize) -> Result<char> {
+        let escape_start = self.current - 2;
+        let mut digits = String::new();
+        if self.match_str("{") {
+            while digits.len() < 6
+                && self
+                    .peek_next()
+                    .unwrap()
+                    .chars()
+                    .all(|c| c.is_ascii_hexdigit())
+            {
+                digits.push_str(self.advance().unwrap());
+            }
+            if !digits.is_empty() && self.match_str("}") {
+                if let Some(c) = u32::from_str_radix(&digits, 16)
+                    .ok()
+                    .and_then(std::char::from_u32)
+                {
+                    return Ok(c);
+                }
             }
-            if s == "\n" {
+        }
+        let near = self.slice(escape_start, self.current).to_string();
+        Err(self.error(pos, "invalid unicode escape", near))
+    }
+
+    /// RAW_STRING → '"""' <any char>* '"""' ;
+    ///
+    /// Raw strings may span lines and keep their content verbatim.
+    fn scan_raw_string(&mut self) -> Result<()> {
+        // consume the rest of the opening '"""'
+        self.advance();
+        self.advance();
+
+        let content_start = self.current;
+        while !self.quotes_ahead(3) {
+            if self.is_at_end() {
+                return Err(self.error_at_start("unterminated raw string"));
+            }
+            if self.advance() == Some("\n") {
                 self.scan_new_line();
             }
-            self.advance();
         }
+        let value = self.slice(content_start, self.current).to_string();
 
-        if self.is_at_end() {
-            return println!("Unterminated string ");
+        // consume the closing '"""'
+        for _ in 0..3 {
+            self.advance();
         }
 
-        let end = self.current - 1;
-        let start = self.start + 1;
-        let content = self.slice(start, end);
-        self.create_token(
-            TokenType::STRING,
-            self.line,
-            content.to_string(),
-            self.inline_offset,
-        );
+        self.create_token(TokenType::STRING, self.start_line, value, self.start_pos);
+        Ok(())
+    }
+
+    /// Whether the next `n` chars are all '"'
+    fn quotes_ahead(&self, n: usize) -> bool {
+        (1..=n).all(|i| self.peek(Some(self.current + i)) == Some("\""))
+    }
+
+    fn error(&self, pos: usize, msg: &str, near: String) -> Error {
+        Error::Lexical(self.line as u64, pos as u64, msg.to_string(), near)
+    }
+
+    /// Reports an error at the start of the token being scanned
+    fn error_at_start(&self, msg: &str) -> Error {
+        Error::Lexical(
+            self.start_line as u64,
+            self.start_pos as u64,
+            msg.to_string(),
+            self.slice(self.start, self.current).to_string(),
+        )
     }
 
     fn scan_comment(&mut self) {
-        while !self.is_at_end() && self.peek(None).unwrap() != "\n" {
+        while !self.is_at_end() && self.peek_next().unwrap() != "\n" {
+            self.advance();
+        }
+    }
+
+    /// `///` comments are kept as tokens so the parser can attach them
+    /// to the declaration that follows
+    fn scan_doc_comment(&mut self) {
+        // consume the third '/'
+        self.advance();
+        let text_start = self.current;
+        self.scan_comment();
+
+        let text = self.slice(text_start, self.current);
+        let text = text.strip_prefix(' ').unwrap_or(text).to_string();
+        self.create_token(TokenType::DocComment, self.start_line, text, self.start_pos);
+    }
+
+    /// `/* ... */` comments may span lines and nest
+    fn scan_block_comment(&mut self) -> Result<()> {
+        let mut depth = 1;
+        while depth > 0 {
+            if self.is_at_end() {
+                return Err(self.error_at_start("unterminated block comment"));
+            }
             self.advance();
+            match self.current_char().chars().next() {
+                Some('\n') => self.scan_new_line(),
+                Some('/') if self.match_str("*") => depth += 1,
+                Some('*') if self.match_str("/") => depth -= 1,
+                _ => (),
+            }
         }
-        self.scan_new_line();
+        Ok(())
     }
 
     fn scan_new_line(&mut self) {
@@ -160,27 +449,74 @@ impl<'a> Scanner<'a> {
         self.inline_offset = 0;
     }
 
-    fn scan_number(&mut self) {
-        while !self.is_at_end() && is_digit(self.peek_next().unwrap()) {
+    /// NUMBER → DIGITS ( "." DIGITS )? ( ( "e" | "E" ) ( "+" | "-" )? DIGITS )?
+    ///        | "0x" HEX_DIGITS | "0o" OCT_DIGITS | "0b" BIN_DIGITS ;
+    fn scan_number(&mut self) -> Result<()> {
+        let radix = match (self.current_char(), self.peek_next().unwrap()) {
+            ("0", "x") => 16,
+            ("0", "o") => 8,
+            ("0", "b") => 2,
+            _ => 10,
+        };
+
+        if radix == 10 {
+            self.scan_digits(10, true)?;
+            if self.peek_next().unwrap() == "."
+                && is_digit(self.peek(Some(self.current + 2)).unwrap())
+            {
+                // consume "."
+                self.advance();
+                self.scan_digits(10, false)?;
+            }
+            if let "e" | "E" = self.peek_next().unwrap() {
+                self.advance();
+                if let "+" | "-" = self.peek_next().unwrap() {
+                    self.advance();
+                }
+                if !is_digit(self.peek_next().unwrap()) {
+                    return Err(self.error_at_start("missing exponent digits"));
+                }
+                self.scan_digits(10, false)?;
+            }
+        } else {
+            // consume the base prefix
+            self.advance();
+            if !is_digit_of(self.peek_next().unwrap(), radix) {
+                return Err(self.error_at_start("missing digits after base prefix"));
+            }
+            self.scan_digits(radix, false)?;
+        }
+
+        // meet non-numeric char
+        if is_alphanumeric(self.peek_next().unwrap()) {
             self.advance();
+            return Err(self.error_at_start("invalid digit in number literal"));
         }
+        self.add_token(TokenType::NUMBER);
+        Ok(())
+    }
 
-        if !self.is_at_end() && self.match_str(".") && is_digit(self.peek_next().unwrap()) {
-            // consume "."
+    /// Consumes digits of `radix`, optionally separated by single `_`s
+    fn scan_digits(&mut self, radix: u32, first_consumed: bool) -> Result<()> {
+        let mut after_digit = first_consumed;
+        loop {
+            let next = self.peek_next().unwrap();
+            if is_digit_of(next, radix) {
+                after_digit = true;
+            } else if next == "_" && after_digit {
+                after_digit = false;
+            } else {
+                break;
+            }
             self.advance();
-            while is_digit(self.peek_next().unwrap()) {
+        }
+        if !after_digit {
+            if !self.is_at_end() {
                 self.advance();
             }
+            return Err(self.error_at_start("digit separator must be followed by a digit"));
         }
-
-        // meet non-numeric char
-        let value = self.slice(self.start, self.current);
-        self.create_token(
-            TokenType::NUMBER,
-            self.line,
-            value.to_string(),
-            self.inline_offset,
-        );
+        Ok(())
     }
 
     fn scan_identifier(&mut self) {
@@ -189,12 +525,9 @@ impl<'a> Scanner<'a> {
         }
 
         let val = self.slice(self.start, self.current);
-        let keyword_type = LoxKeywords.get(val);
-
-        if keyword_type.is_some() {
-            self.add_token(*keyword_type.unwrap());
-        } else {
-            self.add_token(TokenType::IDENTIFIER);
+        match LoxKeywords.get(val) {
+            Some(keyword_type) => self.add_token(*keyword_type),
+            None => self.add_token(TokenType::IDENTIFIER),
         }
     }
 
@@ -204,10 +537,10 @@ impl<'a> Scanner<'a> {
             return false;
         }
         self.advance();
-        return true;
+        true
     }
 
-    fn current_char(&mut self) -> &str {
+    fn current_char(&self) -> &str {
         self.peek(None).unwrap()
     }
 
@@ -217,33 +550,33 @@ impl<'a> Scanner<'a> {
         self.peek(None)
     }
 
-    fn peek(&mut self, n: Option<usize>) -> Option<&str> {
-        let end = if n.is_some() {
-            n.unwrap()
-        } else {
-            self.current
-        };
-        let start = if end <= 0 { 0 } else { end - 1 };
-        if end >= self.count + 1 {
+    fn peek(&self, n: Option<usize>) -> Option<&str> {
+        let end = n.unwrap_or(self.current);
+        let start = if end == 0 { 0 } else { end - 1 };
+        if end > self.count {
             return Some("\0");
         }
         let c: &str = self.slice(start, end);
-        Some(&c)
+        Some(c)
     }
 
-    fn peek_next(&mut self) -> Option<&str> {
+    fn peek_next(&self) -> Option<&str> {
         self.peek(Some(self.current + 1))
     }
 
     fn create_token(&mut self, t: TokenType, line: usize, val: String, pos: usize) -> Token {
-        let t = Token::new(t, line, val, pos);
+        let mut t = Token::new(t, line, val, pos);
+        t.span = Span {
+            start: self.offsets[self.start],
+            end: self.offsets[self.current],
+        };
         self.tokens.push(t.clone());
         t
     }
 
     fn add_token(&mut self, tok_type: TokenType) {
         let val = self.slice(self.start, self.current).to_string();
-        self.create_token(tok_type, self.line, val, self.inline_offset);
+        self.create_token(tok_type, self.start_line, val, self.start_pos);
     }
 }
 
@@ -257,7 +590,9 @@ mod tests {
     }
 }
 
+#[cfg(test)]
 use std::fs::File;
+#[cfg(test)]
 use std::io::Read;
 #[test]
 fn test_scan_identifier() {
@@ -271,7 +606,7 @@ fn test_scan_identifier() {
 
     let src = String::from("class People_123123{ var 2=3};");
     let mut scanner = Scanner::new(&src);
-    scanner.scan_tokens();
+    scanner.scan_tokens().unwrap();
     let mut it = scanner.tokens.iter();
     assert_eq!(it.next().unwrap().val, "class");
     assert_eq!(it.next().unwrap().val, "People_123123");
@@ -285,7 +620,7 @@ fn test_scan_identifier() {
 
     let src = String::from("class People-123123{ var 2=3};");
     let mut scanner = Scanner::new(&src);
-    scanner.scan_tokens();
+    scanner.scan_tokens().unwrap();
     let mut it = scanner.tokens.iter();
     assert_eq!(it.next().unwrap().val, "class");
     assert_eq!(it.next().unwrap().val, "People");
@@ -304,18 +639,17 @@ fn test_scan_identifier() {
 fn test_scan_number() {
     let src = String::from("23333");
     let mut scanner = Scanner::new(&src);
-    scanner.scan_number();
+    scanner.scan_number().unwrap();
     assert_eq!(scanner.tokens.len(), 1);
     let mut it = scanner.tokens.iter();
     assert_eq!(it.next().unwrap().val, "23333");
 
     let src = String::from("2.3333--3333");
     let mut scanner = Scanner::new(&src);
-    scanner.scan_tokens();
+    scanner.scan_tokens().unwrap();
     let mut it = scanner.tokens.iter();
     assert_eq!(it.next().unwrap().val, "2.3333");
-    assert_eq!(it.next().unwrap().val, "-");
-    assert_eq!(it.next().unwrap().val, "-");
+    assert_eq!(it.next().unwrap().val, "--");
     assert_eq!(it.next().unwrap().val, "3333");
 }
 
@@ -342,8 +676,190 @@ fn test_scan_tokens() {
     let mut src = String::new();
     let _f = File::open("./src/fixtures/ex1.lox").and_then(|mut f| f.read_to_string(&mut src));
     let mut scanner = Scanner::new(&src);
-    scanner.scan_tokens();
+    scanner.scan_tokens().unwrap();
     for t in scanner.tokens.iter() {
         info!("{}", t.to_string());
     }
 }
+
+#[test]
+fn test_scan_string_escapes() {
+    let src = String::from(r#""a\tb\n\"q\" \\ \u{1F600}\u{e9}" "ok""#);
+    let mut scanner = Scanner::new(&src);
+    let tokens = scanner.scan_tokens().unwrap();
+    assert_eq!(tokens[0].tok_type, TokenType::STRING);
+    assert_eq!(tokens[0].val, "a\tb\n\"q\" \\ \u{1F600}\u{e9}");
+    assert_eq!(tokens[1].val, "ok");
+
+    for src in &[
+        r#""\q""#,
+        r#""\u{110000}""#,
+        r#""\u{}""#,
+        r#""\u41""#,
+        r#""abc\"#,
+    ] {
+        match Scanner::new(src).scan_tokens() {
+            Err(Error::Lexical(1, _, _, _)) => (),
+            r => panic!("expected a lexical error for {}, got {:?}", src, r),
+        }
+    }
+}
+
+#[test]
+fn test_scan_raw_string() {
+    let src = String::from("var s = \"\"\"line \\n \"one\"\nline two\"\"\";\nprint s;");
+    let mut scanner = Scanner::new(&src);
+    let tokens = scanner.scan_tokens().unwrap();
+    assert_eq!(tokens[3].tok_type, TokenType::STRING);
+    assert_eq!(tokens[3].val, "line \\n \"one\"\nline two");
+    assert_eq!(tokens[3].line, 1);
+    // tokens after the raw string are on the right line
+    assert_eq!(tokens[5].val, "print");
+    assert_eq!(tokens[5].line, 3);
+
+    match Scanner::new("\"\"\"abc\"\"").scan_tokens() {
+        Err(Error::Lexical(1, 1, _, _)) => (),
+        r => panic!("expected a lexical error, got {:?}", r),
+    }
+}
+
+#[test]
+fn test_scan_interpolation() {
+    let src = String::from(r#""a ${b + "c ${d}"} \${e} ${ {"f": 1}["f"] }""#);
+    let mut scanner = Scanner::new(&src);
+    let tokens = scanner.scan_tokens().unwrap();
+    let types: Vec<TokenType> = tokens.iter().map(|t| t.tok_type).collect();
+    assert_eq!(
+        types,
+        vec![
+            TokenType::INTERPOLATION,
+            TokenType::IDENTIFIER,
+            TokenType::PLUS,
+            TokenType::INTERPOLATION,
+            TokenType::IDENTIFIER,
+            TokenType::STRING,
+            TokenType::INTERPOLATION,
+            TokenType::LeftBrace,
+            TokenType::STRING,
+            TokenType::COLON,
+            TokenType::NUMBER,
+            TokenType::RightBrace,
+            TokenType::LeftBracket,
+            TokenType::STRING,
+            TokenType::RightBracket,
+            TokenType::STRING,
+            TokenType::EOF,
+        ]
+    );
+    assert_eq!(tokens[0].val, "a ");
+    assert_eq!(tokens[6].val, " ${e} ");
+
+    match Scanner::new("\"a ${b\n").scan_tokens() {
+        Err(Error::Lexical(1, 4, _, _)) => (),
+        r => panic!("expected a lexical error, got {:?}", r),
+    }
+}
+
+#[test]
+fn test_scan_number_literals() {
+    let src = String::from("0xFF 0b1010 0o17 1.5e-3 2E+2 1_000_000 0x_ 3.foo");
+    let tokens = Scanner::new(&src).scan_tokens();
+    assert!(tokens.is_err());
+
+    let src = String::from("0xFF 0b1010 0o17 1.5e-3 2E+2 1_000_000 0.25 3.foo");
+    let tokens = Scanner::new(&src).scan_tokens().unwrap();
+    let values: Vec<Option<f64>> = tokens[..8].iter().map(|t| parse_number(&t.val)).collect();
+    assert_eq!(
+        values,
+        vec![
+            Some(255.0),
+            Some(10.0),
+            Some(15.0),
+            Some(0.0015),
+            Some(200.0),
+            Some(1_000_000.0),
+            Some(0.25),
+            Some(3.0),
+        ]
+    );
+    assert_eq!(tokens[7].val, "3");
+    assert_eq!(tokens[8].tok_type, TokenType::DOT);
+}
+
+#[test]
+fn test_scan_malformed_numbers() {
+    for (src, msg) in &[
+        ("0x", "missing digits after base prefix"),
+        ("0b", "missing digits after base prefix"),
+        ("1e", "missing exponent digits"),
+        ("1.5e+;", "missing exponent digits"),
+        ("0b102", "invalid digit in number literal"),
+        ("0xFG", "invalid digit in number literal"),
+        ("12abc", "invalid digit in number literal"),
+        ("1_", "digit separator must be followed by a digit"),
+        ("1__0", "digit separator must be followed by a digit"),
+    ] {
+        match Scanner::new(src).scan_tokens() {
+            Err(Error::Lexical(1, 1, ref m, _)) if m == msg => (),
+            r => panic!("expected {:?} for {}, got {:?}", msg, src, r),
+        }
+    }
+}
+
+#[test]
+fn test_scan_block_comments() {
+    let src = String::from("1 /* a /* nested\n */ still comment\n*/ 2 //// plain\n3");
+    let tokens = Scanner::new(&src).scan_tokens().unwrap();
+    let vals: Vec<(&str, usize)> = tokens.iter().map(|t| (t.val.as_str(), t.line)).collect();
+    assert_eq!(vals, vec![("1", 1), ("2", 3), ("3", 4), ("\0", 4)]);
+
+    match Scanner::new("1\n/* a /* b */").scan_tokens() {
+        Err(Error::Lexical(2, 1, _, _)) => (),
+        r => panic!("expected a lexical error, got {:?}", r),
+    }
+}
+
+#[test]
+fn test_scan_doc_comments() {
+    let src = String::from("/// Makes breakfast.\n///\nfun cook() {}");
+    let tokens = Scanner::new(&src).scan_tokens().unwrap();
+    assert_eq!(tokens[0].tok_type, TokenType::DocComment);
+    assert_eq!(tokens[0].val, "Makes breakfast.");
+    assert_eq!(tokens[1].tok_type, TokenType::DocComment);
+    assert_eq!(tokens[1].val, "");
+    assert_eq!(tokens[2].tok_type, TokenType::FUN);
+    assert_eq!(tokens[2].line, 3);
+}
+
+#[test]
+fn test_scan_operators() {
+    let src = String::from("% ** * ~/ ~ & | ^ << <= < >> >= > // ~/\n/");
+    let mut scanner = Scanner::new(&src);
+    let types: Vec<TokenType> = scanner
+        .scan_tokens()
+        .unwrap()
+        .iter()
+        .map(|t| t.tok_type)
+        .collect();
+    assert_eq!(
+        types,
+        vec![
+            TokenType::PERCENT,
+            TokenType::StarStar,
+            TokenType::STAR,
+            TokenType::TildeSlash,
+            TokenType::TILDE,
+            TokenType::AMPERSAND,
+            TokenType::PIPE,
+            TokenType::CARET,
+            TokenType::LessLess,
+            TokenType::LessEqual,
+            TokenType::LESS,
+            TokenType::GreaterGreater,
+            TokenType::GreaterEqual,
+            TokenType::GREATER,
+            TokenType::SLASH,
+            TokenType::EOF,
+        ]
+    );
+}
@@ -1,5 +1,7 @@
 #![allow(dead_code)]
 
+use std::fmt;
+
 #[derive(Debug, PartialEq, Copy, Clone, Hash, Eq)]
 pub enum TokenType {
     // Single-character tokens.
@@ -7,62 +9,107 @@ pub enum TokenType {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
+    COLON,
     COMMA,
+    QUESTION,
     DOT,
     MINUS,
     PLUS,
     SEMICOLON,
     SLASH,
-    STAR,
+    PERCENT,
+    AMPERSAND,
+    PIPE,
+    CARET,
 
     // ONE OR TWO CHARACTER TOKENS
+    PlusEqual,
+    MinusEqual,
+    StarEqual,
+    SlashEqual,
+    PercentEqual,
+    PlusPlus,
+    MinusMinus,
+    STAR,
+    StarStar,
+    TILDE,
+    /// `~/`, integer division
+    TildeSlash,
     BANG,
     BangEqual,
     EQUAL,
     EqualEqual,
     GREATER,
     GreaterEqual,
+    GreaterGreater,
     LESS,
     LessEqual,
+    LessLess,
 
     // Literials.
     IDENTIFIER,
     STRING,
+    /// part of a string literal that is followed by `${`
+    INTERPOLATION,
     NUMBER,
 
+    /// `///` comment documenting the next declaration
+    DocComment,
+
     // KEYWORDS.
     AND,
+    AS,
+    BREAK,
+    CATCH,
     CLASS,
     ELSE,
     FALSE,
+    FINALLY,
     FUN,
     FOR,
+    FROM,
     IF,
+    IMPORT,
+    IN,
     NIL,
     OR,
     PRINT,
     RETURN,
     SUPER,
     THIS,
+    THROW,
     TRUE,
+    TRY,
     VAR,
     WHILE,
+    YIELD,
 
     EOF,
 }
 
-impl TokenType {
-    fn to_string(&self) -> String {
-        format!("{:?}", self)
+impl fmt::Display for TokenType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
     }
 }
 
+/// Byte range of a token in its source
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Token {
     pub tok_type: TokenType,
     pub line: usize,
     pub val: String,
     pub pos: usize,
+    /// empty for tokens that don't come from the scanner
+    pub span: Span,
 }
 
 impl Token {
@@ -72,11 +119,14 @@ impl Token {
             line,
             val,
             pos,
+            span: Span::default(),
         }
     }
+}
 
-    pub fn to_string(&self) -> String {
-        return self.tok_type.to_string() + " " + self.val.as_ref();
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.tok_type, self.val)
     }
 }
 
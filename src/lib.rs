@@ -1,10 +1,13 @@
 pub mod cli;
 mod core;
 pub mod frontend;
+pub mod lsp;
 pub mod result;
+pub mod runtime;
 
 #[macro_use(lazy_static)]
 extern crate lazy_static;
 
+#[cfg(test)]
 #[macro_use]
 extern crate log;
@@ -0,0 +1,451 @@
+//! Name resolution for editor features.
+//!
+//! The resolver walks the `SyntaxTree` rather than the AST, as it needs
+//! the extent of every scope to know which names are visible where.
+
+use crate::frontend::cst::{SyntaxElement, SyntaxKind, SyntaxNode, SyntaxTree, TriviaKind};
+use crate::frontend::token::{Span, TokenType};
+use crate::result::Result;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Variable,
+    Parameter,
+    Function,
+    Class,
+    Method,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// the name in the declaration
+    pub span: Span,
+    /// the whole declaration
+    pub range: Span,
+    /// a one-line summary, e.g. `fun add(a, b)`
+    pub detail: String,
+    pub doc: Option<String>,
+    /// the class of a method
+    pub parent: Option<usize>,
+}
+
+/// A use of a symbol, declarations included
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reference {
+    pub span: Span,
+    pub symbol: usize,
+}
+
+#[derive(Debug)]
+struct Scope {
+    span: Span,
+    parent: Option<usize>,
+    /// symbols in declaration order
+    symbols: Vec<usize>,
+}
+
+#[derive(Debug)]
+pub struct Analysis {
+    pub symbols: Vec<Symbol>,
+    pub references: Vec<Reference>,
+    scopes: Vec<Scope>,
+}
+
+impl Analysis {
+    pub fn analyze(source: &str) -> Result<Analysis> {
+        let tree = SyntaxTree::parse(source)?;
+        let mut resolver = Resolver {
+            analysis: Analysis {
+                symbols: vec![],
+                references: vec![],
+                scopes: vec![],
+            },
+            scope: 0,
+            unresolved: vec![],
+        };
+        resolver.push_scope(Span {
+            start: 0,
+            end: source.len(),
+        });
+        resolver.nodes(&tree.root);
+        resolver.resolve_globals();
+        let mut analysis = resolver.analysis;
+        analysis.references.sort_by_key(|r| r.span.start);
+        Ok(analysis)
+    }
+
+    /// The symbol named at `offset`, touching either end of the name
+    pub fn symbol_at(&self, offset: usize) -> Option<usize> {
+        self.references
+            .iter()
+            .find(|r| r.span.start <= offset && offset <= r.span.end)
+            .map(|r| r.symbol)
+    }
+
+    pub fn references_to(&self, symbol: usize) -> Vec<Span> {
+        self.references
+            .iter()
+            .filter(|r| r.symbol == symbol)
+            .map(|r| r.span)
+            .collect()
+    }
+
+    /// Names that can be used at `offset`, innermost first
+    pub fn visible_at(&self, offset: usize) -> Vec<&Symbol> {
+        let mut scope = self
+            .scopes
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.span.start <= offset && offset <= s.span.end)
+            .max_by_key(|(_, s)| s.span.start)
+            .map(|(i, _)| i);
+
+        let mut visible: Vec<&Symbol> = vec![];
+        while let Some(i) = scope {
+            for &id in self.scopes[i].symbols.iter().rev() {
+                let symbol = &self.symbols[id];
+                // top-level names may be used in functions above them
+                let declared = i == 0 || symbol.span.end <= offset;
+                if declared && !visible.iter().any(|s| s.name == symbol.name) {
+                    visible.push(symbol);
+                }
+            }
+            scope = self.scopes[i].parent;
+        }
+        visible
+    }
+}
+
+struct Resolver {
+    analysis: Analysis,
+    scope: usize,
+    /// references not declared before their use, with their scope
+    unresolved: Vec<(Span, String, usize)>,
+}
+
+impl Resolver {
+    fn push_scope(&mut self, span: Span) {
+        let parent = if self.analysis.scopes.is_empty() {
+            None
+        } else {
+            Some(self.scope)
+        };
+        self.analysis.scopes.push(Scope {
+            span,
+            parent,
+            symbols: vec![],
+        });
+        self.scope = self.analysis.scopes.len() - 1;
+    }
+
+    fn pop_scope(&mut self) {
+        self.scope = self.analysis.scopes[self.scope].parent.unwrap_or(0);
+    }
+
+    fn declare(&mut self, symbol: Symbol) -> usize {
+        let id = self.analysis.symbols.len();
+        self.analysis.references.push(Reference {
+            span: symbol.span,
+            symbol: id,
+        });
+        // methods are looked up on instances, not in scopes
+        if symbol.kind != SymbolKind::Method {
+            self.analysis.scopes[self.scope].symbols.push(id);
+        }
+        self.analysis.symbols.push(symbol);
+        id
+    }
+
+    fn lookup(&self, name: &str, mut scope: Option<usize>) -> Option<usize> {
+        while let Some(i) = scope {
+            let s = &self.analysis.scopes[i];
+            let found = s
+                .symbols
+                .iter()
+                .rev()
+                .find(|&&id| self.analysis.symbols[id].name == name);
+            if let Some(&id) = found {
+                return Some(id);
+            }
+            scope = s.parent;
+        }
+        None
+    }
+
+    fn reference(&mut self, name: &str, span: Span) {
+        match self.lookup(name, Some(self.scope)) {
+            Some(symbol) => self.analysis.references.push(Reference { span, symbol }),
+            None => self.unresolved.push((span, name.to_string(), self.scope)),
+        }
+    }
+
+    /// Functions may call globals declared further down the file
+    fn resolve_globals(&mut self) {
+        for (span, name, _) in std::mem::take(&mut self.unresolved) {
+            if let Some(symbol) = self.lookup(&name, Some(0)) {
+                self.analysis.references.push(Reference { span, symbol });
+            }
+        }
+    }
+
+    fn nodes(&mut self, node: &SyntaxNode) {
+        for child in node.child_nodes() {
+            self.node(child);
+        }
+    }
+
+    fn node(&mut self, node: &SyntaxNode) {
+        match node.kind {
+            SyntaxKind::Block | SyntaxKind::ForStmt => {
+                self.push_scope(span_of(node));
+                self.nodes(node);
+                self.pop_scope();
+            }
+            SyntaxKind::VarDecl => {
+                // the initializer can't see the new variable
+                self.nodes(node);
+                let name = identifiers(node).next().unwrap();
+                self.declare(Symbol {
+                    name: name.0.clone(),
+                    kind: SymbolKind::Variable,
+                    span: name.1,
+                    range: span_of(node),
+                    detail: format!("var {}", name.0),
+                    doc: doc_comment(node),
+                    parent: None,
+                });
+            }
+            SyntaxKind::ForInStmt => {
+                self.push_scope(span_of(node));
+                for (name, span) in identifiers(node) {
+                    self.declare(Symbol {
+                        detail: format!("for ({} in)", name),
+                        name,
+                        kind: SymbolKind::Variable,
+                        span,
+                        range: span,
+                        doc: None,
+                        parent: None,
+                    });
+                }
+                self.nodes(node);
+                self.pop_scope();
+            }
+            SyntaxKind::CatchClause => {
+                self.push_scope(span_of(node));
+                let (name, span) = identifiers(node).next().unwrap();
+                self.declare(Symbol {
+                    detail: format!("catch ({})", name),
+                    name,
+                    kind: SymbolKind::Variable,
+                    span,
+                    range: span,
+                    doc: None,
+                    parent: None,
+                });
+                self.nodes(node);
+                self.pop_scope();
+            }
+            SyntaxKind::ImportStmt => {
+                let path = node
+                    .child_tokens()
+                    .find(|t| t.token.tok_type == TokenType::STRING)
+                    .map_or("", |t| t.text.as_str());
+                for (name, span) in identifiers(node) {
+                    self.declare(Symbol {
+                        detail: format!("import {} {}", path, name),
+                        name,
+                        kind: SymbolKind::Variable,
+                        span,
+                        range: span_of(node),
+                        doc: doc_comment(node),
+                        parent: None,
+                    });
+                }
+            }
+            SyntaxKind::FunDecl => self.function(node, None),
+            SyntaxKind::ClassDecl => {
+                let mut names = identifiers(node);
+                let (name, span) = names.next().unwrap();
+                let superclass = names.next();
+                let detail = match superclass {
+                    Some((ref sup, _)) => format!("class {} < {}", name, sup),
+                    None => format!("class {}", name),
+                };
+                let class = self.declare(Symbol {
+                    name,
+                    kind: SymbolKind::Class,
+                    span,
+                    range: span_of(node),
+                    detail,
+                    doc: doc_comment(node),
+                    parent: None,
+                });
+                if let Some((sup, span)) = superclass {
+                    self.reference(&sup, span);
+                }
+                for method in node.child_nodes() {
+                    self.function(method, Some(class));
+                }
+            }
+            SyntaxKind::VariableExpr => {
+                let (name, span) = identifiers(node).next().unwrap();
+                self.reference(&name, span);
+            }
+            _ => self.nodes(node),
+        }
+    }
+
+    fn function(&mut self, node: &SyntaxNode, class: Option<usize>) {
+        let (name, span) = identifiers(node).next().unwrap();
+        let mut children = node.child_nodes();
+        let params_node = children.next().unwrap();
+        let body = children.next().unwrap();
+        let params: Vec<_> = identifiers(params_node).collect();
+        let signature = format!(
+            "{}({})",
+            name,
+            params
+                .iter()
+                .map(|p| p.0.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        let star = if node.child_tokens().any(|t| t.text == "*") {
+            "*"
+        } else {
+            ""
+        };
+        let (kind, detail) = match class {
+            Some(_) => (SymbolKind::Method, format!("{}{}", star, signature)),
+            None => (SymbolKind::Function, format!("fun{} {}", star, signature)),
+        };
+        self.declare(Symbol {
+            name,
+            kind,
+            span,
+            range: span_of(node),
+            detail,
+            doc: doc_comment(node),
+            parent: class,
+        });
+
+        // parameters and body share a scope
+        self.push_scope(Span {
+            start: span_of(params_node).start,
+            end: span_of(body).end,
+        });
+        for (param, span) in params {
+            self.declare(Symbol {
+                detail: format!("parameter {}", param),
+                name: param,
+                kind: SymbolKind::Parameter,
+                span,
+                range: span,
+                doc: None,
+                parent: None,
+            });
+        }
+        self.nodes(body);
+        self.pop_scope();
+    }
+}
+
+/// The identifiers that are direct children of `node`
+fn identifiers(node: &SyntaxNode) -> impl Iterator<Item = (String, Span)> + '_ {
+    node.child_tokens()
+        .filter(|t| t.token.tok_type == TokenType::IDENTIFIER)
+        .map(|t| (t.text.clone(), t.token.span))
+}
+
+fn span_of(node: &SyntaxNode) -> Span {
+    let tokens = node.tokens();
+    Span {
+        start: tokens.first().map_or(0, |t| t.token.span.start),
+        end: tokens.last().map_or(0, |t| t.token.span.end),
+    }
+}
+
+/// The `///` comments right before a declaration
+fn doc_comment(node: &SyntaxNode) -> Option<String> {
+    let first = match node.children.first() {
+        Some(SyntaxElement::Token(t)) => t,
+        _ => node.first_token()?,
+    };
+    let lines: Vec<&str> = first
+        .leading
+        .iter()
+        .filter(|t| t.kind == TriviaKind::DocComment)
+        .map(|t| {
+            let text = &t.text[3..];
+            text.strip_prefix(' ').unwrap_or(text)
+        })
+        .collect();
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "var a = 1;
+/// Adds.
+fun add(x, y) { return x + y + a + later(); }
+class B < A { get() { var a = 2; return a; } }
+fun later() { return add; }
+";
+
+    fn offset(needle: &str, nth: usize) -> usize {
+        SOURCE.match_indices(needle).nth(nth).unwrap().0
+    }
+
+    #[test]
+    fn test_definitions_and_references() {
+        let analysis = Analysis::analyze(SOURCE).unwrap();
+
+        let a = analysis.symbol_at(offset("a +", 0)).unwrap();
+        assert_eq!(analysis.symbols[a].span.start, offset("a = 1", 0));
+        assert_eq!(analysis.references_to(a).len(), 2);
+
+        // the local `a` in the method is a different variable
+        let local = analysis.symbol_at(offset("return a;", 0) + 7).unwrap();
+        assert_ne!(local, a);
+
+        let later = analysis.symbol_at(offset("later()", 0)).unwrap();
+        assert_eq!(analysis.symbols[later].detail, "fun later()");
+        let add = analysis.symbol_at(offset("add;", 0)).unwrap();
+        assert_eq!(analysis.symbols[add].doc.as_ref().unwrap(), "Adds.");
+        assert_eq!(analysis.references_to(add).len(), 2);
+
+        let method = analysis.symbol_at(offset("get", 0)).unwrap();
+        assert_eq!(analysis.symbols[method].kind, SymbolKind::Method);
+        assert_eq!(analysis.symbols[method].detail, "get()");
+        // `A` is not declared anywhere
+        assert_eq!(analysis.symbol_at(offset("A {", 0)), None);
+    }
+
+    #[test]
+    fn test_visible_names() {
+        let analysis = Analysis::analyze(SOURCE).unwrap();
+        let names = |offset| -> Vec<String> {
+            let mut names: Vec<_> = analysis
+                .visible_at(offset)
+                .into_iter()
+                .map(|s| s.name.clone())
+                .collect();
+            names.sort();
+            names
+        };
+        assert_eq!(
+            names(offset("x + y", 0)),
+            vec!["B", "a", "add", "later", "x", "y"]
+        );
+        assert_eq!(names(0), vec!["B", "a", "add", "later"]);
+    }
+}
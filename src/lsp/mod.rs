@@ -0,0 +1,4 @@
+//! A Language Server Protocol server for editors.
+
+pub mod analysis;
+pub mod server;
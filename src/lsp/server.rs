@@ -0,0 +1,557 @@
+//! JSON-RPC transport and request handling.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+use serde_json::{json, Value};
+
+use crate::frontend::keywords::LoxKeywords;
+use crate::frontend::token::Span;
+use crate::lsp::analysis::{Analysis, SymbolKind};
+use crate::result::Error;
+
+// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// Serves requests until the client sends `exit`. Returns whether the
+/// client asked for a `shutdown` first, as a clean exit requires.
+pub fn serve<R: BufRead, W: Write>(mut input: R, output: W) -> io::Result<bool> {
+    let mut server = Server::new(output);
+    while let Some(message) = read_message(&mut input)? {
+        let message = match message {
+            Ok(message) => message,
+            Err(e) => {
+                server.parse_error(&e)?;
+                continue;
+            }
+        };
+        if !server.handle(&message)? {
+            break;
+        }
+    }
+    Ok(server.shutdown)
+}
+
+/// Reads one `Content-Length` framed message, `None` at the end of input.
+/// A body that isn't JSON is an error for the message, not the stream.
+fn read_message<R: BufRead>(input: &mut R) -> io::Result<Option<serde_json::Result<Value>>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+    let length = length.ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidData, "missing Content-Length header")
+    })?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    Ok(Some(serde_json::from_slice(&body)))
+}
+
+fn write_message<W: Write>(output: &mut W, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+struct Document {
+    text: String,
+    lines: LineIndex,
+    /// missing while the text doesn't parse
+    analysis: Option<Analysis>,
+    diagnostics: Vec<Value>,
+}
+
+impl Document {
+    fn new(text: String) -> Document {
+        let mut document = Document {
+            lines: LineIndex::new(&text),
+            text,
+            analysis: None,
+            diagnostics: vec![],
+        };
+        match Analysis::analyze(&document.text) {
+            Ok(analysis) => document.analysis = Some(analysis),
+            Err(e) => document.diagnostics = vec![document.diagnostic(&e)],
+        }
+        document
+    }
+
+    fn diagnostic(&self, err: &Error) -> Value {
+        let (line, pos, msg) = match *err {
+            Error::Lexical(line, pos, ref msg, _) | Error::Parse(line, pos, ref msg, _) => {
+                (line as usize, pos as usize, msg.clone())
+            }
+            ref e => (0, 0, e.to_string()),
+        };
+        let start = self.lines.offset_of_column(&self.text, line, pos);
+        let end = self.text[start..]
+            .chars()
+            .next()
+            .map_or(start, |c| start + c.len_utf8());
+        json!({
+            "range": self.range(Span { start, end }),
+            "severity": 1,
+            "source": "lox",
+            "message": msg,
+        })
+    }
+
+    fn position(&self, offset: usize) -> Value {
+        let (line, character) = self.lines.position(&self.text, offset);
+        json!({ "line": line, "character": character })
+    }
+
+    fn range(&self, span: Span) -> Value {
+        json!({ "start": self.position(span.start), "end": self.position(span.end) })
+    }
+
+    fn offset(&self, position: &Value) -> Option<usize> {
+        let line = position["line"].as_u64()? as usize;
+        let character = position["character"].as_u64()? as usize;
+        Some(self.lines.offset(&self.text, line, character))
+    }
+}
+
+/// Converts between byte offsets and LSP positions, which count lines
+/// from 0 and characters in UTF-16 code units
+struct LineIndex {
+    starts: Vec<usize>,
+}
+
+impl LineIndex {
+    fn new(text: &str) -> LineIndex {
+        let mut starts = vec![0];
+        starts.extend(text.match_indices('\n').map(|(i, _)| i + 1));
+        LineIndex { starts }
+    }
+
+    fn position(&self, text: &str, offset: usize) -> (usize, usize) {
+        let line = match self.starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next) => next - 1,
+        };
+        let character = text[self.starts[line]..offset]
+            .chars()
+            .map(char::len_utf16)
+            .sum();
+        (line, character)
+    }
+
+    fn offset(&self, text: &str, line: usize, character: usize) -> usize {
+        let start = match self.starts.get(line) {
+            Some(&start) => start,
+            None => return text.len(),
+        };
+        let mut units = 0;
+        for (i, c) in text[start..].char_indices() {
+            if units >= character || c == '\n' {
+                return start + i;
+            }
+            units += c.len_utf16();
+        }
+        text.len()
+    }
+
+    /// The offset of a 1-based line and column as reported in errors;
+    /// column 0 stands for the end of the text
+    fn offset_of_column(&self, text: &str, line: usize, column: usize) -> usize {
+        if line == 0 || column == 0 {
+            return text.len();
+        }
+        let start = self.starts.get(line - 1).cloned().unwrap_or(text.len());
+        text[start..]
+            .char_indices()
+            .nth(column - 1)
+            .map_or(text.len(), |(i, _)| start + i)
+    }
+}
+
+struct Server<W: Write> {
+    output: W,
+    documents: HashMap<String, Document>,
+    shutdown: bool,
+}
+
+impl<W: Write> Server<W> {
+    fn new(output: W) -> Server<W> {
+        Server {
+            output,
+            documents: HashMap::new(),
+            shutdown: false,
+        }
+    }
+
+    /// Handles one message, false once the client asks to exit
+    fn handle(&mut self, message: &Value) -> io::Result<bool> {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        let id = match message.get("id") {
+            Some(id) => id.clone(),
+            None => {
+                return match method {
+                    "exit" => Ok(false),
+                    _ => self.notification(method, params).map(|_| true),
+                }
+            }
+        };
+        let result = match method {
+            "initialize" => Ok(capabilities()),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            "textDocument/definition" => self.definition(params),
+            "textDocument/references" => self.references(params),
+            "textDocument/hover" => self.hover(params),
+            "textDocument/documentSymbol" => self.document_symbols(params),
+            "textDocument/completion" => self.completion(params),
+            _ => Err((METHOD_NOT_FOUND, format!("unknown method {}", method))),
+        };
+        let response = match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": code, "message": message },
+            }),
+        };
+        write_message(&mut self.output, &response)?;
+        Ok(true)
+    }
+
+    /// Replies to a message that isn't JSON, whose id can't be known
+    fn parse_error(&mut self, err: &serde_json::Error) -> io::Result<()> {
+        let response = json!({
+            "jsonrpc": "2.0",
+            "id": null,
+            "error": { "code": PARSE_ERROR, "message": format!("parse error: {}", err) },
+        });
+        write_message(&mut self.output, &response)
+    }
+
+    fn notification(&mut self, method: &str, params: &Value) -> io::Result<()> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let text = match method {
+            "textDocument/didOpen" => params["textDocument"]["text"].as_str(),
+            // full text sync, the last change holds the whole document
+            "textDocument/didChange" => params["contentChanges"]
+                .as_array()
+                .and_then(|changes| changes.last())
+                .and_then(|change| change["text"].as_str()),
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                return self.publish_diagnostics(uri, vec![]);
+            }
+            _ => return Ok(()),
+        };
+        if let Some(text) = text {
+            let document = Document::new(text.to_string());
+            let diagnostics = document.diagnostics.clone();
+            self.documents.insert(uri.to_string(), document);
+            self.publish_diagnostics(uri, diagnostics)?;
+        }
+        Ok(())
+    }
+
+    fn publish_diagnostics(&mut self, uri: &str, diagnostics: Vec<Value>) -> io::Result<()> {
+        let notification = json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": { "uri": uri, "diagnostics": diagnostics },
+        });
+        write_message(&mut self.output, &notification)
+    }
+
+    /// The document and cursor offset of a position request
+    fn locate<'a>(&'a self, params: &'a Value) -> RequestResult<(&'a str, &'a Document, usize)> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let document = self
+            .documents
+            .get(uri)
+            .ok_or_else(|| (INVALID_PARAMS, format!("unknown document {}", uri)))?;
+        let offset = document
+            .offset(&params["position"])
+            .ok_or_else(|| (INVALID_PARAMS, "missing position".to_string()))?;
+        Ok((uri, document, offset))
+    }
+
+    fn definition(&self, params: &Value) -> RequestResult<Value> {
+        let (uri, document, offset) = self.locate(params)?;
+        let symbol = document
+            .analysis
+            .as_ref()
+            .and_then(|a| a.symbol_at(offset).map(|s| &a.symbols[s]));
+        Ok(match symbol {
+            Some(symbol) => json!({ "uri": uri, "range": document.range(symbol.span) }),
+            None => Value::Null,
+        })
+    }
+
+    fn references(&self, params: &Value) -> RequestResult<Value> {
+        let (uri, document, offset) = self.locate(params)?;
+        let include_declaration = params["context"]["includeDeclaration"]
+            .as_bool()
+            .unwrap_or(true);
+        let analysis = match document.analysis {
+            Some(ref analysis) => analysis,
+            None => return Ok(Value::Null),
+        };
+        let symbol = match analysis.symbol_at(offset) {
+            Some(symbol) => symbol,
+            None => return Ok(Value::Null),
+        };
+        let declaration = analysis.symbols[symbol].span;
+        let locations: Vec<Value> = analysis
+            .references_to(symbol)
+            .into_iter()
+            .filter(|&span| include_declaration || span != declaration)
+            .map(|span| json!({ "uri": uri, "range": document.range(span) }))
+            .collect();
+        Ok(Value::Array(locations))
+    }
+
+    fn hover(&self, params: &Value) -> RequestResult<Value> {
+        let (_, document, offset) = self.locate(params)?;
+        let analysis = match document.analysis {
+            Some(ref analysis) => analysis,
+            None => return Ok(Value::Null),
+        };
+        let reference = analysis
+            .references
+            .iter()
+            .find(|r| r.span.start <= offset && offset <= r.span.end);
+        let reference = match reference {
+            Some(reference) => reference,
+            None => return Ok(Value::Null),
+        };
+        let symbol = &analysis.symbols[reference.symbol];
+        let mut value = format!("```lox\n{}\n```", symbol.detail);
+        if let Some(ref doc) = symbol.doc {
+            value.push_str("\n\n");
+            value.push_str(doc);
+        }
+        Ok(json!({
+            "contents": { "kind": "markdown", "value": value },
+            "range": document.range(reference.span),
+        }))
+    }
+
+    /// Classes and functions, nested as in the source
+    fn document_symbols(&self, params: &Value) -> RequestResult<Value> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let document = self
+            .documents
+            .get(uri)
+            .ok_or_else(|| (INVALID_PARAMS, format!("unknown document {}", uri)))?;
+        let analysis = match document.analysis {
+            Some(ref analysis) => analysis,
+            None => return Ok(json!([])),
+        };
+
+        // symbols are declared in source order, so each one is nested in
+        // the closest open symbol whose range contains it
+        let mut roots: Vec<Value> = vec![];
+        let mut open: Vec<(Span, Value)> = vec![];
+        let close = |open: &mut Vec<(Span, Value)>, roots: &mut Vec<Value>| {
+            let (_, symbol) = open.pop().unwrap();
+            match open.last_mut() {
+                Some((_, parent)) => parent["children"].as_array_mut().unwrap().push(symbol),
+                None => roots.push(symbol),
+            }
+        };
+        for symbol in analysis.symbols.iter() {
+            let kind = match symbol.kind {
+                SymbolKind::Class => 5,
+                SymbolKind::Method => 6,
+                SymbolKind::Function => 12,
+                SymbolKind::Variable | SymbolKind::Parameter => continue,
+            };
+            while open
+                .last()
+                .is_some_and(|(range, _)| range.end < symbol.range.start)
+            {
+                close(&mut open, &mut roots);
+            }
+            let value = json!({
+                "name": symbol.name,
+                "detail": symbol.detail,
+                "kind": kind,
+                "range": document.range(symbol.range),
+                "selectionRange": document.range(symbol.span),
+                "children": [],
+            });
+            open.push((symbol.range, value));
+        }
+        while !open.is_empty() {
+            close(&mut open, &mut roots);
+        }
+        Ok(Value::Array(roots))
+    }
+
+    fn completion(&self, params: &Value) -> RequestResult<Value> {
+        let (_, document, offset) = self.locate(params)?;
+        let mut keywords: Vec<&str> = LoxKeywords.keys().cloned().collect();
+        keywords.sort();
+        let mut items: Vec<Value> = keywords
+            .into_iter()
+            .map(|keyword| json!({ "label": keyword, "kind": 14 }))
+            .collect();
+        if let Some(ref analysis) = document.analysis {
+            for symbol in analysis.visible_at(offset) {
+                let kind = match symbol.kind {
+                    SymbolKind::Variable | SymbolKind::Parameter => 6,
+                    SymbolKind::Function => 3,
+                    SymbolKind::Class => 7,
+                    SymbolKind::Method => 2,
+                };
+                items.push(json!({ "label": symbol.name, "kind": kind, "detail": symbol.detail }));
+            }
+        }
+        Ok(Value::Array(items))
+    }
+}
+
+type RequestResult<T> = std::result::Result<T, (i64, String)>;
+
+fn capabilities() -> Value {
+    json!({
+        "capabilities": {
+            "textDocumentSync": 1,
+            "definitionProvider": true,
+            "referencesProvider": true,
+            "hoverProvider": true,
+            "documentSymbolProvider": true,
+            "completionProvider": {},
+        },
+        "serverInfo": { "name": "lox" },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn frame(message: Value) -> String {
+        let body = message.to_string();
+        format!("Content-Length: {}\r\n\r\n{}", body.len(), body)
+    }
+
+    fn request(id: u64, method: &str, params: Value) -> String {
+        frame(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))
+    }
+
+    fn notify(method: &str, params: Value) -> String {
+        frame(json!({ "jsonrpc": "2.0", "method": method, "params": params }))
+    }
+
+    /// Runs a session and returns the messages the server sent
+    fn session(messages: &[String]) -> (bool, Vec<Value>) {
+        let mut output = vec![];
+        let clean = serve(Cursor::new(messages.concat()), &mut output).unwrap();
+        let mut output = Cursor::new(output);
+        let mut replies = vec![];
+        while let Some(reply) = read_message(&mut output).unwrap() {
+            replies.push(reply.unwrap());
+        }
+        (clean, replies)
+    }
+
+    fn at(line: u64, character: u64) -> Value {
+        json!({
+            "textDocument": { "uri": "file:///a.lox" },
+            "position": { "line": line, "character": character },
+            "context": { "includeDeclaration": false },
+        })
+    }
+
+    #[test]
+    fn test_session() {
+        let text = "/// Doubles.\nfun double(n) { return n * 2; }\nprint double(4);\n";
+        let (clean, replies) = session(&[
+            request(1, "initialize", json!({})),
+            notify("initialized", json!({})),
+            notify(
+                "textDocument/didOpen",
+                json!({ "textDocument": { "uri": "file:///a.lox", "text": text } }),
+            ),
+            request(2, "textDocument/definition", at(2, 7)),
+            request(3, "textDocument/references", at(1, 4)),
+            request(4, "textDocument/hover", at(2, 7)),
+            request(5, "textDocument/documentSymbol", at(0, 0)),
+            request(6, "textDocument/completion", at(1, 23)),
+            notify(
+                "textDocument/didChange",
+                json!({
+                    "textDocument": { "uri": "file:///a.lox" },
+                    "contentChanges": [{ "text": "print (1;" }],
+                }),
+            ),
+            request(7, "no/such/method", json!({})),
+            "Content-Length: 9\r\n\r\n{\"id\": 8,".to_string(),
+            request(8, "shutdown", json!(null)),
+            notify("exit", json!(null)),
+        ]);
+        assert!(clean);
+        assert!(replies[0]["result"]["capabilities"]["hoverProvider"]
+            .as_bool()
+            .unwrap());
+        assert_eq!(replies[1]["params"]["diagnostics"], json!([]));
+
+        let range = &replies[2]["result"]["range"];
+        assert_eq!(range["start"], json!({ "line": 1, "character": 4 }));
+        assert_eq!(range["end"], json!({ "line": 1, "character": 10 }));
+
+        let references = replies[3]["result"].as_array().unwrap();
+        assert_eq!(references.len(), 1);
+        assert_eq!(references[0]["range"]["start"]["line"], 2);
+
+        let hover = replies[4]["result"]["contents"]["value"].as_str().unwrap();
+        assert_eq!(hover, "```lox\nfun double(n)\n```\n\nDoubles.");
+
+        assert_eq!(replies[5]["result"][0]["name"], "double");
+
+        let labels: Vec<&str> = replies[6]["result"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["label"].as_str().unwrap())
+            .collect();
+        assert!(labels.contains(&"while"));
+        assert!(labels.contains(&"n"));
+        assert!(labels.contains(&"double"));
+
+        let diagnostics = replies[7]["params"]["diagnostics"].as_array().unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0]["range"]["start"]["line"], 0);
+
+        assert_eq!(replies[8]["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(replies[9]["id"], Value::Null);
+        assert_eq!(replies[9]["error"]["code"], PARSE_ERROR);
+        assert_eq!(replies[10]["id"], 8);
+        assert_eq!(replies[10]["result"], Value::Null);
+    }
+
+    #[test]
+    fn test_positions() {
+        let text = "a\n\u{1F600}b\n";
+        let lines = LineIndex::new(text);
+        assert_eq!(lines.position(text, 6), (1, 2));
+        assert_eq!(lines.offset(text, 1, 2), 6);
+        assert_eq!(lines.offset(text, 1, 99), 7);
+        assert_eq!(lines.offset_of_column(text, 2, 2), 6);
+        assert_eq!(lines.offset_of_column(text, 1, 0), text.len());
+    }
+}
@@ -5,6 +5,8 @@ use std::fmt;
 use std::io;
 use std::result;
 
+use crate::runtime::value::Value;
+
 /// A Lox-Specific Result Type
 pub type Result<T> = result::Result<T, Error>;
 
@@ -19,10 +21,55 @@ pub enum Error {
     Lexical(u64, u64, String, String),
     /// Returned if the parser encounters an error
     Parse(u64, u64, String, String),
-    /// Returned if there is an error at runtime
+    /// Returned if there is an error at runtime, with its line. Natives
+    /// raise it with line 0, and the caller fills it in with `at_line`.
+    /// The full position is in the interpreter's stack trace.
     Runtime(u64, String, String),
     /// Sentinel error for break statements
     Break(u64),
+    /// Sentinel error for return statements
+    Return(Value),
+    /// Returned for a `throw` statement no `catch` handled, with the
+    /// thrown value
+    Throw(u64, Value),
+    /// Sentinel error for `exit(code)`
+    Exit(i32),
+    /// Sentinel error suspending a generator, with the yielded value
+    Yield(Value),
+}
+
+/// A call a runtime error was raised in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StackFrame {
+    /// `<script>` for top-level code
+    pub function: String,
+    /// the script the code is in, if it was read from a file
+    pub file: Option<String>,
+    pub line: usize,
+    /// 0 if only the line is known
+    pub column: usize,
+    /// native functions have no position
+    pub native: bool,
+}
+
+impl fmt::Display for StackFrame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.native {
+            return write!(f, "{} (native)", self.function);
+        }
+        match (&self.file, self.column) {
+            (Some(file), 0) => write!(f, "{} ({}:{})", self.function, file, self.line),
+            (Some(file), column) => {
+                write!(f, "{} ({}:{}:{})", self.function, file, self.line, column)
+            }
+            (None, 0) => write!(f, "{} (line {})", self.function, self.line),
+            (None, column) => write!(
+                f,
+                "{} (line {}, column {})",
+                self.function, self.line, column
+            ),
+        }
+    }
 }
 
 impl From<io::Error> for Error {
@@ -54,25 +101,31 @@ impl fmt::Display for Error {
                 "Runtime Error [line {}] unexpected break statement",
                 line
             ),
+            Error::Return(_) => write!(f, "Runtime Error unexpected return statement"),
+            Error::Throw(ref line, ref value) => {
+                write!(f, "Runtime Error [line {}] Uncaught {}", line, describe(value))
+            }
+            Error::Exit(ref code) => write!(f, "Exited with code {}", code),
+            Error::Yield(_) => write!(f, "Runtime Error unexpected yield"),
         }
     }
 }
 
-impl error::Error for Error {
-    fn description(&self) -> &str {
-        match *self {
-            Error::Usage => "usage error",
-            Error::IO(ref e) => e.description(),
-            Error::Lexical(_, _, _, _) => "lexical error",
-            Error::Parse(_, _, _, _) => "parse error",
-            Error::Runtime(_, _, _) => "runtime error",
-            Error::Break(_) => "break error",
+/// An error object as its class and message, other values as they are
+fn describe(value: &Value) -> String {
+    if let Value::Instance(ref instance) = *value {
+        let instance = instance.borrow();
+        if let Some(message) = instance.fields.get("message") {
+            return format!("{}: {}", instance.class.name, message);
         }
     }
+    value.repr()
+}
 
-    fn cause(&self) -> Option<&error::Error> {
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match *self {
-            Error::IO(ref e) => e.cause(),
+            Error::IO(ref e) => Some(e),
             _ => None,
         }
     }
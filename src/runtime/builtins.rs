@@ -0,0 +1,164 @@
+//! Native functions defined in the global scope of every script
+
+use std::env;
+use std::io;
+use std::rc::Rc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use super::function::{number_arg, string_arg, NativeFn, NativeFunction};
+use super::interpreter::Interpreter;
+use super::value::Value;
+use crate::result::{Error, Result};
+
+lazy_static! {
+    /// what `clock()` counts from
+    static ref STARTED: Instant = Instant::now();
+}
+
+pub fn functions() -> Vec<NativeFunction> {
+    let functions: &[(&str, usize, NativeFn)] = &[
+        ("str", 1, str),
+        ("num", 1, num),
+        ("type", 1, type_of),
+        ("input", 0, input),
+        ("args", 0, args),
+        ("env", 1, env),
+        ("exit", 1, exit),
+        ("clock", 0, clock),
+        ("now", 0, now),
+        ("sleep", 1, sleep),
+    ];
+    let mut functions: Vec<NativeFunction> = functions
+        .iter()
+        .map(|&(name, arity, func)| NativeFunction::new(name, arity, func))
+        .collect();
+    functions.push(NativeFunction::new("range", 3, range).with_optional(2));
+    functions
+}
+
+/// The text `print` would show for the value
+fn str(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    match args[0] {
+        Value::Str(_) => Ok(args[0].clone()),
+        ref value => Ok(Value::Str(Rc::from(value.to_string()))),
+    }
+}
+
+/// Parses a decimal number, surrounding whitespace is ignored
+fn num(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    let text = match args[0] {
+        Value::Number(_) => return Ok(args[0].clone()),
+        Value::Str(ref s) => s.trim(),
+        ref arg => {
+            return Err(Error::Runtime(
+                0,
+                "num() expects a number or a string as argument 1".to_string(),
+                arg.repr(),
+            ))
+        }
+    };
+    // `parse` would also take words like "inf" and "NaN"
+    let numeric = text
+        .chars()
+        .all(|c| c.is_ascii_digit() || "+-.eE".contains(c));
+    match text.parse() {
+        Ok(n) if numeric => Ok(Value::Number(n)),
+        _ => Err(Error::Runtime(
+            0,
+            "num() can't parse a number from".to_string(),
+            args[0].repr(),
+        )),
+    }
+}
+
+/// The name of the type of the value, e.g. "string"
+fn type_of(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    Ok(Value::Str(Rc::from(args[0].type_name())))
+}
+
+/// A line read from stdin without its line ending, nil at the end of input
+fn input(_: &mut Interpreter, _: &[Value]) -> Result<Value> {
+    let mut line = String::new();
+    if io::stdin().read_line(&mut line)? == 0 {
+        return Ok(Value::Nil);
+    }
+    let len = line.trim_end_matches(&['\n', '\r'][..]).len();
+    line.truncate(len);
+    Ok(Value::Str(Rc::from(line)))
+}
+
+/// The command-line arguments given after the script path
+fn args(interpreter: &mut Interpreter, _: &[Value]) -> Result<Value> {
+    let args = interpreter.args().iter();
+    Ok(Value::list(
+        args.map(|arg| Value::Str(Rc::from(arg.as_str()))).collect(),
+    ))
+}
+
+/// The value of an environment variable, or nil if it isn't set
+fn env(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    let name = string_arg("env", args, 0)?;
+    Ok(env::var(name).map_or(Value::Nil, |value| Value::Str(Rc::from(value))))
+}
+
+/// Stops the program, after running the `finally` blocks it leaves
+fn exit(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    let code = number_arg("exit", args, 0)?;
+    if code.fract() != 0.0 || code < i32::MIN as f64 || code > i32::MAX as f64 {
+        return Err(Error::Runtime(
+            0,
+            "exit() expects an integer exit code".to_string(),
+            args[0].repr(),
+        ));
+    }
+    Err(Error::Exit(code as i32))
+}
+
+/// Seconds on a monotonic clock, only differences between calls mean
+/// anything
+fn clock(_: &mut Interpreter, _: &[Value]) -> Result<Value> {
+    Ok(Value::Number(STARTED.elapsed().as_secs_f64()))
+}
+
+/// Seconds since the Unix epoch
+fn now(_: &mut Interpreter, _: &[Value]) -> Result<Value> {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0.0, |d| d.as_secs_f64());
+    Ok(Value::Number(since_epoch))
+}
+
+fn sleep(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    let seconds = number_arg("sleep", args, 0)?;
+    if !(seconds >= 0.0 && seconds.is_finite()) {
+        return Err(Error::Runtime(
+            0,
+            "sleep() expects a number of seconds that isn't negative".to_string(),
+            args[0].repr(),
+        ));
+    }
+    thread::sleep(Duration::from_secs_f64(seconds));
+    Ok(Value::Nil)
+}
+
+/// The numbers from `start` up to, but not including, `end`, as
+/// `range(end)`, `range(start, end)` or `range(start, end, step)`
+fn range(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    let mut bounds = [0.0, 0.0, 1.0];
+    for (i, bound) in bounds.iter_mut().take(args.len()).enumerate() {
+        *bound = number_arg("range", args, i)?;
+    }
+    if args.len() == 1 {
+        bounds.swap(0, 1);
+    }
+    let [start, end, step] = bounds;
+    if step == 0.0 || !step.is_finite() {
+        return Err(Error::Runtime(
+            0,
+            "range() expects a step that isn't 0".to_string(),
+            args[2].repr(),
+        ));
+    }
+    Ok(Value::Range(start, end, step))
+}
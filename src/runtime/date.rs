@@ -0,0 +1,219 @@
+//! The built-in `date` library, converting between timestamps, in seconds
+//! since the Unix epoch, and UTC dates written with `%` patterns:
+//! `%Y` year, `%m` month, `%d` day, `%H` hours, `%M` minutes, `%S` seconds
+//! and `%%` a percent sign
+
+use std::rc::Rc;
+
+use super::function::{number_arg, string_arg, NativeFn, NativeFunction};
+use super::interpreter::Interpreter;
+use super::module::Module;
+use super::value::Value;
+use crate::result::{Error, Result};
+
+const SECONDS_PER_DAY: i64 = 86_400;
+/// 0000-01-01 00:00:00, `%Y` writes and reads four digit years
+const MIN_TIMESTAMP: f64 = -62_167_219_200.0;
+/// 10000-01-01 00:00:00, the first timestamp out of range
+const MAX_TIMESTAMP: f64 = 253_402_300_800.0;
+
+/// Builds the `date` module, which is also defined as a global
+pub fn module() -> Module {
+    let functions: &[(&str, usize, NativeFn)] = &[("format", 2, format), ("parse", 2, parse)];
+    let members = functions
+        .iter()
+        .map(|&(name, arity, func)| {
+            let function = NativeFunction::new(name, arity, func);
+            (name, Value::Native(Rc::new(function)))
+        })
+        .collect();
+    Module::library("date", members)
+}
+
+/// A point in time split into UTC calendar fields
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct DateTime {
+    year: i64,
+    month: i64,
+    day: i64,
+    hour: i64,
+    minute: i64,
+    second: i64,
+}
+
+impl DateTime {
+    fn from_timestamp(timestamp: f64) -> DateTime {
+        let seconds = timestamp.floor() as i64;
+        let days = seconds.div_euclid(SECONDS_PER_DAY);
+        let time = seconds.rem_euclid(SECONDS_PER_DAY);
+        let (year, month, day) = civil_from_days(days);
+        DateTime {
+            year,
+            month,
+            day,
+            hour: time / 3600,
+            minute: time / 60 % 60,
+            second: time % 60,
+        }
+    }
+
+    fn timestamp(&self) -> f64 {
+        let days = days_from_civil(self.year, self.month, self.day);
+        (days * SECONDS_PER_DAY + self.hour * 3600 + self.minute * 60 + self.second) as f64
+    }
+
+    fn is_valid(&self) -> bool {
+        (1..=12).contains(&self.month)
+            && (1..=days_in_month(self.year, self.month)).contains(&self.day)
+            && self.hour < 24
+            && self.minute < 60
+            && self.second < 60
+    }
+}
+
+/// The date of a day counted from 1970-01-01, after Howard Hinnant's
+/// `civil_from_days`
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// The inverse of `civil_from_days`
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+fn unknown_directive(function: &str, directive: Option<char>) -> Error {
+    Error::Runtime(
+        0,
+        format!("{}() doesn't know the directive", function),
+        directive.map_or("%".to_string(), |c| format!("%{}", c)),
+    )
+}
+
+/// Writes a timestamp as a UTC date, e.g. `format(0, "%Y-%m-%d")`
+fn format(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    let timestamp = number_arg("date.format", args, 0)?;
+    let pattern = string_arg("date.format", args, 1)?;
+    if !(MIN_TIMESTAMP..MAX_TIMESTAMP).contains(&timestamp) {
+        return Err(Error::Runtime(
+            0,
+            "date.format() expects a timestamp from year 0 to 9999".to_string(),
+            args[0].repr(),
+        ));
+    }
+    let date = DateTime::from_timestamp(timestamp);
+    let mut out = String::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('Y') => out.push_str(&format!("{:04}", date.year)),
+            Some('m') => out.push_str(&format!("{:02}", date.month)),
+            Some('d') => out.push_str(&format!("{:02}", date.day)),
+            Some('H') => out.push_str(&format!("{:02}", date.hour)),
+            Some('M') => out.push_str(&format!("{:02}", date.minute)),
+            Some('S') => out.push_str(&format!("{:02}", date.second)),
+            Some('%') => out.push('%'),
+            other => return Err(unknown_directive("date.format", other)),
+        }
+    }
+    Ok(Value::Str(Rc::from(out)))
+}
+
+/// Reads a UTC date written with the pattern back into a timestamp,
+/// fields the pattern leaves out are those of 1970-01-01 00:00:00
+fn parse(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    let text = string_arg("date.parse", args, 0)?;
+    let pattern = string_arg("date.parse", args, 1)?;
+    let mismatch = || {
+        Error::Runtime(
+            0,
+            format!("date.parse() can't match the pattern {:?}", pattern),
+            args[0].repr(),
+        )
+    };
+
+    let mut date = DateTime::from_timestamp(0.0);
+    let mut rest = text;
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        let (field, width) = match c {
+            '%' => match chars.next() {
+                Some('Y') => (&mut date.year, 4),
+                Some('m') => (&mut date.month, 2),
+                Some('d') => (&mut date.day, 2),
+                Some('H') => (&mut date.hour, 2),
+                Some('M') => (&mut date.minute, 2),
+                Some('S') => (&mut date.second, 2),
+                Some('%') => {
+                    rest = rest.strip_prefix('%').ok_or_else(mismatch)?;
+                    continue;
+                }
+                other => return Err(unknown_directive("date.parse", other)),
+            },
+            c => {
+                rest = rest.strip_prefix(c).ok_or_else(mismatch)?;
+                continue;
+            }
+        };
+        let digits = rest.get(..width).ok_or_else(mismatch)?;
+        if !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(mismatch());
+        }
+        *field = digits.parse().map_err(|_| mismatch())?;
+        rest = &rest[width..];
+    }
+    if !rest.is_empty() || !date.is_valid() {
+        return Err(mismatch());
+    }
+    Ok(Value::Number(date.timestamp()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calendar_conversions() {
+        let date = |timestamp| {
+            let d = DateTime::from_timestamp(timestamp);
+            (d.year, d.month, d.day, d.hour, d.minute, d.second)
+        };
+        assert_eq!(date(0.0), (1970, 1, 1, 0, 0, 0));
+        assert_eq!(date(951_825_599.5), (2000, 2, 29, 11, 59, 59));
+        assert_eq!(date(-1.0), (1969, 12, 31, 23, 59, 59));
+        assert_eq!(date(MIN_TIMESTAMP), (0, 1, 1, 0, 0, 0));
+        assert_eq!(date(MAX_TIMESTAMP - 1.0), (9999, 12, 31, 23, 59, 59));
+        for &days in &[-800_000, -1, 0, 59, 10_957, 2_932_896] {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use super::value::Value;
+use crate::frontend::token::Token;
+use crate::result::{Error, Result};
+
+/// A scope of variable bindings, chained to its enclosing scope
+#[derive(Debug, Default)]
+pub struct Environment {
+    values: HashMap<String, Value>,
+    enclosing: Option<Rc<RefCell<Environment>>>,
+}
+
+impl Environment {
+    pub fn new() -> Environment {
+        Environment::default()
+    }
+
+    pub fn with_enclosing(enclosing: Rc<RefCell<Environment>>) -> Environment {
+        Environment {
+            values: HashMap::new(),
+            enclosing: Some(enclosing),
+        }
+    }
+
+    pub fn define(&mut self, name: &str, value: Value) {
+        self.values.insert(name.to_string(), value);
+    }
+
+    /// Looks a variable up by name, walking the enclosing scopes
+    pub fn lookup(&self, name: &str) -> Option<Value> {
+        match self.values.get(name) {
+            Some(value) => Some(value.clone()),
+            None => self.enclosing.as_ref()?.borrow().lookup(name),
+        }
+    }
+
+    /// Whether this scope is directly inside `scope`
+    pub fn is_inside(&self, scope: &Rc<RefCell<Environment>>) -> bool {
+        self.enclosing
+            .as_ref()
+            .is_some_and(|enclosing| Rc::ptr_eq(enclosing, scope))
+    }
+
+    /// Looks a variable up in this scope only
+    pub fn lookup_local(&self, name: &str) -> Option<Value> {
+        self.values.get(name).cloned()
+    }
+
+    pub fn get(&self, name: &Token) -> Result<Value> {
+        self.lookup(&name.val)
+            .ok_or_else(|| undefined_variable(name))
+    }
+
+    pub fn assign(&mut self, name: &Token, value: Value) -> Result<()> {
+        if let Some(v) = self.values.get_mut(&name.val) {
+            *v = value;
+            return Ok(());
+        }
+        match self.enclosing {
+            Some(ref enclosing) => enclosing.borrow_mut().assign(name, value),
+            None => Err(undefined_variable(name)),
+        }
+    }
+}
+
+fn undefined_variable(name: &Token) -> Error {
+    Error::Runtime(
+        name.line as u64,
+        "Undefined variable".to_string(),
+        name.val.clone(),
+    )
+}
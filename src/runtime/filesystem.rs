@@ -0,0 +1,104 @@
+//! The built-in `fs` library, failures are raised as I/O errors
+
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::rc::Rc;
+
+use super::function::{string_arg, NativeFn, NativeFunction};
+use super::interpreter::Interpreter;
+use super::module::Module;
+use super::value::Value;
+use crate::result::Result;
+
+/// Builds the `fs` module, which is also defined as a global
+pub fn module() -> Module {
+    let functions: &[(&str, usize, NativeFn)] = &[
+        ("readFile", 1, read_file),
+        ("writeFile", 2, write_file),
+        ("appendFile", 2, append_file),
+        ("readLines", 1, read_lines),
+        ("exists", 1, exists),
+        ("listDir", 1, list_dir),
+        ("mkdir", 1, mkdir),
+        ("remove", 1, remove),
+    ];
+    let members = functions
+        .iter()
+        .map(|&(name, arity, func)| {
+            let function = NativeFunction::new(name, arity, func);
+            (name, Value::Native(Rc::new(function)))
+        })
+        .collect();
+    Module::library("fs", members)
+}
+
+fn string(s: &str) -> Value {
+    Value::Str(Rc::from(s))
+}
+
+fn read_file(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    let path = string_arg("fs.readFile", args, 0)?;
+    Ok(string(&fs::read_to_string(path)?))
+}
+
+/// Creates the file or replaces its content
+fn write_file(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    let path = string_arg("fs.writeFile", args, 0)?;
+    let text = string_arg("fs.writeFile", args, 1)?;
+    fs::write(path, text)?;
+    Ok(Value::Nil)
+}
+
+/// Adds to the end of the file, creating it if needed
+fn append_file(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    let path = string_arg("fs.appendFile", args, 0)?;
+    let text = string_arg("fs.appendFile", args, 1)?;
+    fs::OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(path)?
+        .write_all(text.as_bytes())?;
+    Ok(Value::Nil)
+}
+
+/// The lines of the file, without their line endings
+fn read_lines(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    let path = string_arg("fs.readLines", args, 0)?;
+    let text = fs::read_to_string(path)?;
+    Ok(Value::list(text.lines().map(string).collect()))
+}
+
+fn exists(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    let path = string_arg("fs.exists", args, 0)?;
+    Ok(Value::Bool(Path::new(path).exists()))
+}
+
+/// The names of the entries of a directory, sorted
+fn list_dir(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    let path = string_arg("fs.listDir", args, 0)?;
+    let mut names = vec![];
+    for entry in fs::read_dir(path)? {
+        names.push(entry?.file_name().to_string_lossy().into_owned());
+    }
+    names.sort();
+    Ok(Value::list(names.iter().map(|name| string(name)).collect()))
+}
+
+/// Creates a directory and any missing parents
+fn mkdir(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    let path = string_arg("fs.mkdir", args, 0)?;
+    fs::create_dir_all(path)?;
+    Ok(Value::Nil)
+}
+
+/// Removes a file or an empty directory
+fn remove(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    let path = string_arg("fs.remove", args, 0)?;
+    if Path::new(path).is_dir() {
+        fs::remove_dir(path)?;
+    } else {
+        fs::remove_file(path)?;
+    }
+    Ok(Value::Nil)
+}
@@ -0,0 +1,156 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+use super::environment::Environment;
+use super::generator::Generator;
+use super::interpreter::Interpreter;
+use super::value::Value;
+use crate::frontend::ast::stmt::FunctionDecl;
+use crate::result::{Error, Result};
+
+/// Signature of functions implemented in Rust.
+/// Bound methods receive their receiver as the first argument.
+pub type NativeFn = fn(&mut Interpreter, &[Value]) -> Result<Value>;
+
+/// A function or method declared in Lox
+#[derive(Debug)]
+pub struct LoxFunction {
+    pub decl: Rc<FunctionDecl>,
+    pub closure: Rc<RefCell<Environment>>,
+    pub is_initializer: bool,
+    /// the script the function was declared in
+    pub file: Option<Rc<str>>,
+}
+
+impl LoxFunction {
+    pub fn new(
+        decl: Rc<FunctionDecl>,
+        closure: Rc<RefCell<Environment>>,
+        is_initializer: bool,
+    ) -> LoxFunction {
+        LoxFunction {
+            decl,
+            closure,
+            is_initializer,
+            file: None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.decl.name.val
+    }
+
+    pub fn arity(&self) -> usize {
+        self.decl.params.len()
+    }
+
+    /// Creates a copy of the method whose closure defines `this`
+    pub fn bind(&self, instance: Value) -> LoxFunction {
+        let mut env = Environment::with_enclosing(self.closure.clone());
+        env.define("this", instance);
+        let mut method = LoxFunction::new(
+            self.decl.clone(),
+            Rc::new(RefCell::new(env)),
+            self.is_initializer,
+        );
+        method.file = self.file.clone();
+        method
+    }
+
+    pub fn call(&self, interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value> {
+        let mut env = Environment::with_enclosing(self.closure.clone());
+        for (param, arg) in self.decl.params.iter().zip(args) {
+            env.define(&param.val, arg);
+        }
+        if self.decl.generator {
+            let env = Rc::new(RefCell::new(env));
+            let generator = Generator::new(self.decl.clone(), env, self.file.clone());
+            return Ok(Value::Generator(Rc::new(RefCell::new(generator))));
+        }
+
+        let value = match interpreter.execute_block(&self.decl.body, env) {
+            Ok(()) => Value::Nil,
+            Err(Error::Return(value)) => value,
+            Err(e) => return Err(e),
+        };
+
+        if self.is_initializer {
+            return Ok(self.closure.borrow().lookup("this").unwrap_or(Value::Nil));
+        }
+        Ok(value)
+    }
+}
+
+/// A function implemented in Rust
+#[derive(Clone)]
+pub struct NativeFunction {
+    pub name: String,
+    pub arity: usize,
+    /// how many arguments after the first `arity` may be left out
+    pub optional: usize,
+    pub func: NativeFn,
+    /// the value a built-in method was looked up on
+    pub receiver: Option<Value>,
+}
+
+impl NativeFunction {
+    pub fn new(name: &str, arity: usize, func: NativeFn) -> NativeFunction {
+        NativeFunction {
+            name: name.to_string(),
+            arity,
+            optional: 0,
+            func,
+            receiver: None,
+        }
+    }
+
+    /// Lets the last `optional` of `arity` arguments be left out
+    pub fn with_optional(mut self, optional: usize) -> NativeFunction {
+        self.arity -= optional;
+        self.optional = optional;
+        self
+    }
+
+    pub fn bind(mut self, receiver: Value) -> NativeFunction {
+        self.receiver = Some(receiver);
+        self
+    }
+
+    pub fn call(&self, interpreter: &mut Interpreter, mut args: Vec<Value>) -> Result<Value> {
+        if let Some(ref receiver) = self.receiver {
+            args.insert(0, receiver.clone());
+        }
+        (self.func)(interpreter, &args)
+    }
+}
+
+/// The `index`th argument of the native `function`, which must be a number
+pub fn number_arg(function: &str, args: &[Value], index: usize) -> Result<f64> {
+    match args[index] {
+        Value::Number(n) => Ok(n),
+        ref arg => Err(Error::Runtime(
+            0,
+            format!("{}() expects a number as argument {}", function, index + 1),
+            arg.repr(),
+        )),
+    }
+}
+
+/// The `index`th argument of the native `function`, which must be a string
+pub fn string_arg<'a>(function: &str, args: &'a [Value], index: usize) -> Result<&'a str> {
+    match args[index] {
+        Value::Str(ref s) => Ok(s),
+        ref arg => Err(Error::Runtime(
+            0,
+            format!("{}() expects a string as argument {}", function, index + 1),
+            arg.repr(),
+        )),
+    }
+}
+
+impl fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
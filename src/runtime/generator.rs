@@ -0,0 +1,84 @@
+//! Generators, returned by calls to `fun*` functions
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::environment::Environment;
+use super::function::{NativeFn, NativeFunction};
+use super::interpreter::{Interpreter, Resume};
+use super::value::Value;
+use crate::frontend::ast::stmt::FunctionDecl;
+use crate::result::Result;
+
+/// A call to a generator function, run a `yield` at a time
+pub struct Generator {
+    pub decl: Rc<FunctionDecl>,
+    /// the scope of the call, with the arguments bound
+    pub env: Rc<RefCell<Environment>>,
+    /// the script the function is in
+    pub file: Option<Rc<str>>,
+    pub state: State,
+}
+
+pub enum State {
+    /// not started yet
+    Created,
+    /// stopped at a `yield`, where it was in each statement it is in,
+    /// innermost first
+    Suspended(Vec<Resume>),
+    Running,
+    Done,
+}
+
+impl Generator {
+    pub fn new(
+        decl: Rc<FunctionDecl>,
+        env: Rc<RefCell<Environment>>,
+        file: Option<Rc<str>>,
+    ) -> Generator {
+        Generator {
+            decl,
+            env,
+            file,
+            state: State::Created,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.decl.name.val
+    }
+}
+
+/// Looks up a built-in generator method, e.g. `g.next()`
+pub fn method(name: &str) -> Option<NativeFunction> {
+    let (arity, func): (usize, NativeFn) = match name {
+        "next" => (0, next),
+        "send" => (1, send),
+        "done" => (0, done),
+        _ => return None,
+    };
+    Some(NativeFunction::new(name, arity, func))
+}
+
+fn receiver(args: &[Value]) -> &Rc<RefCell<Generator>> {
+    match args[0] {
+        Value::Generator(ref generator) => generator,
+        _ => unreachable!("generator methods are only bound to generators"),
+    }
+}
+
+/// The next yielded value, or nil once the generator is done. A yielded
+/// nil is told apart by `done()` being false after it.
+fn next(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    interpreter.resume(receiver(args), Value::Nil)
+}
+
+/// Like `next()`, with the suspended `yield` giving `value`
+fn send(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    interpreter.resume(receiver(args), args[1].clone())
+}
+
+fn done(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    let done = matches!(receiver(args).borrow().state, State::Done);
+    Ok(Value::Bool(done))
+}
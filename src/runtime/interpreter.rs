@@ -0,0 +1,2695 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::slice;
+use std::vec;
+
+use super::builtins;
+use super::class::{LoxClass, LoxInstance};
+use super::date;
+use super::environment::Environment;
+use super::filesystem;
+use super::function::LoxFunction;
+use super::generator::{self, Generator, State};
+use super::json;
+use super::list;
+use super::map::{self, LoxMap};
+use super::math;
+use super::module::{self, Module};
+use super::re;
+use super::string;
+use super::value::Value;
+use crate::frontend::ast::expr::{
+    BinaryExpr, BinaryOperator, CompoundAssignExpr, Expr, IncrementExpr,
+};
+use crate::frontend::ast::stmt::{ClassDecl, ForInStmt, ImportStmt, Stmt, TryStmt};
+use crate::frontend::parser::Parser;
+use crate::frontend::scanner::{self, Scanner};
+use crate::frontend::token::{Token, TokenType};
+use crate::result::{Error, Result, StackFrame};
+
+/// Lox code every interpreter starts with
+const PRELUDE: &str = "
+class Error {
+    init(message) {
+        this.message = message;
+    }
+}
+";
+
+/// Calls nested deeper than this raise a "Stack overflow" error
+const MAX_FRAMES: usize = 1000;
+
+/// Stack size of a thread running Lox code, enough for `MAX_FRAMES`
+/// calls even in debug builds
+pub const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+/// A place in a script
+#[derive(Debug, Clone)]
+struct Position {
+    file: Option<Rc<str>>,
+    line: usize,
+    column: usize,
+}
+
+/// A call in progress
+#[derive(Debug, Clone)]
+struct Frame {
+    function: String,
+    native: bool,
+    /// where the call is, in the caller
+    call: Position,
+}
+
+impl Frame {
+    /// The frame of a stack trace, `at` is where the function is
+    fn stack_frame(&self, at: Position) -> StackFrame {
+        if self.native {
+            return StackFrame {
+                function: self.function.clone(),
+                file: None,
+                line: 0,
+                column: 0,
+                native: true,
+            };
+        }
+        StackFrame {
+            function: self.function.clone(),
+            file: at.file.map(|f| f.to_string()),
+            line: at.line,
+            column: at.column,
+            native: false,
+        }
+    }
+}
+
+/// What a `for (x in ...)` loop goes through
+pub enum Elements {
+    /// read as the loop goes, so elements pushed meanwhile are seen
+    List(Rc<RefCell<Vec<Value>>>),
+    /// map entries, taken when the loop starts
+    Entries(vec::IntoIter<(Value, Value)>),
+    Chars(vec::IntoIter<char>),
+    /// start, end and step
+    Range(f64, f64, f64),
+    /// an object whose `next()` returns nil once it's done
+    Iterator(Value),
+    /// run until it's done, so it can yield nil
+    Generator(Rc<RefCell<Generator>>),
+}
+
+/// Where a suspended generator is in one of the statements it was running
+pub enum Resume {
+    /// at the statement `index` of a block run in `env`
+    Block(usize, Rc<RefCell<Environment>>),
+    /// in the branch taken, the `then` one if true
+    If(bool),
+    /// in the body of a `while` loop
+    While,
+    /// in the body of a `for-in` loop, run for the element `index` in `env`
+    ForIn(Elements, usize, Rc<RefCell<Environment>>),
+    Try(TryPart),
+    /// in an expression, with the operands it evaluated before
+    Operands(Vec<Value>),
+    /// at the `yield`, with the value sent back in once resumed
+    Yield(Value),
+}
+
+/// The operands of an expression a generator is running, evaluated in
+/// order. Those evaluated before it was suspended are given back rather
+/// than evaluated again once it's resumed.
+#[derive(Default)]
+struct Operands {
+    values: Vec<Value>,
+    /// how many of `values` were given back
+    used: usize,
+}
+
+impl Operands {
+    /// The next operand if it was evaluated before
+    fn next(&mut self) -> Option<Value> {
+        let value = self.values.get(self.used).cloned();
+        if value.is_some() {
+            self.used += 1;
+        }
+        value
+    }
+
+    /// Keeps the value of the next operand
+    fn keep(&mut self, value: Value) -> Value {
+        self.values.push(value.clone());
+        self.used += 1;
+        value
+    }
+}
+
+/// The part of a `try` statement a generator is suspended in
+pub enum TryPart {
+    Body,
+    Catch,
+    /// with what the `try` ends with after `finally`, and its trace
+    Finally(Result<()>, Vec<StackFrame>),
+}
+
+/// A tree-walking interpreter for Lox programs
+pub struct Interpreter {
+    /// the prelude, native functions and libraries, enclosing the globals
+    /// of every module
+    builtins: Rc<RefCell<Environment>>,
+    pub globals: Rc<RefCell<Environment>>,
+    environment: Rc<RefCell<Environment>>,
+    out: Box<dyn Write>,
+    /// the script being run
+    file: Option<Rc<str>>,
+    /// calls in progress, outermost first
+    frames: Vec<Frame>,
+    /// calls left by the error being raised, innermost first
+    unwound: Vec<StackFrame>,
+    /// where the error being raised is in the innermost call not in
+    /// `unwound` yet
+    raised: Option<Position>,
+    /// where the error that ended the last `interpret` was raised
+    trace: Vec<StackFrame>,
+    /// the class of the objects caught runtime errors become
+    error_class: Rc<LoxClass>,
+    /// directories searched for imported scripts not found next to the
+    /// importing one
+    search_path: Vec<PathBuf>,
+    /// modules by canonical path, each is only run once
+    modules: HashMap<PathBuf, Rc<Module>>,
+    /// scripts being run, the main one first
+    loading: Vec<PathBuf>,
+    /// what `args()` returns
+    args: Vec<String>,
+}
+
+impl Default for Interpreter {
+    fn default() -> Interpreter {
+        Interpreter::new()
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Interpreter {
+        Interpreter::with_output(Box::new(io::stdout()))
+    }
+
+    /// Creates an interpreter whose `print` statements write to `out`
+    pub fn with_output(out: Box<dyn Write>) -> Interpreter {
+        let builtins = Rc::new(RefCell::new(Environment::new()));
+        let globals = Rc::new(RefCell::new(Environment::with_enclosing(builtins.clone())));
+        let mut interpreter = Interpreter {
+            environment: builtins.clone(),
+            builtins,
+            globals,
+            out,
+            file: None,
+            frames: vec![],
+            unwound: vec![],
+            raised: None,
+            trace: vec![],
+            error_class: Rc::new(LoxClass::new("Error", None, HashMap::new())),
+            search_path: vec![],
+            modules: HashMap::new(),
+            loading: vec![],
+            args: vec![],
+        };
+        let prelude = Scanner::new(PRELUDE)
+            .scan_tokens()
+            .and_then(|tokens| Parser::new(tokens).parse())
+            .expect("the prelude is valid Lox");
+        interpreter
+            .interpret(&prelude)
+            .expect("the prelude runs without errors");
+        if let Some(Value::Class(class)) = interpreter.builtins.borrow().lookup("Error") {
+            interpreter.error_class = class;
+        }
+        {
+            let mut builtins = interpreter.builtins.borrow_mut();
+            for function in builtins::functions() {
+                let name = function.name.clone();
+                builtins.define(&name, Value::Native(Rc::new(function)));
+            }
+            builtins.define("math", Value::Module(Rc::new(math::module())));
+            builtins.define("fs", Value::Module(Rc::new(filesystem::module())));
+            builtins.define("date", Value::Module(Rc::new(date::module())));
+            builtins.define("json", Value::Module(Rc::new(json::module())));
+            builtins.define("re", Value::Module(Rc::new(re::module())));
+        }
+        interpreter.environment = interpreter.globals.clone();
+        interpreter
+    }
+
+    /// Names the script being run, functions declared from now on are in
+    /// it and imports are resolved relative to it
+    pub fn set_file(&mut self, file: &str) {
+        self.file = Some(Rc::from(file));
+        self.loading = fs::canonicalize(file).into_iter().collect();
+    }
+
+    /// Sets the command-line arguments given to the script
+    pub fn set_args(&mut self, args: Vec<String>) {
+        self.args = args;
+    }
+
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+
+    /// Adds a directory to look for imported scripts in
+    pub fn add_search_path<P: Into<PathBuf>>(&mut self, dir: P) {
+        self.search_path.push(dir.into());
+    }
+
+    pub fn interpret(&mut self, statements: &[Stmt]) -> Result<()> {
+        self.unwound.clear();
+        self.raised = None;
+        self.trace.clear();
+        for stmt in statements {
+            if let Err(e) = self.execute(stmt) {
+                if let Error::Runtime(line, ..) | Error::Throw(line, _) = e {
+                    self.trace = self.take_trace(line);
+                }
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// The calls the runtime error that ended the last `interpret` was
+    /// raised in, innermost first
+    pub fn stack_trace(&self) -> &[StackFrame] {
+        &self.trace
+    }
+
+    fn execute(&mut self, stmt: &Stmt) -> Result<()> {
+        match *stmt {
+            Stmt::Expression(ref expr) => {
+                self.evaluate(expr)?;
+            }
+            Stmt::Print(ref expr) => {
+                let value = self.evaluate(expr)?;
+                writeln!(self.out, "{}", value)?;
+            }
+            Stmt::Var(ref name, ref initializer) => {
+                let value = match *initializer {
+                    Some(ref expr) => self.evaluate(expr)?,
+                    None => Value::Nil,
+                };
+                self.declaration_scope();
+                self.environment.borrow_mut().define(&name.val, value);
+            }
+            Stmt::Block(ref statements) => {
+                let env = Environment::with_enclosing(self.environment.clone());
+                self.execute_block(statements, env)?;
+            }
+            Stmt::If(ref stmt) => {
+                if self.evaluate(&stmt.condition)?.is_truthy() {
+                    self.execute(&stmt.then_branch)?;
+                } else if let Some(ref else_branch) = stmt.else_branch {
+                    self.execute(else_branch)?;
+                }
+            }
+            Stmt::While(ref stmt) => {
+                while self.evaluate(&stmt.condition)?.is_truthy() {
+                    match self.execute(&stmt.body) {
+                        Err(Error::Break(_)) => break,
+                        result => result?,
+                    }
+                }
+            }
+            Stmt::ForIn(ref stmt) => self.for_in(stmt)?,
+            Stmt::Function(ref decl) => {
+                // in the function's own scope, so it can call itself
+                self.declaration_scope();
+                let mut function =
+                    LoxFunction::new(decl.clone(), self.environment.clone(), false);
+                function.file = self.file.clone();
+                self.environment
+                    .borrow_mut()
+                    .define(&decl.name.val, Value::Function(Rc::new(function)));
+            }
+            Stmt::Return(_, ref value) => {
+                let value = match *value {
+                    Some(ref expr) => self.evaluate(expr)?,
+                    None => Value::Nil,
+                };
+                return Err(Error::Return(value));
+            }
+            Stmt::Class(ref decl) => self.class_declaration(decl)?,
+            Stmt::Break(ref keyword) => return Err(Error::Break(keyword.line as u64)),
+            Stmt::Throw(ref keyword, ref value) => {
+                let value = self.evaluate(value)?;
+                self.raised = Some(self.position(keyword));
+                return Err(Error::Throw(keyword.line as u64, value));
+            }
+            Stmt::Try(ref stmt) => self.try_statement(stmt)?,
+            Stmt::Import(ref stmt) => self.import(stmt)?,
+        }
+        Ok(())
+    }
+
+    /// Runs the body once per element, with the element, or with the index
+    /// or key and the element, bound to the loop variables
+    fn for_in(&mut self, stmt: &ForInStmt) -> Result<()> {
+        let iterable = self.evaluate(&stmt.iterable)?;
+        let mut elements = self.elements(&iterable, &stmt.keyword)?;
+        let keyed = matches!(elements, Elements::Entries(_));
+        for index in 0.. {
+            let (key, element) = match self.next_element(&mut elements, index, &stmt.keyword)? {
+                Some(pair) => pair,
+                None => break,
+            };
+            let env = self.loop_scope(stmt, keyed, key, element);
+            match self.execute_block(slice::from_ref(&stmt.body), env) {
+                Err(Error::Break(_)) => break,
+                result => result?,
+            }
+        }
+        Ok(())
+    }
+
+    /// The scope of one run of a `for-in` loop body
+    fn loop_scope(&self, stmt: &ForInStmt, keyed: bool, key: Value, element: Value) -> Environment {
+        let mut env = Environment::with_enclosing(self.environment.clone());
+        match stmt.variables[..] {
+            [ref name] if keyed => env.define(&name.val, key),
+            [ref name] => env.define(&name.val, element),
+            [ref first, ref second, ..] => {
+                env.define(&first.val, key);
+                env.define(&second.val, element);
+            }
+            [] => unreachable!("the parser requires a loop variable"),
+        }
+        env
+    }
+
+    fn elements(&mut self, iterable: &Value, keyword: &Token) -> Result<Elements> {
+        Ok(match *iterable {
+            Value::List(ref list) => Elements::List(list.clone()),
+            Value::Map(ref map) => {
+                let entries: Vec<(Value, Value)> = map
+                    .borrow()
+                    .iter()
+                    .map(|(key, value)| (key.to_value(), value.clone()))
+                    .collect();
+                Elements::Entries(entries.into_iter())
+            }
+            Value::Str(ref s) => Elements::Chars(s.chars().collect::<Vec<_>>().into_iter()),
+            Value::Range(start, end, step) => Elements::Range(start, end, step),
+            Value::Generator(ref generator) => Elements::Generator(generator.clone()),
+            // an object with `iter()` makes the iterator, others are one
+            Value::Instance(ref instance) => {
+                if instance.borrow().class.find_method("iter").is_none() {
+                    return Ok(Elements::Iterator(iterable.clone()));
+                }
+                let iter = self.get_property(iterable.clone(), &renamed(keyword, "iter"))?;
+                match self.call(iter, vec![], keyword)? {
+                    Value::Generator(generator) => Elements::Generator(generator),
+                    iterator => Elements::Iterator(iterator),
+                }
+            }
+            _ => {
+                return Err(Error::Runtime(
+                    keyword.line as u64,
+                    "Can only loop over lists, maps, strings, ranges and iterators".to_string(),
+                    iterable.type_name().to_string(),
+                ))
+            }
+        })
+    }
+
+    /// The index or key and the element, or None once they ran out
+    fn next_element(
+        &mut self,
+        elements: &mut Elements,
+        index: usize,
+        keyword: &Token,
+    ) -> Result<Option<(Value, Value)>> {
+        let element = match *elements {
+            Elements::List(ref list) => list.borrow().get(index).cloned(),
+            Elements::Entries(ref mut entries) => return Ok(entries.next()),
+            Elements::Chars(ref mut chars) => chars.next().map(|c| Value::from(c.to_string())),
+            Elements::Range(start, end, step) => {
+                // computed from the start so steps don't add up errors
+                let n = start + index as f64 * step;
+                if (step > 0.0 && n < end) || (step < 0.0 && n > end) {
+                    Some(Value::Number(n))
+                } else {
+                    None
+                }
+            }
+            Elements::Iterator(ref iterator) => {
+                let next = self.get_property(iterator.clone(), &renamed(keyword, "next"))?;
+                match self.call(next, vec![], keyword)? {
+                    Value::Nil => None,
+                    element => Some(element),
+                }
+            }
+            Elements::Generator(ref generator) => {
+                let element = self.resume(generator, Value::Nil).map_err(|e| at_line(e, keyword))?;
+                match generator.borrow().state {
+                    State::Done => None,
+                    _ => Some(element),
+                }
+            }
+        };
+        Ok(element.map(|element| (Value::Number(index as f64), element)))
+    }
+
+    /// Binds the module or the names taken from it
+    fn import(&mut self, stmt: &ImportStmt) -> Result<()> {
+        let module = match self.load_module(&stmt.path) {
+            Ok(module) => module,
+            Err(e) => {
+                // errors in the module itself are already placed at the path
+                if let (Error::Runtime(..) | Error::Throw(..), None) = (&e, &self.raised) {
+                    self.raised = Some(self.position(&stmt.path));
+                }
+                return Err(e);
+            }
+        };
+        self.declaration_scope();
+        if let Some(ref alias) = stmt.alias {
+            self.environment
+                .borrow_mut()
+                .define(&alias.val, Value::Module(module.clone()));
+        }
+        for name in stmt.names.iter() {
+            let value = module.globals.borrow().lookup_local(&name.val);
+            let value = match value {
+                Some(value) => value,
+                None => {
+                    self.raised = Some(self.position(name));
+                    return Err(Error::Runtime(
+                        name.line as u64,
+                        format!("Module {} has no member", stmt.path.val),
+                        name.val.clone(),
+                    ));
+                }
+            };
+            self.environment.borrow_mut().define(&name.val, value);
+        }
+        Ok(())
+    }
+
+    /// Runs the script `path` names in a global scope of its own, unless
+    /// it already ran
+    fn load_module(&mut self, path: &Token) -> Result<Rc<Module>> {
+        if let Some(Value::Module(library)) = self.builtins.borrow().lookup_local(&path.val) {
+            return Ok(library);
+        }
+        let importer = self.file.as_deref().map(Path::new);
+        let resolved = module::resolve(&path.val, importer, &self.search_path).ok_or_else(|| {
+            Error::Runtime(
+                path.line as u64,
+                "Module not found".to_string(),
+                path.val.clone(),
+            )
+        })?;
+        if let Some(module) = self.modules.get(&resolved) {
+            return Ok(module.clone());
+        }
+        if let Some(i) = self.loading.iter().position(|p| *p == resolved) {
+            let cycle: Vec<String> = self.loading[i..]
+                .iter()
+                .chain(Some(&resolved))
+                .map(|p| p.display().to_string())
+                .collect();
+            return Err(Error::Runtime(
+                path.line as u64,
+                "Import cycle".to_string(),
+                cycle.join(" -> "),
+            ));
+        }
+
+        let invalid = |e: Error| {
+            Error::Runtime(
+                path.line as u64,
+                format!("Can't load module {}", path.val),
+                e.to_string(),
+            )
+        };
+        let source = fs::read_to_string(&resolved).map_err(|e| invalid(e.into()))?;
+        let statements = Scanner::new(&source)
+            .scan_tokens()
+            .and_then(|tokens| Parser::new(tokens).parse())
+            .map_err(invalid)?;
+
+        let globals = Rc::new(RefCell::new(Environment::with_enclosing(
+            self.builtins.clone(),
+        )));
+        let frame = Frame {
+            function: format!("<module {}>", path.val),
+            native: false,
+            call: self.position(path),
+        };
+        self.push_frame(frame, path.line)?;
+        self.loading.push(resolved.clone());
+        let file = Rc::from(resolved.to_string_lossy().as_ref());
+        let caller_file = self.file.replace(file);
+        let caller_env = mem::replace(&mut self.environment, globals.clone());
+        let result = statements.iter().try_for_each(|stmt| self.execute(stmt));
+        self.environment = caller_env;
+        self.loading.pop();
+        self.pop_frame(caller_file, &result);
+        result?;
+
+        let module = Rc::new(Module::new(resolved.clone(), globals));
+        self.modules.insert(resolved, module.clone());
+        Ok(module)
+    }
+
+    /// Runs the `catch` handler for thrown values and runtime errors, and
+    /// the `finally` block whichever way the statement is left
+    fn try_statement(&mut self, stmt: &TryStmt) -> Result<()> {
+        let env = Environment::with_enclosing(self.environment.clone());
+        let mut result = self.execute_block(&stmt.body, env);
+        if let Some((ref name, ref handler)) = stmt.catch {
+            if let Some(error) = self.catch(&mut result) {
+                let mut env = Environment::with_enclosing(self.environment.clone());
+                env.define(&name.val, error);
+                result = self.execute_block(handler, env);
+            }
+        }
+        if let Some(ref finally) = stmt.finally {
+            let env = Environment::with_enclosing(self.environment.clone());
+            // an error raised in `finally` replaces the one being raised
+            let unwound = mem::take(&mut self.unwound);
+            self.execute_block(finally, env)?;
+            self.unwound = unwound;
+        }
+        result
+    }
+
+    /// Takes a catchable error out of `result` as the value to bind
+    fn catch(&mut self, result: &mut Result<()>) -> Option<Value> {
+        let (line, value) = match *result {
+            Err(Error::Throw(line, ref value)) => (line, value.clone()),
+            Err(Error::Runtime(line, ref msg, ref near)) => {
+                let instance = LoxInstance::new(self.error_class.clone());
+                let error = Value::Instance(Rc::new(RefCell::new(instance)));
+                let message = Value::from(format!("{}: {}", msg, near));
+                set_field(&error, "message", message);
+                (line, error)
+            }
+            _ => return None,
+        };
+        *result = Ok(());
+
+        let stack = self
+            .take_trace(line)
+            .iter()
+            .map(|frame| Value::from(frame.to_string()))
+            .collect();
+        let stack = Value::list(stack);
+        if let Value::Instance(ref instance) = value {
+            let is_error = instance.borrow().class.extends(&self.error_class);
+            // a rethrown error keeps where it was first raised
+            if is_error && !instance.borrow().fields.contains_key("stack") {
+                set_field(&value, "line", Value::Number(line as f64));
+                set_field(&value, "stack", stack);
+            }
+        }
+        Some(value)
+    }
+
+    /// The calls the error being raised on `line` is in, innermost first
+    fn take_trace(&mut self, line: u64) -> Vec<StackFrame> {
+        let mut trace = mem::take(&mut self.unwound);
+        let mut at = self.raised.take().unwrap_or_else(|| Position {
+            file: self.file.clone(),
+            line: line as usize,
+            column: 0,
+        });
+        for frame in self.frames.iter().rev() {
+            trace.push(frame.stack_frame(at));
+            at = frame.call.clone();
+        }
+        trace.push(StackFrame {
+            function: "<script>".to_string(),
+            file: at.file.map(|f| f.to_string()),
+            line: at.line,
+            column: at.column,
+            native: false,
+        });
+        trace
+    }
+
+    fn position(&self, token: &Token) -> Position {
+        Position {
+            file: self.file.clone(),
+            line: token.line,
+            column: token.pos,
+        }
+    }
+
+    /// Runs a generator until its next `yield`, giving the yielded value,
+    /// or nil once it's done. The suspended `yield` gives `sent`.
+    pub fn resume(&mut self, generator: &Rc<RefCell<Generator>>, sent: Value) -> Result<Value> {
+        let (decl, env, file, mut saved) = {
+            let mut generator = generator.borrow_mut();
+            let saved = match mem::replace(&mut generator.state, State::Running) {
+                State::Created => vec![],
+                State::Suspended(saved) => saved,
+                State::Running => {
+                    return Err(Error::Runtime(
+                        0,
+                        "Generator is already running".to_string(),
+                        generator.name().to_string(),
+                    ))
+                }
+                State::Done => {
+                    generator.state = State::Done;
+                    return Ok(Value::Nil);
+                }
+            };
+            let file = generator.file.clone();
+            (generator.decl.clone(), generator.env.clone(), file, saved)
+        };
+        if let Some(Resume::Yield(value)) = saved.first_mut() {
+            *value = sent;
+        }
+
+        let frame = Frame {
+            function: decl.name.val.clone(),
+            native: false,
+            // only shown for calls of Lox functions, which resume never is
+            call: Position {
+                file: None,
+                line: 0,
+                column: 0,
+            },
+        };
+        if let Err(e) = self.push_frame(frame, 0) {
+            // left as it was, a suspended generator is at a `yield`
+            generator.borrow_mut().state = match saved.is_empty() {
+                true => State::Created,
+                false => State::Suspended(saved),
+            };
+            return Err(e);
+        }
+        let caller_file = mem::replace(&mut self.file, file);
+        let result = self.resume_block(&decl.body, move || env, &mut saved);
+        self.pop_frame(caller_file, &result);
+
+        let mut generator = generator.borrow_mut();
+        match result {
+            Err(Error::Yield(value)) => {
+                generator.state = State::Suspended(saved);
+                Ok(value)
+            }
+            Ok(()) | Err(Error::Return(_)) => {
+                generator.state = State::Done;
+                Ok(Value::Nil)
+            }
+            Err(e) => {
+                generator.state = State::Done;
+                Err(e)
+            }
+        }
+    }
+
+    /// Like `execute` in a generator. Statements with a `yield` in them
+    /// are run here so they can be suspended, and resumed from `saved`.
+    fn resume_stmt(&mut self, stmt: &Stmt, saved: &mut Vec<Resume>) -> Result<()> {
+        if saved.is_empty() && !has_yield(stmt) {
+            return self.execute(stmt);
+        }
+        match *stmt {
+            Stmt::Expression(ref expr) => self.resume_expr(expr, saved).map(drop),
+            Stmt::Print(ref expr) => {
+                let value = self.resume_expr(expr, saved)?;
+                writeln!(self.out, "{}", value)?;
+                Ok(())
+            }
+            Stmt::Var(ref name, Some(ref initializer)) => {
+                let value = self.resume_expr(initializer, saved)?;
+                self.declaration_scope();
+                self.environment.borrow_mut().define(&name.val, value);
+                Ok(())
+            }
+            Stmt::Return(_, Some(ref value)) => Err(Error::Return(self.resume_expr(value, saved)?)),
+            Stmt::Throw(ref keyword, ref value) => {
+                let value = self.resume_expr(value, saved)?;
+                self.raised = Some(self.position(keyword));
+                Err(Error::Throw(keyword.line as u64, value))
+            }
+            Stmt::Block(ref statements) => {
+                let enclosing = self.environment.clone();
+                self.resume_block(statements, move || scope(enclosing), saved)
+            }
+            Stmt::If(ref stmt) => {
+                // unless it's in a branch, it's in the condition
+                let then = match saved.last() {
+                    Some(&Resume::If(then)) => {
+                        saved.pop();
+                        then
+                    }
+                    _ => self.resume_expr(&stmt.condition, saved)?.is_truthy(),
+                };
+                let branch = match then {
+                    true => Some(&stmt.then_branch),
+                    false => stmt.else_branch.as_ref(),
+                };
+                match branch {
+                    Some(branch) => {
+                        let result = self.resume_stmt(branch, saved);
+                        on_yield(result, saved, || Resume::If(then))
+                    }
+                    None => Ok(()),
+                }
+            }
+            Stmt::While(ref stmt) => {
+                // unless it's in the body, it's in the condition
+                let mut resumed = matches!(saved.last(), Some(Resume::While));
+                if resumed {
+                    saved.pop();
+                }
+                while resumed || self.resume_expr(&stmt.condition, saved)?.is_truthy() {
+                    resumed = false;
+                    let result = self.resume_stmt(&stmt.body, saved);
+                    match on_yield(result, saved, || Resume::While) {
+                        Err(Error::Break(_)) => break,
+                        result => result?,
+                    }
+                }
+                Ok(())
+            }
+            Stmt::ForIn(ref stmt) => self.resume_for_in(stmt, saved),
+            Stmt::Try(ref stmt) => self.resume_try(stmt, saved),
+            _ => unreachable!("declarations and `break` don't hold a yield"),
+        }
+    }
+
+    /// Like `execute_block` in a generator, in the scope `fresh` makes
+    /// unless it's resumed
+    fn resume_block(
+        &mut self,
+        statements: &[Stmt],
+        fresh: impl FnOnce() -> Rc<RefCell<Environment>>,
+        saved: &mut Vec<Resume>,
+    ) -> Result<()> {
+        let (start, env) = match saved.pop() {
+            Some(Resume::Block(index, env)) => (index, env),
+            None => (0, fresh()),
+            Some(_) => unreachable!("suspended in another statement"),
+        };
+        let previous = mem::replace(&mut self.environment, env);
+        let mut result = Ok(());
+        for (index, stmt) in statements.iter().enumerate().skip(start) {
+            result = self.resume_stmt(stmt, saved);
+            let env = &self.environment;
+            result = on_yield(result, saved, || Resume::Block(index, env.clone()));
+            if result.is_err() {
+                break;
+            }
+        }
+        self.environment = previous;
+        result
+    }
+
+    fn resume_for_in(&mut self, stmt: &ForInStmt, saved: &mut Vec<Resume>) -> Result<()> {
+        // unless it's in the body, it's in the iterable
+        let in_body = match saved.last() {
+            Some(Resume::ForIn(..)) => saved.pop(),
+            _ => None,
+        };
+        let (mut elements, start, mut resumed) = match in_body {
+            Some(Resume::ForIn(elements, index, env)) => (elements, index, Some(env)),
+            _ => {
+                let iterable = self.resume_expr(&stmt.iterable, saved)?;
+                (self.elements(&iterable, &stmt.keyword)?, 0, None)
+            }
+        };
+        let keyed = matches!(elements, Elements::Entries(_));
+        for index in start.. {
+            let env = match resumed.take() {
+                Some(env) => env,
+                None => match self.next_element(&mut elements, index, &stmt.keyword)? {
+                    Some((key, element)) => {
+                        Rc::new(RefCell::new(self.loop_scope(stmt, keyed, key, element)))
+                    }
+                    None => break,
+                },
+            };
+            let previous = mem::replace(&mut self.environment, env);
+            let result = self.resume_stmt(&stmt.body, saved);
+            let env = mem::replace(&mut self.environment, previous);
+            match result {
+                Err(Error::Yield(value)) => {
+                    saved.push(Resume::ForIn(elements, index, env));
+                    return Err(Error::Yield(value));
+                }
+                Err(Error::Break(_)) => break,
+                result => result?,
+            }
+        }
+        Ok(())
+    }
+
+    fn resume_try(&mut self, stmt: &TryStmt, saved: &mut Vec<Resume>) -> Result<()> {
+        let resumed = match saved.pop() {
+            Some(Resume::Try(part)) => Some(part),
+            None => None,
+            Some(_) => unreachable!("suspended in another statement"),
+        };
+        let mut result = Ok(());
+        let mut caught = None;
+        if let None | Some(TryPart::Body) = resumed {
+            let enclosing = self.environment.clone();
+            result = self.resume_block(&stmt.body, move || scope(enclosing), saved);
+            if let Err(Error::Yield(_)) = result {
+                saved.push(Resume::Try(TryPart::Body));
+                return result;
+            }
+            if stmt.catch.is_some() {
+                caught = self.catch(&mut result);
+            }
+        }
+        if let (Some((ref name, ref handler)), true) = (
+            &stmt.catch,
+            caught.is_some() || matches!(resumed, Some(TryPart::Catch)),
+        ) {
+            let enclosing = self.environment.clone();
+            let fresh = move || {
+                let mut env = Environment::with_enclosing(enclosing);
+                env.define(&name.val, caught.unwrap_or(Value::Nil));
+                Rc::new(RefCell::new(env))
+            };
+            result = self.resume_block(handler, fresh, saved);
+            if let Err(Error::Yield(_)) = result {
+                saved.push(Resume::Try(TryPart::Catch));
+                return result;
+            }
+        }
+        if let Some(ref finally) = stmt.finally {
+            let (pending, unwound) = match resumed {
+                Some(TryPart::Finally(pending, unwound)) => (pending, unwound),
+                // an error raised in `finally` replaces the one being raised
+                _ => (result, mem::take(&mut self.unwound)),
+            };
+            let enclosing = self.environment.clone();
+            match self.resume_block(finally, move || scope(enclosing), saved) {
+                Err(Error::Yield(value)) => {
+                    saved.push(Resume::Try(TryPart::Finally(pending, unwound)));
+                    return Err(Error::Yield(value));
+                }
+                finished => finished?,
+            }
+            self.unwound = unwound;
+            result = pending;
+        }
+        result
+    }
+
+    /// Like `evaluate` in a generator. Expressions with a `yield` in them
+    /// are evaluated here so they can be suspended, keeping the operands
+    /// they evaluated in `saved`.
+    fn resume_expr(&mut self, expr: &Expr, saved: &mut Vec<Resume>) -> Result<Value> {
+        if saved.is_empty() && !expr_has_yield(expr) {
+            return self.evaluate(expr);
+        }
+        let mut operands = match saved.pop() {
+            Some(Resume::Operands(values)) => Operands { values, used: 0 },
+            None => Operands::default(),
+            Some(_) => unreachable!("suspended in another expression"),
+        };
+        let result = self.resume_operation(expr, &mut operands, saved);
+        match result {
+            Err(Error::Yield(_)) => saved.push(Resume::Operands(operands.values)),
+            Err(Error::Runtime(..)) | Err(Error::Throw(..)) if self.raised.is_none() => {
+                self.raised = expr.token().map(|t| self.position(t));
+            }
+            _ => (),
+        }
+        result
+    }
+
+    /// The next operand of an expression a generator is running
+    fn operand(
+        &mut self,
+        operands: &mut Operands,
+        expr: &Expr,
+        saved: &mut Vec<Resume>,
+    ) -> Result<Value> {
+        match operands.next() {
+            Some(value) => Ok(value),
+            None => {
+                let value = self.resume_expr(expr, saved)?;
+                Ok(operands.keep(value))
+            }
+        }
+    }
+
+    /// Like `evaluate_expr`, with the operands taken from `operands`
+    fn resume_operation(
+        &mut self,
+        expr: &Expr,
+        operands: &mut Operands,
+        saved: &mut Vec<Resume>,
+    ) -> Result<Value> {
+        match *expr {
+            Expr::Yield(_, ref value) => {
+                let value = match *value {
+                    Some(ref value) => self.operand(operands, value, saved)?,
+                    None => Value::Nil,
+                };
+                match saved.pop() {
+                    Some(Resume::Yield(sent)) => Ok(sent),
+                    None => {
+                        // `resume` fills in the value sent back in
+                        saved.push(Resume::Yield(Value::Nil));
+                        Err(Error::Yield(value))
+                    }
+                    Some(_) => unreachable!("suspended in another expression"),
+                }
+            }
+            Expr::Grouping(ref expr) => self.operand(operands, expr, saved),
+            Expr::Unary(ref op, ref right) => {
+                let right = self.operand(operands, right, saved)?;
+                unary(op, right)
+            }
+            Expr::BinaryExpr(ref binary) => {
+                let left = self.operand(operands, &binary.lhs, saved)?;
+                let right = self.operand(operands, &binary.rhs, saved)?;
+                binary_operation(binary, left, right)
+            }
+            Expr::Logical(ref logical) => {
+                let left = self.operand(operands, &logical.lhs, saved)?;
+                if short_circuits(&logical.op, &left) {
+                    return Ok(left);
+                }
+                self.operand(operands, &logical.rhs, saved)
+            }
+            Expr::Conditional(ref conditional) => {
+                let condition = self.operand(operands, &conditional.condition, saved)?;
+                let branch = match condition.is_truthy() {
+                    true => &conditional.then_branch,
+                    false => &conditional.else_branch,
+                };
+                self.operand(operands, branch, saved)
+            }
+            Expr::Comma(ref comma) => {
+                self.operand(operands, &comma.lhs, saved)?;
+                self.operand(operands, &comma.rhs, saved)
+            }
+            Expr::Assign(ref name, ref value) => {
+                let value = self.operand(operands, value, saved)?;
+                self.environment.borrow_mut().assign(name, value.clone())?;
+                Ok(value)
+            }
+            Expr::CompoundAssign(ref assign) => self.compound_assign(assign, operands, saved),
+            Expr::Increment(ref increment) => self.increment(increment, operands, saved),
+            Expr::Call(ref call) => {
+                let callee = self.operand(operands, &call.callee, saved)?;
+                let mut args = Vec::with_capacity(call.args.len());
+                for arg in call.args.iter() {
+                    args.push(self.operand(operands, arg, saved)?);
+                }
+                self.call(callee, args, &call.paren)
+            }
+            Expr::Get(ref object, ref name) => {
+                let object = self.operand(operands, object, saved)?;
+                self.get_property(object, name)
+            }
+            Expr::Set(ref set) => {
+                let instance = instance(self.operand(operands, &set.object, saved)?, &set.name)?;
+                let value = self.operand(operands, &set.value, saved)?;
+                instance
+                    .borrow_mut()
+                    .fields
+                    .insert(set.name.val.clone(), value.clone());
+                Ok(value)
+            }
+            Expr::Index(ref index) => {
+                let object = self.operand(operands, &index.object, saved)?;
+                let key = self.operand(operands, &index.index, saved)?;
+                self.get_index(&object, &key)
+                    .map_err(|e| at_line(e, &index.bracket))
+            }
+            Expr::SetIndex(ref set) => {
+                let object = self.operand(operands, &set.object, saved)?;
+                let key = self.operand(operands, &set.index, saved)?;
+                let value = self.operand(operands, &set.value, saved)?;
+                self.set_index(&object, &key, value.clone())
+                    .map_err(|e| at_line(e, &set.bracket))?;
+                Ok(value)
+            }
+            Expr::List(_, ref elements) => {
+                let mut values = Vec::with_capacity(elements.len());
+                for element in elements.iter() {
+                    values.push(self.operand(operands, element, saved)?);
+                }
+                Ok(Value::list(values))
+            }
+            Expr::Map(ref brace, ref entries) => {
+                let mut map = LoxMap::new();
+                for (key, value) in entries.iter() {
+                    let key = self.operand(operands, key, saved)?;
+                    let key = map::to_key(&key).map_err(|e| at_line(e, brace))?;
+                    let value = self.operand(operands, value, saved)?;
+                    map.insert(key, value);
+                }
+                Ok(Value::map(map))
+            }
+            Expr::Interpolation(_, ref parts) => {
+                let mut s = String::new();
+                for part in parts.iter() {
+                    s.push_str(&self.operand(operands, part, saved)?.to_string());
+                }
+                Ok(Value::from(s))
+            }
+            // nothing to suspend in
+            Expr::Noop | Expr::Literal(_) | Expr::Variable(_) | Expr::This(_) | Expr::Super(..) => {
+                self.evaluate_expr(expr)
+            }
+        }
+    }
+
+    /// Opens the scope a declaration binds its name in. Global scopes, and
+    /// the builtins the prelude declares in, are shared, so functions can
+    /// use globals declared after them; anywhere else a declaration gets a
+    /// scope of its own, so closures made before it keep seeing what the
+    /// name meant where they were written.
+    fn declaration_scope(&mut self) {
+        let shared = Rc::ptr_eq(&self.environment, &self.builtins)
+            || self.environment.borrow().is_inside(&self.builtins);
+        if !shared {
+            let env = Environment::with_enclosing(self.environment.clone());
+            self.environment = Rc::new(RefCell::new(env));
+        }
+    }
+
+    /// Executes `statements` in `env`, restoring the current scope afterwards
+    pub fn execute_block(&mut self, statements: &[Stmt], env: Environment) -> Result<()> {
+        let previous = mem::replace(&mut self.environment, Rc::new(RefCell::new(env)));
+        let result = statements.iter().try_for_each(|stmt| self.execute(stmt));
+        self.environment = previous;
+        result
+    }
+
+    fn class_declaration(&mut self, decl: &ClassDecl) -> Result<()> {
+        let superclass = match decl.superclass {
+            Some(ref expr) => match self.evaluate(expr)? {
+                Value::Class(class) => Some(class),
+                _ => {
+                    return Err(Error::Runtime(
+                        decl.name.line as u64,
+                        "Superclass must be a class".to_string(),
+                        decl.name.val.clone(),
+                    ))
+                }
+            },
+            None => None,
+        };
+
+        self.declaration_scope();
+        self.environment
+            .borrow_mut()
+            .define(&decl.name.val, Value::Nil);
+
+        let enclosing = self.environment.clone();
+        if let Some(ref superclass) = superclass {
+            let mut env = Environment::with_enclosing(enclosing.clone());
+            env.define("super", Value::Class(superclass.clone()));
+            self.environment = Rc::new(RefCell::new(env));
+        }
+
+        let mut methods = HashMap::new();
+        for method in decl.methods.iter() {
+            let mut function = LoxFunction::new(
+                method.clone(),
+                self.environment.clone(),
+                method.name.val == "init",
+            );
+            function.file = self.file.clone();
+            methods.insert(method.name.val.clone(), Rc::new(function));
+        }
+        self.environment = enclosing;
+
+        let class = LoxClass::new(&decl.name.val, superclass, methods);
+        self.environment
+            .borrow_mut()
+            .assign(&decl.name, Value::Class(Rc::new(class)))
+    }
+
+    pub fn evaluate(&mut self, expr: &Expr) -> Result<Value> {
+        let result = self.evaluate_expr(expr);
+        if let Err(Error::Runtime(..)) | Err(Error::Throw(..)) = result {
+            // the innermost expression that failed
+            if self.raised.is_none() {
+                self.raised = expr.token().map(|t| self.position(t));
+            }
+        }
+        result
+    }
+
+    fn evaluate_expr(&mut self, expr: &Expr) -> Result<Value> {
+        match *expr {
+            Expr::Noop => Ok(Value::Nil),
+            Expr::Literal(ref token) => Ok(literal(token)),
+            Expr::Grouping(ref expr) => self.evaluate(expr),
+            Expr::Unary(ref op, ref right) => {
+                let right = self.evaluate(right)?;
+                unary(op, right)
+            }
+            Expr::BinaryExpr(ref binary) => {
+                let left = self.evaluate(&binary.lhs)?;
+                let right = self.evaluate(&binary.rhs)?;
+                binary_operation(binary, left, right)
+            }
+            Expr::Logical(ref logical) => {
+                let left = self.evaluate(&logical.lhs)?;
+                if short_circuits(&logical.op, &left) {
+                    return Ok(left);
+                }
+                self.evaluate(&logical.rhs)
+            }
+            Expr::Conditional(ref conditional) => {
+                if self.evaluate(&conditional.condition)?.is_truthy() {
+                    self.evaluate(&conditional.then_branch)
+                } else {
+                    self.evaluate(&conditional.else_branch)
+                }
+            }
+            Expr::Comma(ref comma) => {
+                self.evaluate(&comma.lhs)?;
+                self.evaluate(&comma.rhs)
+            }
+            Expr::Variable(ref name) => self.environment.borrow().get(name),
+            Expr::Assign(ref name, ref value) => {
+                let value = self.evaluate(value)?;
+                self.environment.borrow_mut().assign(name, value.clone())?;
+                Ok(value)
+            }
+            Expr::CompoundAssign(ref assign) => {
+                self.compound_assign(assign, &mut Operands::default(), &mut vec![])
+            }
+            Expr::Increment(ref increment) => {
+                self.increment(increment, &mut Operands::default(), &mut vec![])
+            }
+            Expr::Call(ref call) => {
+                let callee = self.evaluate(&call.callee)?;
+                let mut args = Vec::with_capacity(call.args.len());
+                for arg in call.args.iter() {
+                    args.push(self.evaluate(arg)?);
+                }
+                self.call(callee, args, &call.paren)
+            }
+            Expr::Get(ref object, ref name) => {
+                let object = self.evaluate(object)?;
+                self.get_property(object, name)
+            }
+            Expr::Set(ref set) => {
+                let instance = instance(self.evaluate(&set.object)?, &set.name)?;
+                let value = self.evaluate(&set.value)?;
+                instance
+                    .borrow_mut()
+                    .fields
+                    .insert(set.name.val.clone(), value.clone());
+                Ok(value)
+            }
+            Expr::Index(ref index) => {
+                let object = self.evaluate(&index.object)?;
+                let key = self.evaluate(&index.index)?;
+                self.get_index(&object, &key)
+                    .map_err(|e| at_line(e, &index.bracket))
+            }
+            Expr::SetIndex(ref set) => {
+                let object = self.evaluate(&set.object)?;
+                let key = self.evaluate(&set.index)?;
+                let value = self.evaluate(&set.value)?;
+                self.set_index(&object, &key, value.clone())
+                    .map_err(|e| at_line(e, &set.bracket))?;
+                Ok(value)
+            }
+            Expr::This(ref keyword) => self.environment.borrow().get(keyword),
+            Expr::Super(ref keyword, ref method) => {
+                let superclass = match self.environment.borrow().get(keyword)? {
+                    Value::Class(class) => class,
+                    _ => unreachable!("'super' is always bound to a class"),
+                };
+                let this = self
+                    .environment
+                    .borrow()
+                    .lookup("this")
+                    .expect("'super' is only bound inside methods");
+                match superclass.find_method(&method.val) {
+                    Some(m) => Ok(Value::Function(Rc::new(m.bind(this)))),
+                    None => Err(Error::Runtime(
+                        method.line as u64,
+                        "Undefined property".to_string(),
+                        method.val.clone(),
+                    )),
+                }
+            }
+            Expr::List(_, ref elements) => {
+                let mut values = Vec::with_capacity(elements.len());
+                for element in elements.iter() {
+                    values.push(self.evaluate(element)?);
+                }
+                Ok(Value::list(values))
+            }
+            Expr::Map(ref brace, ref entries) => {
+                let mut map = LoxMap::new();
+                for (key, value) in entries.iter() {
+                    let key = self.evaluate(key)?;
+                    let key = map::to_key(&key).map_err(|e| at_line(e, brace))?;
+                    let value = self.evaluate(value)?;
+                    map.insert(key, value);
+                }
+                Ok(Value::map(map))
+            }
+            Expr::Interpolation(_, ref parts) => {
+                let mut s = String::new();
+                for part in parts.iter() {
+                    s.push_str(&self.evaluate(part)?.to_string());
+                }
+                Ok(Value::from(s))
+            }
+            Expr::Yield(..) => unreachable!("expressions with a yield are run by resume_expr"),
+        }
+    }
+
+    fn compound_assign(
+        &mut self,
+        assign: &CompoundAssignExpr,
+        operands: &mut Operands,
+        saved: &mut Vec<Resume>,
+    ) -> Result<Value> {
+        let operator = match assign.operator() {
+            Some(operator) => operator,
+            None => return Err(unknown_operator(&assign.op)),
+        };
+        let update = |interpreter: &mut Self, old, operands: &mut Operands, saved: &mut _| {
+            let value = interpreter.operand(operands, &assign.value, saved)?;
+            operate(&assign.op, operator, old, value)
+        };
+        let (_, value) = self.update(&assign.target, operands, saved, update)?;
+        Ok(value)
+    }
+
+    /// `++x` gives the new value, `x++` the old one
+    fn increment(
+        &mut self,
+        increment: &IncrementExpr,
+        operands: &mut Operands,
+        saved: &mut Vec<Resume>,
+    ) -> Result<Value> {
+        let (old, value) = self.update(&increment.target, operands, saved, |_, old, _, _| {
+            let n = number_operand(&increment.op, &old)?;
+            Ok(Value::Number(n + increment.delta()))
+        })?;
+        Ok(if increment.prefix { value } else { old })
+    }
+
+    /// Reads the target, computes its new value from the old one and
+    /// writes it back, giving both. The object and index of the target
+    /// are only evaluated once, and the old value is kept in `operands`
+    /// with them in case the new one suspends a generator.
+    fn update(
+        &mut self,
+        target: &Expr,
+        operands: &mut Operands,
+        saved: &mut Vec<Resume>,
+        update: impl FnOnce(&mut Self, Value, &mut Operands, &mut Vec<Resume>) -> Result<Value>,
+    ) -> Result<(Value, Value)> {
+        match *target {
+            Expr::Variable(ref name) => {
+                let old = match operands.next() {
+                    Some(old) => old,
+                    None => operands.keep(self.environment.borrow().get(name)?),
+                };
+                let value = update(self, old.clone(), operands, saved)?;
+                self.environment.borrow_mut().assign(name, value.clone())?;
+                Ok((old, value))
+            }
+            Expr::Get(ref object, ref name) => {
+                let instance = instance(self.operand(operands, object, saved)?, name)?;
+                let old = match operands.next() {
+                    Some(old) => old,
+                    None => {
+                        let old = self.get_property(Value::Instance(instance.clone()), name)?;
+                        operands.keep(old)
+                    }
+                };
+                let value = update(self, old.clone(), operands, saved)?;
+                instance
+                    .borrow_mut()
+                    .fields
+                    .insert(name.val.clone(), value.clone());
+                Ok((old, value))
+            }
+            Expr::Index(ref index) => {
+                let object = self.operand(operands, &index.object, saved)?;
+                let key = self.operand(operands, &index.index, saved)?;
+                let old = match operands.next() {
+                    Some(old) => old,
+                    None => {
+                        let old = self
+                            .get_index(&object, &key)
+                            .map_err(|e| at_line(e, &index.bracket))?;
+                        operands.keep(old)
+                    }
+                };
+                let value = update(self, old.clone(), operands, saved)?;
+                self.set_index(&object, &key, value.clone())
+                    .map_err(|e| at_line(e, &index.bracket))?;
+                Ok((old, value))
+            }
+            _ => unreachable!("the parser only allows variables, fields and elements"),
+        }
+    }
+
+    /// Calls a function, method or class with already evaluated arguments
+    pub fn call(&mut self, callee: Value, args: Vec<Value>, paren: &Token) -> Result<Value> {
+        let (arity, optional) = match callee {
+            Value::Function(ref f) => (f.arity(), 0),
+            Value::Native(ref f) => (f.arity, f.optional),
+            Value::Class(ref c) => (c.arity(), 0),
+            _ => {
+                return Err(Error::Runtime(
+                    paren.line as u64,
+                    "Can only call functions and classes".to_string(),
+                    callee.type_name().to_string(),
+                ))
+            }
+        };
+        if args.len() < arity || args.len() > arity + optional {
+            let expected = match optional {
+                0 => arity.to_string(),
+                _ => format!("{} to {}", arity, arity + optional),
+            };
+            return Err(Error::Runtime(
+                paren.line as u64,
+                format!("Expected {} arguments but got {}", expected, args.len()),
+                paren.val.clone(),
+            ));
+        }
+
+        let (function, native, file) = match callee {
+            Value::Function(ref f) => (f.name().to_string(), false, f.file.clone()),
+            Value::Native(ref f) => (f.name.clone(), true, self.file.clone()),
+            Value::Class(ref c) => {
+                let file = c.find_method("init").map_or(self.file.clone(), |f| f.file.clone());
+                (c.name.clone(), false, file)
+            }
+            _ => unreachable!(),
+        };
+        let frame = Frame {
+            function,
+            native,
+            call: self.position(paren),
+        };
+        self.push_frame(frame, paren.line)?;
+        let caller_file = mem::replace(&mut self.file, file);
+        let result = match callee {
+            Value::Function(f) => f.call(self, args),
+            Value::Native(f) => f.call(self, args).map_err(|e| at_line(e, paren)),
+            Value::Class(class) => {
+                let instance =
+                    Value::Instance(Rc::new(RefCell::new(LoxInstance::new(class.clone()))));
+                match class.find_method("init") {
+                    Some(init) => init.bind(instance.clone()).call(self, args).map(|_| instance),
+                    None => Ok(instance),
+                }
+            }
+            _ => unreachable!(),
+        };
+        self.pop_frame(caller_file, &result);
+        result
+    }
+
+    /// Starts a call, unless calls are already nested `MAX_FRAMES` deep
+    fn push_frame(&mut self, frame: Frame, line: usize) -> Result<()> {
+        if self.frames.len() >= MAX_FRAMES {
+            return Err(Error::Runtime(
+                line as u64,
+                "Stack overflow".to_string(),
+                frame.function,
+            ));
+        }
+        self.frames.push(frame);
+        Ok(())
+    }
+
+    /// Ends the innermost call, adding it to the trace of the error it
+    /// failed with
+    fn pop_frame<T>(&mut self, caller_file: Option<Rc<str>>, result: &Result<T>) {
+        let file = mem::replace(&mut self.file, caller_file);
+        let frame = self.frames.pop().expect("a call in progress");
+        if let Err(Error::Runtime(line, ..)) | Err(Error::Throw(line, _)) = *result {
+            let at = self.raised.take().unwrap_or(Position {
+                file,
+                line: line as usize,
+                column: 0,
+            });
+            self.unwound.push(frame.stack_frame(at));
+            self.raised = Some(frame.call);
+        }
+    }
+
+    fn get_property(&mut self, object: Value, name: &Token) -> Result<Value> {
+        let method = match object {
+            Value::Instance(ref instance) => {
+                if let Some(value) = instance.borrow().fields.get(&name.val) {
+                    return Ok(value.clone());
+                }
+                let method = instance.borrow().class.find_method(&name.val);
+                if let Some(method) = method {
+                    return Ok(Value::Function(Rc::new(method.bind(object.clone()))));
+                }
+                None
+            }
+            Value::List(_) => list::method(&name.val),
+            Value::Map(_) => map::method(&name.val),
+            Value::Str(_) => string::method(&name.val),
+            Value::Regex(_) => re::method(&name.val),
+            Value::Generator(_) => generator::method(&name.val),
+            Value::Module(ref module) => {
+                return module
+                    .globals
+                    .borrow()
+                    .lookup_local(&name.val)
+                    .ok_or_else(|| {
+                        Error::Runtime(
+                            name.line as u64,
+                            "Undefined property".to_string(),
+                            name.val.clone(),
+                        )
+                    })
+            }
+            _ => {
+                return Err(Error::Runtime(
+                    name.line as u64,
+                    "Only instances have properties".to_string(),
+                    name.val.clone(),
+                ))
+            }
+        };
+        match method {
+            Some(method) => Ok(Value::Native(Rc::new(method.bind(object)))),
+            None => Err(Error::Runtime(
+                name.line as u64,
+                "Undefined property".to_string(),
+                name.val.clone(),
+            )),
+        }
+    }
+
+    fn get_index(&mut self, object: &Value, index: &Value) -> Result<Value> {
+        match *object {
+            Value::List(ref list) => {
+                let list = list.borrow();
+                Ok(list[list::to_index(index, list.len())?].clone())
+            }
+            Value::Map(ref map) => {
+                let key = map::to_key(index)?;
+                match map.borrow().get(&key) {
+                    Some(value) => Ok(value.clone()),
+                    None => Err(Error::Runtime(0, "Undefined key".to_string(), index.repr())),
+                }
+            }
+            _ => Err(Error::Runtime(
+                0,
+                "Only lists and maps can be indexed".to_string(),
+                object.type_name().to_string(),
+            )),
+        }
+    }
+
+    fn set_index(&mut self, object: &Value, index: &Value, value: Value) -> Result<()> {
+        match *object {
+            Value::List(ref list) => {
+                let mut list = list.borrow_mut();
+                let i = list::to_index(index, list.len())?;
+                list[i] = value;
+                Ok(())
+            }
+            Value::Map(ref map) => {
+                let key = map::to_key(index)?;
+                map.borrow_mut().insert(key, value);
+                Ok(())
+            }
+            _ => Err(Error::Runtime(
+                0,
+                "Only lists and maps can be indexed".to_string(),
+                object.type_name().to_string(),
+            )),
+        }
+    }
+}
+
+fn literal(token: &Token) -> Value {
+    match token.tok_type {
+        TokenType::NIL => Value::Nil,
+        TokenType::TRUE => Value::Bool(true),
+        TokenType::FALSE => Value::Bool(false),
+        TokenType::NUMBER => Value::Number(scanner::parse_number(&token.val).unwrap_or(f64::NAN)),
+        _ => Value::from(token.val.as_str()),
+    }
+}
+
+fn number_operand(op: &Token, operand: &Value) -> Result<f64> {
+    match *operand {
+        Value::Number(n) => Ok(n),
+        _ => Err(Error::Runtime(
+            op.line as u64,
+            "Operand must be a number".to_string(),
+            op.val.clone(),
+        )),
+    }
+}
+
+/// Applies a binary operator, `op` is the token errors are reported at
+fn unary(op: &Token, right: Value) -> Result<Value> {
+    match op.tok_type {
+        TokenType::BANG => Ok(Value::Bool(!right.is_truthy())),
+        TokenType::MINUS => Ok(Value::Number(-number_operand(op, &right)?)),
+        TokenType::TILDE => Ok(Value::Number(!integer_operand(op, &right)? as f64)),
+        _ => Err(unknown_operator(op)),
+    }
+}
+
+fn binary_operation(binary: &BinaryExpr, left: Value, right: Value) -> Result<Value> {
+    match binary.operator() {
+        Some(operator) => operate(&binary.op, operator, left, right),
+        None => Err(unknown_operator(&binary.op)),
+    }
+}
+
+/// Whether `and` or `or` gives its left operand without evaluating the
+/// right one
+fn short_circuits(op: &Token, left: &Value) -> bool {
+    match op.tok_type {
+        TokenType::OR => left.is_truthy(),
+        _ => !left.is_truthy(),
+    }
+}
+
+fn operate(op: &Token, operator: BinaryOperator, left: Value, right: Value) -> Result<Value> {
+    let value = match operator {
+        BinaryOperator::Equal => Value::Bool(left.equals(&right)),
+        BinaryOperator::BangEqual => Value::Bool(!left.equals(&right)),
+        BinaryOperator::Plus => match (left, right) {
+            (Value::Number(a), Value::Number(b)) => Value::Number(a + b),
+            (Value::Str(a), Value::Str(b)) => Value::from(format!("{}{}", a, b)),
+            _ => {
+                return Err(Error::Runtime(
+                    op.line as u64,
+                    "Operands must be two numbers or two strings".to_string(),
+                    op.val.clone(),
+                ))
+            }
+        },
+        BinaryOperator::Ampersand
+        | BinaryOperator::Pipe
+        | BinaryOperator::Caret
+        | BinaryOperator::LessLess
+        | BinaryOperator::GreaterGreater
+        | BinaryOperator::TildeSlash => {
+            let a = integer_operand(op, &left)?;
+            let b = integer_operand(op, &right)?;
+            let n = match operator {
+                BinaryOperator::Ampersand => a & b,
+                BinaryOperator::Pipe => a | b,
+                BinaryOperator::Caret => a ^ b,
+                // truncates like `%`, so `(a ~/ b) * b + a % b == a`
+                BinaryOperator::TildeSlash => a.checked_div(b).ok_or_else(|| {
+                    Error::Runtime(op.line as u64, "Division by zero".to_string(), op.val.clone())
+                })?,
+                _ if !(0..64).contains(&b) => {
+                    return Err(Error::Runtime(
+                        op.line as u64,
+                        "Shift amount must be between 0 and 63".to_string(),
+                        op.val.clone(),
+                    ))
+                }
+                BinaryOperator::LessLess => match a << b {
+                    n if n >> b == a => n,
+                    _ => {
+                        return Err(Error::Runtime(
+                            op.line as u64,
+                            "Result of the shift does not fit in an integer".to_string(),
+                            op.val.clone(),
+                        ))
+                    }
+                },
+                _ => a >> b,
+            };
+            Value::Number(n as f64)
+        }
+        _ => {
+            let (a, b) = number_operands(op, &left, &right)?;
+            let division = matches!(operator, BinaryOperator::Slash | BinaryOperator::Percent);
+            if division && b == 0.0 {
+                return Err(Error::Runtime(
+                    op.line as u64,
+                    "Division by zero".to_string(),
+                    op.val.clone(),
+                ));
+            }
+            match operator {
+                BinaryOperator::Minus => Value::Number(a - b),
+                BinaryOperator::Star => Value::Number(a * b),
+                BinaryOperator::Slash => Value::Number(a / b),
+                BinaryOperator::Percent => Value::Number(a % b),
+                BinaryOperator::StarStar => Value::Number(a.powf(b)),
+                BinaryOperator::Greater => Value::Bool(a > b),
+                BinaryOperator::GreaterEqual => Value::Bool(a >= b),
+                BinaryOperator::Less => Value::Bool(a < b),
+                BinaryOperator::LessEqual => Value::Bool(a <= b),
+                _ => unreachable!(),
+            }
+        }
+    };
+    Ok(value)
+}
+
+/// Operand of a bitwise operator, a number without a fractional part
+fn integer_operand(op: &Token, operand: &Value) -> Result<i64> {
+    match *operand {
+        Value::Number(n) if n.fract() == 0.0 && n.abs() < i64::MAX as f64 => Ok(n as i64),
+        _ => Err(Error::Runtime(
+            op.line as u64,
+            "Operands must be integers".to_string(),
+            op.val.clone(),
+        )),
+    }
+}
+
+fn number_operands(op: &Token, left: &Value, right: &Value) -> Result<(f64, f64)> {
+    match (left, right) {
+        (Value::Number(a), Value::Number(b)) => Ok((*a, *b)),
+        _ => Err(Error::Runtime(
+            op.line as u64,
+            "Operands must be numbers".to_string(),
+            op.val.clone(),
+        )),
+    }
+}
+
+fn unknown_operator(op: &Token) -> Error {
+    Error::Runtime(
+        op.line as u64,
+        "Unknown operator".to_string(),
+        op.val.clone(),
+    )
+}
+
+/// A token standing for `name` where `token` is
+fn renamed(token: &Token, name: &str) -> Token {
+    let mut renamed = token.clone();
+    renamed.tok_type = TokenType::IDENTIFIER;
+    renamed.val = name.to_string();
+    renamed
+}
+
+/// The instance a field `name` is set on
+fn instance(object: Value, name: &Token) -> Result<Rc<RefCell<LoxInstance>>> {
+    match object {
+        Value::Instance(instance) => Ok(instance),
+        _ => Err(Error::Runtime(
+            name.line as u64,
+            "Only instances have fields".to_string(),
+            name.val.clone(),
+        )),
+    }
+}
+
+fn set_field(instance: &Value, name: &str, value: Value) {
+    if let Value::Instance(ref instance) = *instance {
+        instance.borrow_mut().fields.insert(name.to_string(), value);
+    }
+}
+
+/// A new scope inside `enclosing`
+fn scope(enclosing: Rc<RefCell<Environment>>) -> Rc<RefCell<Environment>> {
+    Rc::new(RefCell::new(Environment::with_enclosing(enclosing)))
+}
+
+/// Whether a generator can be suspended while running `stmt`
+fn has_yield(stmt: &Stmt) -> bool {
+    let any = |statements: &[Stmt]| statements.iter().any(has_yield);
+    match *stmt {
+        Stmt::Expression(ref expr) | Stmt::Print(ref expr) | Stmt::Throw(_, ref expr) => {
+            expr_has_yield(expr)
+        }
+        Stmt::Var(_, ref expr) | Stmt::Return(_, ref expr) => {
+            expr.as_ref().is_some_and(expr_has_yield)
+        }
+        Stmt::Block(ref statements) => any(statements),
+        Stmt::If(ref stmt) => {
+            expr_has_yield(&stmt.condition)
+                || has_yield(&stmt.then_branch)
+                || stmt.else_branch.as_ref().is_some_and(has_yield)
+        }
+        Stmt::While(ref stmt) => expr_has_yield(&stmt.condition) || has_yield(&stmt.body),
+        Stmt::ForIn(ref stmt) => expr_has_yield(&stmt.iterable) || has_yield(&stmt.body),
+        Stmt::Try(ref stmt) => {
+            any(&stmt.body)
+                || stmt.catch.as_ref().is_some_and(|(_, handler)| any(handler))
+                || stmt.finally.as_deref().is_some_and(any)
+        }
+        _ => false,
+    }
+}
+
+/// Whether a generator can be suspended while evaluating `expr`
+fn expr_has_yield(expr: &Expr) -> bool {
+    match *expr {
+        Expr::Yield(..) => true,
+        Expr::BinaryExpr(ref b) | Expr::Logical(ref b) | Expr::Comma(ref b) => {
+            expr_has_yield(&b.lhs) || expr_has_yield(&b.rhs)
+        }
+        Expr::Conditional(ref c) => {
+            expr_has_yield(&c.condition)
+                || expr_has_yield(&c.then_branch)
+                || expr_has_yield(&c.else_branch)
+        }
+        Expr::Unary(_, ref e) | Expr::Grouping(ref e) | Expr::Get(ref e, _) => expr_has_yield(e),
+        Expr::Assign(_, ref value) => expr_has_yield(value),
+        Expr::CompoundAssign(ref assign) => {
+            expr_has_yield(&assign.target) || expr_has_yield(&assign.value)
+        }
+        Expr::Increment(ref increment) => expr_has_yield(&increment.target),
+        Expr::Call(ref call) => {
+            expr_has_yield(&call.callee) || call.args.iter().any(expr_has_yield)
+        }
+        Expr::Set(ref set) => expr_has_yield(&set.object) || expr_has_yield(&set.value),
+        Expr::Index(ref index) => expr_has_yield(&index.object) || expr_has_yield(&index.index),
+        Expr::SetIndex(ref set) => {
+            expr_has_yield(&set.object) || expr_has_yield(&set.index) || expr_has_yield(&set.value)
+        }
+        Expr::List(_, ref exprs) | Expr::Interpolation(_, ref exprs) => {
+            exprs.iter().any(expr_has_yield)
+        }
+        Expr::Map(_, ref entries) => entries
+            .iter()
+            .any(|(key, value)| expr_has_yield(key) || expr_has_yield(value)),
+        Expr::Noop | Expr::Literal(_) | Expr::Variable(_) | Expr::This(_) | Expr::Super(..) => {
+            false
+        }
+    }
+}
+
+/// Notes where the generator is in a statement if `result` suspends it
+fn on_yield(result: Result<()>, saved: &mut Vec<Resume>, at: impl FnOnce() -> Resume) -> Result<()> {
+    if let Err(Error::Yield(_)) = result {
+        saved.push(at());
+    }
+    result
+}
+
+/// Fills in the line of errors raised by code that doesn't know it,
+/// such as native functions, whose I/O errors become catchable
+pub fn at_line(err: Error, token: &Token) -> Error {
+    match err {
+        Error::Runtime(0, msg, near) => Error::Runtime(token.line as u64, msg, near),
+        Error::IO(e) => Error::Runtime(token.line as u64, "I/O error".to_string(), e.to_string()),
+        e => e,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::parser::Parser;
+    use crate::frontend::scanner::Scanner;
+
+    fn run(src: &str) -> Result<Interpreter> {
+        let tokens = Scanner::new(src).scan_tokens()?;
+        let statements = Parser::new(tokens).parse()?;
+        let mut interpreter = Interpreter::with_output(Box::new(io::sink()));
+        interpreter.interpret(&statements)?;
+        Ok(interpreter)
+    }
+
+    fn global(interpreter: &Interpreter, name: &str) -> Value {
+        interpreter.globals.borrow().lookup(name).unwrap()
+    }
+
+    #[test]
+    fn test_arithmetic_and_strings() {
+        let lox = run("var a = 1 + 2 * 3 - 4 / 2; var s = \"a\" + \"b\";").unwrap();
+        assert_eq!(global(&lox, "a"), Value::Number(5.0));
+        assert_eq!(global(&lox, "s"), Value::from("ab"));
+    }
+
+    #[test]
+    fn test_closures_and_classes() {
+        let src = "
+            fun counter() { var i = 0; fun inc() { i = i + 1; return i; } return inc; }
+            var c = counter(); c(); var n = c();
+            class A { init(x) { this.x = x; } get() { return this.x; } }
+            class B < A { get() { return super.get() * 10; } }
+            var b = B(4).get();
+        ";
+        let lox = run(src).unwrap();
+        assert_eq!(global(&lox, "n"), Value::Number(2.0));
+        assert_eq!(global(&lox, "b"), Value::Number(40.0));
+    }
+
+    #[test]
+    fn test_closure_scopes() {
+        // a closure sees the variables in scope where it was written, not
+        // ones declared after it
+        let src = r#"
+            var a = "global";
+            var seen = [];
+            {
+                fun show() { seen.push(a); }
+                show();
+                var a = "block";
+                show();
+                seen.push(a);
+                a = "assigned";
+                seen.push(a);
+            }
+            {
+                fun fact(n) { if (n < 2) return 1; return n * fact(n - 1); }
+                class Node { next() { return Node(); } }
+                seen.push(fact(4));
+                seen.push(type(Node().next()));
+            }
+            fun* gen() { var x = yield 1; fun get() { return x; } yield get(); }
+            var g = gen(); g.next();
+            seen.push(g.send("sent"));
+            fun later() { return defined; }
+            var defined = "later";
+            seen.push(later());
+        "#;
+        let lox = run(src).unwrap();
+        assert_eq!(
+            global(&lox, "seen").to_string(),
+            r#"["global", "global", "block", "assigned", 24, "instance", "sent", "later"]"#
+        );
+    }
+
+    #[test]
+    fn test_loops_and_break() {
+        let src = "var n = 0; for (var i = 0; i < 10; i = i + 1) { if (i == 5) break; n = n + i; }";
+        let lox = run(src).unwrap();
+        assert_eq!(global(&lox, "n"), Value::Number(10.0));
+    }
+
+    #[test]
+    fn test_map_literal_and_methods() {
+        let src = r#"
+            var m = {"a": 1, "b": 2, 3: "three", true: nil};
+            m["c"] = m["a"] + m["b"];
+            m["a"] = 10;
+            var keys = m.keys();
+            var len = m.len();
+            var has = m.has(3);
+            var removed = m.remove("b");
+            var after = m.len();
+            var missing = m.has("b");
+        "#;
+        let lox = run(src).unwrap();
+        assert_eq!(
+            global(&lox, "keys").to_string(),
+            r#"["a", "b", 3, true, "c"]"#
+        );
+        assert_eq!(global(&lox, "len"), Value::Number(5.0));
+        assert_eq!(global(&lox, "has"), Value::Bool(true));
+        assert_eq!(global(&lox, "removed"), Value::Number(2.0));
+        assert_eq!(global(&lox, "after"), Value::Number(4.0));
+        assert_eq!(global(&lox, "missing"), Value::Bool(false));
+        assert_eq!(
+            global(&lox, "m").to_string(),
+            r#"{"a": 10, 3: "three", true: nil, "c": 3}"#
+        );
+    }
+
+    #[test]
+    fn test_map_and_block_disambiguation() {
+        let lox = run("var x = 0; { x = 1; } {} {\"k\": 2}.len();").unwrap();
+        assert_eq!(global(&lox, "x"), Value::Number(1.0));
+
+        // keys longer than a token, and blocks holding conditionals or maps
+        let src = r#"
+            var x = 1;
+            {-1: 2}.len();
+            {"a" + "b": 1, [1][0]: 2}.len();
+            {x ? "a" : "b": 1}.len();
+            { x = x ? 2 : 3; }
+            { var m = {"k": x}; x = m["k"] + 1; }
+        "#;
+        let lox = run(src).unwrap();
+        assert_eq!(global(&lox, "x"), Value::Number(3.0));
+    }
+
+    #[test]
+    fn test_map_errors() {
+        match run("var m = {}; m[[1]] = 1;") {
+            Err(Error::Runtime(1, _, _)) => (),
+            r => panic!("expected a runtime error, got {:?}", r.err()),
+        }
+        match run("var m = {\"a\": 1};\nm[\"b\"];") {
+            Err(Error::Runtime(2, _, _)) => (),
+            r => panic!("expected a runtime error, got {:?}", r.err()),
+        }
+    }
+
+    #[test]
+    fn test_interpolation() {
+        let src = r#"
+            var who = "you";
+            var s = "Enjoy your breakfast, ${who}.";
+            var t = "${1 + 1} ${[1, "a"]} ${nil}${"nested ${who}"}";
+        "#;
+        let lox = run(src).unwrap();
+        assert_eq!(global(&lox, "s"), Value::from("Enjoy your breakfast, you."));
+        assert_eq!(
+            global(&lox, "t"),
+            Value::from(r#"2 [1, "a"] nilnested you"#)
+        );
+        assert!(run("var s = \"${}\";").is_err());
+    }
+
+    #[test]
+    fn test_arithmetic_and_bitwise_operators() {
+        let src = "
+            var a = [7 % 3, -7 % 3, 7.5 % 2, 7 ~/ 2, -7 ~/ 2, 2 ** 3 ** 2, -2 ** 2];
+            var b = [6 & 3, 6 | 3, 6 ^ 3, ~5, 1 << 4, -16 >> 2, -3 << 2];
+            var c = [7 % -2, -7 ~/ -2, 7 ~/ -2];
+            var d = [];
+            for (var i = 0; i < 4; i++) {
+                var x = [7, -7, 7, -7][i];
+                var y = [2, 2, -2, -2][i];
+                d.push((x ~/ y) * y + x % y == x);
+            }
+        ";
+        let lox = run(src).unwrap();
+        assert_eq!(global(&lox, "a").to_string(), "[1, -1, 1.5, 3, -3, 512, -4]");
+        assert_eq!(global(&lox, "b").to_string(), "[2, 7, 5, -6, 16, -4, -12]");
+        assert_eq!(global(&lox, "c").to_string(), "[1, 3, -3]");
+        assert_eq!(global(&lox, "d").to_string(), "[true, true, true, true]");
+
+        for src in &[
+            "1 / 0;",
+            "1 % 0;",
+            "1 ~/ 0;",
+            "7.5 ~/ 2;",
+            "1.5 & 1;",
+            "1 << 64;",
+            "4611686018427387904 << 2;",
+            "~\"a\";",
+        ] {
+            match run(src) {
+                Err(Error::Runtime(1, _, _)) => (),
+                r => panic!("expected a runtime error for {}, got {:?}", src, r.err()),
+            }
+        }
+    }
+
+    #[test]
+    fn test_compound_assignment() {
+        let src = r#"
+            class Box {}
+            var calls = 0;
+            var box = Box();
+            fun get() { calls += 1; return box; }
+            var n = 10;
+            n -= 3;
+            n *= 2;
+            n /= 4;
+            n %= 2;
+            var s = "a";
+            s += "b";
+            box.x = 1;
+            get().x += 41;
+            var l = [1, 2];
+            var i = 0;
+            l[i += 1] *= 5;
+            var m = {"k": 1};
+            var k = (m["k"] += 1) + 1;
+        "#;
+        let lox = run(src).unwrap();
+        assert_eq!(global(&lox, "n"), Value::Number(1.5));
+        assert_eq!(global(&lox, "s"), Value::from("ab"));
+        assert_eq!(global(&lox, "calls"), Value::Number(1.0));
+        assert_eq!(global(&lox, "l").to_string(), "[1, 10]");
+        assert_eq!(global(&lox, "i"), Value::Number(1.0));
+        assert_eq!(global(&lox, "k"), Value::Number(3.0));
+        match run("class A {} var a = A(); a.x += 1;") {
+            Err(Error::Runtime(1, _, _)) => (),
+            r => panic!("expected a runtime error, got {:?}", r.err()),
+        }
+    }
+
+    #[test]
+    fn test_conditional_and_comma() {
+        let src = "
+            var a = true ? 1 : undefined();
+            var b = false ? undefined() : nil ? 2 : 3;
+            var n = 0;
+            var i;
+            var j;
+            for (i = 0, j = 10; i < j; i = i + 1, j = j - 1) n = n + 1;
+            var c = (n = n + 1, n * 2);
+        ";
+        let lox = run(src).unwrap();
+        assert_eq!(global(&lox, "a"), Value::Number(1.0));
+        assert_eq!(global(&lox, "b"), Value::Number(3.0));
+        assert_eq!(global(&lox, "n"), Value::Number(6.0));
+        assert_eq!(global(&lox, "c"), Value::Number(12.0));
+    }
+
+    #[test]
+    fn test_exceptions() {
+        let src = r#"
+            var log = [];
+            fun fail(n) {
+                if (n == 0) nil.foo;
+                fail(n - 1);
+            }
+            try {
+                fail(2);
+                log.push("unreachable");
+            } catch (e) {
+                log.push(e.message);
+                log.push(e.line);
+                log.push(e.stack);
+            } finally {
+                log.push("finally");
+            }
+
+            class NotFound < Error {}
+            fun find() {
+                try {
+                    throw NotFound("missing");
+                } finally {
+                    log.push("cleanup");
+                }
+            }
+            try { find(); } catch (e) { log.push(e.message); }
+            try { throw "plain"; } catch (e) { log.push(e); }
+
+            fun early() {
+                try { return 1; } finally { log.push("returned"); }
+            }
+            var r = early();
+            while (true) {
+                try { break; } finally { log.push("broke"); }
+            }
+            try {
+                try { throw Error("inner"); } catch (e) { throw e; }
+            } catch (e) { log.push(e.stack.len()); }
+        "#;
+        let lox = run(src).unwrap();
+        assert_eq!(
+            global(&lox, "log").to_string(),
+            r#"["Only instances have properties: foo", 4, "#.to_string()
+                + r#"["fail (line 4, column 33)", "fail (line 5, column 27)", "#
+                + r#""fail (line 5, column 27)", "<script> (line 8, column 23)"], "#
+                + r#""finally", "cleanup", "missing", "plain", "returned", "broke", 1]"#
+        );
+        assert_eq!(global(&lox, "r"), Value::Number(1.0));
+
+        match run("fun f() { throw Error(\"boom\"); }\nf();") {
+            Err(e @ Error::Throw(1, _)) => {
+                assert_eq!(e.to_string(), "Runtime Error [line 1] Uncaught Error: boom")
+            }
+            r => panic!("expected an uncaught error, got {:?}", r.err()),
+        }
+    }
+
+    #[test]
+    fn test_stack_trace() {
+        let src = "class Index {
+    init(map) { this.map = map; }
+    has(key) { return this.map.has(key); }
+}
+fun lookup(key) {
+    return Index({}).has(key);
+}
+print lookup(\"a\");
+lookup([1]);";
+        let tokens = Scanner::new(src).scan_tokens().unwrap();
+        let statements = Parser::new(tokens).parse().unwrap();
+        let mut interpreter = Interpreter::with_output(Box::new(io::sink()));
+        interpreter.set_file("index.lox");
+        assert!(interpreter.interpret(&statements).is_err());
+
+        let frame = |function: &str, line, column| StackFrame {
+            function: function.to_string(),
+            file: Some("index.lox".to_string()),
+            line,
+            column,
+            native: false,
+        };
+        let has = StackFrame {
+            function: "has".to_string(),
+            file: None,
+            line: 0,
+            column: 0,
+            native: true,
+        };
+        assert_eq!(
+            interpreter.stack_trace(),
+            &[
+                has,
+                frame("has", 3, 39),
+                frame("lookup", 6, 29),
+                frame("<script>", 9, 11),
+            ]
+        );
+        let printed: Vec<String> = interpreter
+            .stack_trace()
+            .iter()
+            .map(|f| f.to_string())
+            .collect();
+        assert_eq!(printed[0], "has (native)");
+        assert_eq!(printed[1], "has (index.lox:3:39)");
+
+        // a new run starts without a trace
+        assert!(interpreter.interpret(&[]).is_ok());
+        assert!(interpreter.stack_trace().is_empty());
+
+        // statements that fail outside of an expression give a column too
+        let src = "print 1;\n  import \"missing.lox\" as m;";
+        let tokens = Scanner::new(src).scan_tokens().unwrap();
+        let statements = Parser::new(tokens).parse().unwrap();
+        assert!(interpreter.interpret(&statements).is_err());
+        assert_eq!(interpreter.stack_trace(), &[frame("<script>", 2, 10)]);
+    }
+
+    #[test]
+    fn test_modules() {
+        let dir = std::env::temp_dir().join(format!("lox-modules-{}", std::process::id()));
+        let lib = dir.join("lib");
+        fs::create_dir_all(&lib).unwrap();
+        let write = |name: &str, src: &str| fs::write(dir.join(name), src).unwrap();
+        write("counter.lox", "var count = 0; fun bump() { count += 1; return count; }");
+        write("lib/shapes.lox", "var name = \"shapes\"; fun area(w, h) { return w * h; }");
+        write("a.lox", "import \"b.lox\" as b;");
+        write("b.lox", "import \"a.lox\" as a;");
+        write("main.lox", "");
+
+        let run_in = |src: &str| -> Result<Interpreter> {
+            let tokens = Scanner::new(src).scan_tokens()?;
+            let statements = Parser::new(tokens).parse()?;
+            let mut interpreter = Interpreter::with_output(Box::new(io::sink()));
+            interpreter.set_file(dir.join("main.lox").to_str().unwrap());
+            interpreter.add_search_path(&lib);
+            interpreter.interpret(&statements)?;
+            Ok(interpreter)
+        };
+
+        // both imports share the module, which only runs once
+        let lox = run_in(
+            "import \"counter.lox\" as c; from \"counter.lox\" import bump;
+var count = 10; bump(); bump(); var n = c.count;",
+        )
+        .unwrap();
+        assert_eq!(global(&lox, "n"), Value::Number(2.0));
+        assert_eq!(global(&lox, "count"), Value::Number(10.0));
+
+        let lox = run_in("from \"shapes.lox\" import area, name; var a = area(2, 3);").unwrap();
+        assert_eq!(global(&lox, "a"), Value::Number(6.0));
+        assert_eq!(global(&lox, "name").to_string(), "shapes");
+
+        let error = |src: &str| match run_in(src) {
+            Err(Error::Runtime(_, message, _)) => message,
+            other => panic!("expected a runtime error, got {:?}", other.map(|_| ())),
+        };
+        assert_eq!(error("import \"missing.lox\" as m;"), "Module not found");
+        assert_eq!(error("import \"a.lox\" as a;"), "Import cycle");
+        assert_eq!(
+            error("from \"counter.lox\" import reset;"),
+            "Module counter.lox has no member"
+        );
+        assert_eq!(error("import \"counter.lox\" as c; c.reset;"), "Undefined property");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_math() {
+        let lox = run("var a = math.sqrt(16) + math.pow(2, 10);
+var b = [math.floor(-1.5), math.ceil(1.2), math.round(2.5), math.abs(-3)];
+var c = [math.min(1, 2), math.max(1, 2), math.log(math.E), math.cos(math.PI)];
+var d = [math.isNaN(math.sqrt(-1)), math.isInfinite(math.exp(1000)), math.isNaN(1)];
+from \"math\" import atan2, PI;
+var e = atan2(1, 1) * 4 == PI;")
+        .unwrap();
+        assert_eq!(global(&lox, "a"), Value::Number(1028.0));
+        assert_eq!(global(&lox, "b").to_string(), "[-2, 2, 3, 3]");
+        assert_eq!(global(&lox, "c").to_string(), "[1, 2, 1, -1]");
+        assert_eq!(global(&lox, "d").to_string(), "[true, true, false]");
+        assert_eq!(global(&lox, "e"), Value::Bool(true));
+
+        match run("var x = 1;\nmath.pow(x, \"2\");") {
+            Err(Error::Runtime(2, message, near)) => {
+                assert_eq!(message, "math.pow() expects a number as argument 2");
+                assert_eq!(near, "\"2\"");
+            }
+            r => panic!("expected a runtime error, got {:?}", r.err()),
+        }
+    }
+
+    #[test]
+    fn test_string_methods() {
+        let lox = run("var s = \" héllo wörld \".trim();
+var a = [s.len(), s.indexOf(\"wö\"), s.indexOf(\"x\"), s.substring(1, 5)];
+var b = [s.upper(), s.replace(\"l\", \"L\"), s.startsWith(\"hé\"), s.endsWith(\"x\")];
+var c = s.split(\" \");
+var d = \"-\".join([1, \"b\", nil]);
+var e = [\"añb\".chars(), \"xy\".split(\"\")];")
+        .unwrap();
+        assert_eq!(global(&lox, "a").to_string(), "[11, 6, -1, \"éllo\"]");
+        assert_eq!(
+            global(&lox, "b").to_string(),
+            "[\"HÉLLO WÖRLD\", \"héLLo wörLd\", true, false]"
+        );
+        assert_eq!(global(&lox, "c").to_string(), "[\"héllo\", \"wörld\"]");
+        assert_eq!(global(&lox, "d").to_string(), "1-b-nil");
+        assert_eq!(global(&lox, "e").to_string(), "[[\"a\", \"ñ\", \"b\"], [\"x\", \"y\"]]");
+
+        match run("\"abc\".substring(2, 4);") {
+            Err(Error::Runtime(1, message, _)) => assert_eq!(message, "Substring out of range"),
+            r => panic!("expected a runtime error, got {:?}", r.err()),
+        }
+        match run("\"abc\".split(1);") {
+            Err(Error::Runtime(1, message, _)) => {
+                assert_eq!(message, "string.split() expects a string as argument 1")
+            }
+            r => panic!("expected a runtime error, got {:?}", r.err()),
+        }
+    }
+
+    #[test]
+    fn test_conversions() {
+        let lox = run("var a = [str(1.5), str(nil), str([1, \"a\"]), num(\" 2.5e1 \"), num(-3)];
+var b = [type(1), type(\"\"), type(nil), type([]), type({}), type(str), type(Error), type(math)];")
+        .unwrap();
+        assert_eq!(global(&lox, "a").to_string(), "[\"1.5\", \"nil\", \"[1, \\\"a\\\"]\", 25, -3]");
+        assert_eq!(
+            global(&lox, "b").to_string(),
+            "[\"number\", \"string\", \"nil\", \"list\", \"map\", \"function\", \"class\", \"module\"]"
+        );
+        for src in &["num(\"12abc\");", "num(\"NaN\");", "num(\"\");", "num(true);"] {
+            match run(src) {
+                Err(Error::Runtime(1, ..)) => (),
+                r => panic!("expected a runtime error for {}, got {:?}", src, r.err()),
+            }
+        }
+    }
+
+    #[test]
+    fn test_files_and_process() {
+        let dir = std::env::temp_dir().join(format!("lox-fs-{}", std::process::id()));
+        let src = format!(
+            "var dir = \"{}\";
+fs.mkdir(dir + \"/sub\");
+fs.writeFile(dir + \"/a.txt\", \"one\\n\");
+fs.appendFile(dir + \"/a.txt\", \"two\\n\");
+var text = fs.readFile(dir + \"/a.txt\");
+var lines = fs.readLines(dir + \"/a.txt\");
+var entries = fs.listDir(dir);
+fs.remove(dir + \"/a.txt\");
+fs.remove(dir + \"/sub\");
+var gone = [fs.exists(dir + \"/a.txt\"), fs.exists(dir)];
+var message;
+try {{ fs.readFile(dir + \"/a.txt\"); }} catch (e) {{ message = e.message; }}
+var home = env(\"LOX_TEST_UNSET_VARIABLE\");",
+            dir.display()
+        );
+        let lox = run(&src).unwrap();
+        assert_eq!(global(&lox, "text").to_string(), "one\ntwo\n");
+        assert_eq!(global(&lox, "lines").to_string(), "[\"one\", \"two\"]");
+        assert_eq!(global(&lox, "entries").to_string(), "[\"a.txt\", \"sub\"]");
+        assert_eq!(global(&lox, "gone").to_string(), "[false, true]");
+        assert!(global(&lox, "message").to_string().starts_with("I/O error: "));
+        assert_eq!(global(&lox, "home"), Value::Nil);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let tokens = Scanner::new("var a = args();").scan_tokens().unwrap();
+        let statements = Parser::new(tokens).parse().unwrap();
+        let mut interpreter = Interpreter::with_output(Box::new(io::sink()));
+        interpreter.set_args(vec!["-v".to_string(), "x".to_string()]);
+        interpreter.interpret(&statements).unwrap();
+        assert_eq!(global(&interpreter, "a").to_string(), "[\"-v\", \"x\"]");
+
+        match run("var done = false;\ntry { exit(3); } catch (e) {} finally { done = true; }") {
+            Err(Error::Exit(3)) => (),
+            r => panic!("expected an exit, got {:?}", r.err()),
+        }
+        match run("exit(1.5);") {
+            Err(Error::Runtime(1, ..)) => (),
+            r => panic!("expected a runtime error, got {:?}", r.err()),
+        }
+    }
+
+    #[test]
+    fn test_time() {
+        let lox = run("var start = clock();
+sleep(0.01);
+var elapsed = clock() - start;
+var recent = now() > 1.5e9;
+var day = date.format(951825599.5, \"%Y-%m-%d %H:%M:%S %%\");
+var back = date.parse(day, \"%Y-%m-%d %H:%M:%S %%\");
+var epoch = date.parse(\"1970\", \"%Y\");")
+        .unwrap();
+        match global(&lox, "elapsed") {
+            Value::Number(n) => assert!(n >= 0.01, "slept for {}s", n),
+            v => panic!("expected a number, got {}", v),
+        }
+        assert_eq!(global(&lox, "recent"), Value::Bool(true));
+        assert_eq!(global(&lox, "day").to_string(), "2000-02-29 11:59:59 %");
+        assert_eq!(global(&lox, "back"), Value::Number(951825599.0));
+        assert_eq!(global(&lox, "epoch"), Value::Number(0.0));
+
+        for src in &[
+            "sleep(-1);",
+            "date.format(0, \"%Q\");",
+            "date.format(1e300, \"%Y\");",
+            "date.format(-1 / 0, \"%Y\");",
+            "date.format(0 / 0, \"%Y\");",
+            "date.format(253402300800, \"%Y\");",
+            "date.parse(\"2001-02-29\", \"%Y-%m-%d\");",
+            "date.parse(\"2001-02-01 \", \"%Y-%m-%d\");",
+            "date.parse(\"01-02\", \"%Y-%m\");",
+        ] {
+            match run(src) {
+                Err(Error::Runtime(1, ..)) => (),
+                r => panic!("expected a runtime error for {}, got {:?}", src, r.err()),
+            }
+        }
+    }
+
+    #[test]
+    fn test_json() {
+        let lox = run(r#"var config = json.parse("{\"name\": \"lox\", \"tags\": [1, 2.5, true, null], \"nested\": {}}");
+var name = config["name"];
+var tags = config["tags"];
+var compact = json.stringify({"b": [1, "x\n"], "a": nil, 2: {}}, nil);
+var pretty = json.stringify({"a": [1, []], "b": {"c": false}}, 2);
+var message;
+try { json.parse("{\n  \"a\": }"); } catch (e) { message = e.message; }"#)
+        .unwrap();
+        assert_eq!(global(&lox, "name").to_string(), "lox");
+        assert_eq!(global(&lox, "tags").to_string(), "[1, 2.5, true, nil]");
+        assert_eq!(
+            global(&lox, "compact").to_string(),
+            r#"{"b":[1,"x\n"],"a":null,"2":{}}"#
+        );
+        assert_eq!(
+            global(&lox, "pretty").to_string(),
+            "{\n  \"a\": [\n    1,\n    []\n  ],\n  \"b\": {\n    \"c\": false\n  }\n}"
+        );
+        assert_eq!(
+            global(&lox, "message").to_string(),
+            "Invalid JSON at line 2, column 8: expected value"
+        );
+
+        for src in &[
+            "var l = [1]; l.push(l); json.stringify(l, nil);",
+            "json.stringify([1 / 3, clock], nil);",
+            "json.stringify(math.sqrt(-1), nil);",
+            "json.stringify(1, -1);",
+        ] {
+            match run(src) {
+                Err(Error::Runtime(1, ..)) => (),
+                r => panic!("expected a runtime error for {}, got {:?}", src, r.err()),
+            }
+        }
+        // a list appearing twice without containing itself isn't a cycle
+        let lox = run("var l = [1]; var s = json.stringify([l, l], 0);").unwrap();
+        assert_eq!(global(&lox, "s").to_string(), "[[1],[1]]");
+
+        // keys keep the document's order and the indent can be left out
+        let lox = run(r#"var s = json.stringify(json.parse("{\"b\": 1, \"a\": {\"d\": 2, \"c\": 3}}"));
+var keys = json.stringify({nil: 1, true: 2, 3: nil});"#)
+        .unwrap();
+        assert_eq!(global(&lox, "s").to_string(), r#"{"b":1,"a":{"d":2,"c":3}}"#);
+        assert_eq!(global(&lox, "keys").to_string(), r#"{"null":1,"true":2,"3":null}"#);
+    }
+
+    #[test]
+    fn test_regular_expressions() {
+        let lox = run(r#"var date = re.compile("(?P<year>\\d{4})-(\\d{2})(x)?");
+var m = date.search("née 1999-04, 2001-12");
+var a = [m.text, m.start, m.end, m.groups, m.spans, m.named["year"]];
+var b = [re.match("\\d+", "a1"), re.match("\\d+", "12a").text, re.search("z", "abc")];
+var all = re.findAll(date, "1999-04 2001-12");
+var c = [all.len(), all[1].text, all[1].start];
+var d = re.replace("(\\w+)@(\\w+)", "me@home you@work", "$2:$1");
+var e = date.split("a1999-01b2000-02c");
+var f = [type(date), str(date)];"#)
+        .unwrap();
+        assert_eq!(
+            global(&lox, "a").to_string(),
+            "[\"1999-04\", 4, 11, [\"1999\", \"04\", nil], [[4, 8], [9, 11], nil], \"1999\"]"
+        );
+        assert_eq!(global(&lox, "b").to_string(), "[nil, \"12\", nil]");
+        assert_eq!(global(&lox, "c").to_string(), "[2, \"2001-12\", 8]");
+        assert_eq!(global(&lox, "d").to_string(), "home:me work:you");
+        assert_eq!(global(&lox, "e").to_string(), "[\"a\", \"b\", \"c\"]");
+        assert_eq!(
+            global(&lox, "f").to_string(),
+            r#"["regex", "<regex (?P<year>\\d{4})-(\\d{2})(x)?>"]"#
+        );
+
+        for src in &["re.compile(\"(\");", "re.search(1, \"a\");", "re.split(\"a\", nil);"] {
+            match run(src) {
+                Err(Error::Runtime(1, ..)) => (),
+                r => panic!("expected a runtime error for {}, got {:?}", src, r.err()),
+            }
+        }
+        // a method's receiver isn't one of its numbered arguments
+        for &(src, expected) in &[
+            ("re.search(\"a\", 1);", "re.search() expects a string as argument 2"),
+            ("re.compile(\"a\").search(1);", "re.search() expects a string as argument 1"),
+            ("re.compile(\"a\").replace(\"b\", 1);", "re.replace() expects a string as argument 2"),
+        ] {
+            match run(src) {
+                Err(Error::Runtime(1, ref msg, _)) => assert_eq!(msg, expected),
+                r => panic!("expected a runtime error for {}, got {:?}", src, r.err()),
+            }
+        }
+    }
+
+    #[test]
+    fn test_for_in() {
+        let src = "
+            var out = [];
+            var l = [1, 2];
+            for (x in l) { if (x == 1) l.push(3); out.push(x); }
+            for (i, x in [\"a\", \"b\"]) out.push(str(i) + x);
+            var m = {\"k\": 1, \"j\": 2};
+            for (k in m) { m.remove(k); out.push(k); }
+            for (k, v in {\"z\": 26}) out.push(k + str(v));
+            for (c in \"añ\") out.push(c);
+            for (n in range(3)) out.push(n);
+            for (n in range(5, 0, -2)) { if (n == 1) break; out.push(n); }
+            for (n in range(0, 0.3, 0.1)) out.push(n);
+
+            class Countdown {
+                init(start) { this.start = start; }
+                iter() { return CountdownIterator(this.start); }
+            }
+            class CountdownIterator {
+                init(n) { this.n = n; }
+                next() {
+                    if (this.n == 0) return nil;
+                    this.n = this.n - 1;
+                    return this.n + 1;
+                }
+            }
+            for (n in Countdown(2)) out.push(n);
+            var it = CountdownIterator(1);
+            for (i, n in it) out.push([i, n]);
+
+            var fs = [];
+            for (x in [1, 2]) { fun get() { return x; } fs.push(get); }
+            var captured = [fs[0](), fs[1]()];
+        ";
+        let lox = run(src).unwrap();
+        assert_eq!(
+            global(&lox, "out").to_string(),
+            "[1, 2, 3, \"0a\", \"1b\", \"k\", \"j\", \"z26\", \"a\", \"ñ\", 0, 1, 2, 5, 3, \
+             0, 0.1, 0.2, 2, 1, [0, 1]]"
+        );
+        assert_eq!(global(&lox, "captured").to_string(), "[1, 2]");
+
+        for src in &["for (x in 1) {}", "for (x in range(1, 2, 0)) {}", "for (x in range()) {}"] {
+            match run(src) {
+                Err(Error::Runtime(1, ..)) => (),
+                r => panic!("expected a runtime error for {}, got {:?}", src, r.err()),
+            }
+        }
+    }
+
+    #[test]
+    fn test_increments() {
+        let src = r#"
+            var i = 0;
+            var seen = [i++, i, ++i, i--, --i];
+            class Box {}
+            var calls = 0;
+            var box = Box();
+            fun get() { calls += 1; return box; }
+            box.n = 1;
+            var old = get().n++;
+            var l = [10];
+            var j = 0;
+            var element = ++l[j++];
+            var m = {"k": 1};
+            m["k"]--;
+        "#;
+        let lox = run(src).unwrap();
+        assert_eq!(global(&lox, "seen").to_string(), "[0, 1, 2, 2, 0]");
+        assert_eq!(global(&lox, "i"), Value::Number(0.0));
+        assert_eq!(global(&lox, "old"), Value::Number(1.0));
+        assert_eq!(global(&lox, "calls"), Value::Number(1.0));
+        assert_eq!(global(&lox, "l").to_string(), "[11]");
+        assert_eq!(global(&lox, "element"), Value::Number(11.0));
+        assert_eq!(global(&lox, "j"), Value::Number(1.0));
+        assert_eq!(global(&lox, "m").to_string(), "{\"k\": 0}");
+        match run("var s = \"a\"; s++;") {
+            Err(Error::Runtime(1, ref msg, _)) => assert_eq!(msg, "Operand must be a number"),
+            r => panic!("expected a runtime error, got {:?}", r.err()),
+        }
+    }
+
+    #[test]
+    fn test_stack_overflow() {
+        let src = "
+            fun down(n) { return down(n + 1); }
+            var caught;
+            try { down(0); } catch (e) { caught = e.message; }
+            fun count(n) { if (n == 0) return 0; return count(n - 1) + 1; }
+            var deep = count(900);
+        ";
+        // as deep as the limit goes, like `lox run` does
+        let (caught, deep) = std::thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn(move || {
+                let lox = run(src).unwrap();
+                (global(&lox, "caught").to_string(), global(&lox, "deep").to_string())
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+        assert_eq!(caught, "Stack overflow: down");
+        assert_eq!(deep, "900");
+    }
+
+    #[test]
+    fn test_generators() {
+        let src = "
+            var out = [];
+            fun* count(n) {
+                for (var i = 0; i < n; i = i + 1) yield i;
+            }
+            for (n in count(3)) out.push(n);
+
+            fun* sum() {
+                var total = 0;
+                while (true) {
+                    var n = yield total;
+                    if (n == nil) return;
+                    total = total + n;
+                }
+            }
+            var s = sum();
+            var sums = [s.next(), s.send(2), s.send(5), s.next(), s.next(), s.done()];
+
+            fun* parts() {
+                try {
+                    yield 1;
+                    throw \"boom\";
+                } catch (e) {
+                    yield e;
+                } finally {
+                    yield \"finally\";
+                }
+                for (k, v in {\"k\": 2}) yield k + str(v);
+                var got;
+                got = yield;
+                if (got) yield \"then\"; else yield \"else\";
+            }
+            var p = parts();
+            for (x in [1, 2, 3, 4]) out.push(p.next());
+            p.next();
+            out.push(p.send(true));
+
+            fun* nested(n) {
+                if (n > 1) for (x in nested(n - 1)) yield x;
+                yield n;
+            }
+            class Pair {
+                init(a, b) { this.a = a; this.b = b; }
+                *iter() { yield this.a; yield this.b; }
+            }
+            for (x in nested(2)) out.push(x);
+            for (x in Pair(\"a\", \"b\")) out.push(x);
+            var shown = str(count(1));
+
+            fun* gap() { yield 1; yield nil; yield 3; }
+            var gaps = [];
+            for (x in gap()) gaps.push(x);
+            var g = gap();
+            g.next();
+            var mid = [g.next(), g.done()];
+            g.next();
+            var end = [g.next(), g.done()];
+
+            fun* inside() {
+                class Box {}
+                var box = Box();
+                var l = [0, 0];
+                box.x = yield \"a\";
+                l[1] = yield \"b\";
+                l[0] += yield \"c\";
+                var sum = 1 + (yield \"d\") * 10;
+                var pair = [yield \"e\", yield \"f\"];
+                while ((yield \"g\") != nil) {}
+                var either = (yield \"h\") or (yield \"i\");
+                return yield [box.x, l, sum, pair, either];
+            }
+            var i = inside();
+            var sent = [i.next()];
+            for (x in [1, 2, 3, 4, 5, 6, 7, nil, nil, 9, 10]) sent.push(i.send(x));
+            sent.push(i.done());
+        ";
+        let lox = run(src).unwrap();
+        assert_eq!(
+            global(&lox, "out").to_string(),
+            "[0, 1, 2, 1, \"boom\", \"finally\", \"k2\", \"then\", 1, 2, \"a\", \"b\"]"
+        );
+        assert_eq!(global(&lox, "sums").to_string(), "[0, 2, 7, nil, nil, true]");
+        assert_eq!(global(&lox, "shown").to_string(), "<generator count>");
+        assert_eq!(global(&lox, "gaps").to_string(), "[1, nil, 3]");
+        assert_eq!(global(&lox, "mid").to_string(), "[nil, false]");
+        assert_eq!(global(&lox, "end").to_string(), "[nil, true]");
+        assert_eq!(
+            global(&lox, "sent").to_string(),
+            "[\"a\", \"b\", \"c\", \"d\", \"e\", \"f\", \"g\", \"g\", \"h\", \"i\", \
+             [1, [3, 2], 41, [5, 6], 9], nil, true]"
+        );
+
+        let src = "fun* g() { yield 1; undefined; } var it = g(); it.next(); it.next();";
+        assert!(matches!(run(src), Err(Error::Runtime(1, ..))));
+        let src = "fun* g() { it.next(); yield 1; } var it = g(); it.next();";
+        match run(src) {
+            Err(Error::Runtime(_, ref msg, _)) => assert_eq!(msg, "Generator is already running"),
+            r => panic!("expected a runtime error, got {:?}", r.err()),
+        }
+    }
+
+    #[test]
+    fn test_lists() {
+        let lox = run("var l = [1, 2]; l.push(3); l[0] = l.pop(); var n = l.len();").unwrap();
+        assert_eq!(global(&lox, "l").to_string(), "[3, 2]");
+        assert_eq!(global(&lox, "n"), Value::Number(2.0));
+    }
+}
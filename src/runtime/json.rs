@@ -0,0 +1,187 @@
+//! The built-in `json` library
+
+use std::rc::Rc;
+
+use serde_json::Value as Json;
+
+use super::function::{string_arg, NativeFn, NativeFunction};
+use super::interpreter::Interpreter;
+use super::map::{LoxMap, MapKey};
+use super::module::Module;
+use super::value::Value;
+use crate::result::{Error, Result};
+
+/// Builds the `json` module, which is also defined as a global
+pub fn module() -> Module {
+    // name, arity, how many of the last arguments can be left out
+    let functions: &[(&str, usize, usize, NativeFn)] =
+        &[("parse", 1, 0, parse), ("stringify", 2, 1, stringify)];
+    let members = functions
+        .iter()
+        .map(|&(name, arity, optional, func)| {
+            let function = NativeFunction::new(name, arity, func).with_optional(optional);
+            (name, Value::Native(Rc::new(function)))
+        })
+        .collect();
+    Module::library("json", members)
+}
+
+/// Decodes a JSON document, objects become maps with their keys in
+/// document order
+fn parse(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    let text = string_arg("json.parse", args, 0)?;
+    let json: Json = serde_json::from_str(text).map_err(|e| {
+        // serde_json ends its message with the position given here
+        let message = e.to_string();
+        let position = format!(" at line {} column {}", e.line(), e.column());
+        let description = message.strip_suffix(&position).unwrap_or(&message);
+        Error::Runtime(
+            0,
+            format!("Invalid JSON at line {}, column {}", e.line(), e.column()),
+            description.to_string(),
+        )
+    })?;
+    Ok(from_json(json))
+}
+
+fn from_json(json: Json) -> Value {
+    match json {
+        Json::Null => Value::Nil,
+        Json::Bool(b) => Value::Bool(b),
+        Json::Number(n) => Value::Number(n.as_f64().unwrap_or(f64::NAN)),
+        Json::String(s) => Value::Str(Rc::from(s)),
+        Json::Array(values) => Value::list(values.into_iter().map(from_json).collect()),
+        Json::Object(entries) => {
+            let mut map = LoxMap::new();
+            for (key, value) in entries {
+                map.insert(MapKey::Str(Rc::from(key)), from_json(value));
+            }
+            Value::map(map)
+        }
+    }
+}
+
+/// Encodes a value, compactly if `indent` is left out, nil or 0, otherwise
+/// with that many spaces per level
+fn stringify(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    let indent = match *args.get(1).unwrap_or(&Value::Nil) {
+        Value::Nil => 0,
+        Value::Number(n) if n.fract() == 0.0 && (0.0..=16.0).contains(&n) => n as usize,
+        ref arg => {
+            return Err(Error::Runtime(
+                0,
+                "json.stringify() expects nil or up to 16 spaces as argument 2".to_string(),
+                arg.repr(),
+            ))
+        }
+    };
+    let mut writer = Writer {
+        out: String::new(),
+        indent,
+        seen: vec![],
+    };
+    writer.value(&args[0], 0)?;
+    Ok(Value::Str(Rc::from(writer.out)))
+}
+
+struct Writer {
+    out: String,
+    indent: usize,
+    /// the lists and maps being written, to detect cycles
+    seen: Vec<*const ()>,
+}
+
+impl Writer {
+    fn value(&mut self, value: &Value, depth: usize) -> Result<()> {
+        match *value {
+            Value::Nil => self.out.push_str("null"),
+            Value::Bool(b) => self.out.push_str(&b.to_string()),
+            Value::Number(n) if n.is_finite() => self.out.push_str(&n.to_string()),
+            Value::Str(ref s) => self.string(s),
+            Value::List(ref list) => {
+                self.enter(Rc::as_ptr(list) as *const ())?;
+                let list = list.borrow();
+                self.container('[', ']', list.len(), depth, |writer, i| {
+                    writer.value(&list[i], depth + 1)
+                })?;
+                self.seen.pop();
+            }
+            Value::Map(ref map) => {
+                self.enter(Rc::as_ptr(map) as *const ())?;
+                let map = map.borrow();
+                let entries: Vec<(&MapKey, &Value)> = map.iter().collect();
+                let separator = if self.indent > 0 { ": " } else { ":" };
+                self.container('{', '}', entries.len(), depth, |writer, i| {
+                    let (key, value) = entries[i];
+                    // object keys are strings, a nil key is written like a nil value
+                    match *key {
+                        MapKey::Nil => writer.string("null"),
+                        ref key => writer.string(&key.to_value().to_string()),
+                    }
+                    writer.out.push_str(separator);
+                    writer.value(value, depth + 1)
+                })?;
+                self.seen.pop();
+            }
+            _ => {
+                return Err(Error::Runtime(
+                    0,
+                    "json.stringify() can't convert".to_string(),
+                    value.repr(),
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    fn enter(&mut self, ptr: *const ()) -> Result<()> {
+        if self.seen.contains(&ptr) {
+            return Err(Error::Runtime(
+                0,
+                "json.stringify() can't convert a value containing itself".to_string(),
+                "[...]".to_string(),
+            ));
+        }
+        self.seen.push(ptr);
+        Ok(())
+    }
+
+    /// Writes `len` items between the brackets, one per line if indenting
+    fn container<F>(
+        &mut self,
+        open: char,
+        close: char,
+        len: usize,
+        depth: usize,
+        mut item: F,
+    ) -> Result<()>
+    where
+        F: FnMut(&mut Writer, usize) -> Result<()>,
+    {
+        self.out.push(open);
+        for i in 0..len {
+            if i > 0 {
+                self.out.push(',');
+            }
+            self.newline(depth + 1);
+            item(self, i)?;
+        }
+        if len > 0 {
+            self.newline(depth);
+        }
+        self.out.push(close);
+        Ok(())
+    }
+
+    fn newline(&mut self, depth: usize) {
+        if self.indent > 0 {
+            self.out.push('\n');
+            self.out.push_str(&" ".repeat(self.indent * depth));
+        }
+    }
+
+    fn string(&mut self, s: &str) {
+        let quoted = serde_json::to_string(s).expect("strings always serialize");
+        self.out.push_str(&quoted);
+    }
+}
@@ -0,0 +1,58 @@
+//! Built-in list methods
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::function::{NativeFn, NativeFunction};
+use super::interpreter::Interpreter;
+use super::value::Value;
+use crate::result::{Error, Result};
+
+/// Converts an index value into a position in a list of `len` elements
+pub fn to_index(index: &Value, len: usize) -> Result<usize> {
+    match *index {
+        Value::Number(n) if n.fract() == 0.0 && n >= 0.0 && (n as usize) < len => Ok(n as usize),
+        Value::Number(n) => Err(Error::Runtime(
+            0,
+            "List index out of range".to_string(),
+            n.to_string(),
+        )),
+        _ => Err(Error::Runtime(
+            0,
+            "List index must be a number".to_string(),
+            index.type_name().to_string(),
+        )),
+    }
+}
+
+/// Looks up a built-in list method, e.g. `l.push(1)`
+pub fn method(name: &str) -> Option<NativeFunction> {
+    let (arity, func): (usize, NativeFn) = match name {
+        "len" => (0, len),
+        "push" => (1, push),
+        "pop" => (0, pop),
+        _ => return None,
+    };
+    Some(NativeFunction::new(name, arity, func))
+}
+
+fn receiver(args: &[Value]) -> &Rc<RefCell<Vec<Value>>> {
+    match args[0] {
+        Value::List(ref list) => list,
+        _ => unreachable!("list methods are only bound to lists"),
+    }
+}
+
+fn len(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    Ok(Value::Number(receiver(args).borrow().len() as f64))
+}
+
+fn push(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    receiver(args).borrow_mut().push(args[1].clone());
+    Ok(Value::Nil)
+}
+
+/// Removes and returns the last element, or nil if the list is empty
+fn pop(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    Ok(receiver(args).borrow_mut().pop().unwrap_or(Value::Nil))
+}
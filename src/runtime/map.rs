@@ -0,0 +1,241 @@
+//! The built-in Map type: an insertion-ordered hash map keyed by
+//! strings, numbers, booleans and nil
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use super::function::{NativeFn, NativeFunction};
+use super::interpreter::Interpreter;
+use super::value::Value;
+use crate::result::{Error, Result};
+
+/// The hashable subset of Lox values
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MapKey {
+    Nil,
+    Bool(bool),
+    /// bits of the number, with `-0` folded into `0` and every NaN into one
+    Number(u64),
+    Str(Rc<str>),
+}
+
+impl MapKey {
+    pub fn from_value(value: &Value) -> Option<MapKey> {
+        match *value {
+            Value::Nil => Some(MapKey::Nil),
+            Value::Bool(b) => Some(MapKey::Bool(b)),
+            Value::Number(n) => {
+                let n = if n == 0.0 {
+                    0.0
+                } else if n.is_nan() {
+                    f64::NAN
+                } else {
+                    n
+                };
+                Some(MapKey::Number(n.to_bits()))
+            }
+            Value::Str(ref s) => Some(MapKey::Str(s.clone())),
+            _ => None,
+        }
+    }
+
+    pub fn to_value(&self) -> Value {
+        match *self {
+            MapKey::Nil => Value::Nil,
+            MapKey::Bool(b) => Value::Bool(b),
+            MapKey::Number(bits) => Value::Number(f64::from_bits(bits)),
+            MapKey::Str(ref s) => Value::Str(s.clone()),
+        }
+    }
+}
+
+/// A map that iterates in insertion order.
+/// Re-assigning an existing key keeps its original position.
+#[derive(Debug, Clone, Default)]
+pub struct LoxMap {
+    /// `None` where an entry was removed, until `remove` closes up the gaps
+    entries: Vec<Option<(MapKey, Value)>>,
+    index: HashMap<MapKey, usize>,
+}
+
+impl LoxMap {
+    pub fn new() -> LoxMap {
+        LoxMap::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    pub fn get(&self, key: &MapKey) -> Option<&Value> {
+        let i = *self.index.get(key)?;
+        self.entries[i].as_ref().map(|(_, v)| v)
+    }
+
+    pub fn contains_key(&self, key: &MapKey) -> bool {
+        self.index.contains_key(key)
+    }
+
+    pub fn insert(&mut self, key: MapKey, value: Value) {
+        match self.index.get(&key) {
+            Some(&i) => self.entries[i] = Some((key, value)),
+            None => {
+                self.index.insert(key.clone(), self.entries.len());
+                self.entries.push(Some((key, value)));
+            }
+        }
+    }
+
+    /// Leaves a gap where the entry was, so the others keep their
+    /// positions; the gaps are closed up once they are half the entries
+    pub fn remove(&mut self, key: &MapKey) -> Option<Value> {
+        let i = self.index.remove(key)?;
+        let (_, value) = self.entries[i].take()?;
+        if self.index.len() * 2 < self.entries.len() {
+            self.entries.retain(Option::is_some);
+            for (i, (k, _)) in self.entries.iter().flatten().enumerate() {
+                *self.index.get_mut(k).unwrap() = i;
+            }
+        }
+        Some(value)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&MapKey, &Value)> {
+        self.entries.iter().flatten().map(|(k, v)| (k, v))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &MapKey> {
+        self.iter().map(|(k, _)| k)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &Value> {
+        self.iter().map(|(_, v)| v)
+    }
+}
+
+/// Converts a value into a map key, or reports which type can't be used as one
+pub fn to_key(value: &Value) -> Result<MapKey> {
+    MapKey::from_value(value).ok_or_else(|| {
+        Error::Runtime(
+            0,
+            "Map keys must be strings, numbers, booleans or nil".to_string(),
+            value.type_name().to_string(),
+        )
+    })
+}
+
+/// Looks up a built-in map method, e.g. `m.keys()`
+pub fn method(name: &str) -> Option<NativeFunction> {
+    let (arity, func): (usize, NativeFn) = match name {
+        "len" => (0, len),
+        "keys" => (0, keys),
+        "values" => (0, values),
+        "has" => (1, has),
+        "remove" => (1, remove),
+        _ => return None,
+    };
+    Some(NativeFunction::new(name, arity, func))
+}
+
+fn receiver(args: &[Value]) -> &Rc<RefCell<LoxMap>> {
+    match args[0] {
+        Value::Map(ref map) => map,
+        _ => unreachable!("map methods are only bound to maps"),
+    }
+}
+
+fn len(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    Ok(Value::Number(receiver(args).borrow().len() as f64))
+}
+
+fn keys(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    let keys = receiver(args)
+        .borrow()
+        .keys()
+        .map(MapKey::to_value)
+        .collect();
+    Ok(Value::list(keys))
+}
+
+fn values(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    let values = receiver(args).borrow().values().cloned().collect();
+    Ok(Value::list(values))
+}
+
+fn has(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    let key = to_key(&args[1])?;
+    Ok(Value::Bool(receiver(args).borrow().contains_key(&key)))
+}
+
+/// Removes the key and returns its value, or nil if it wasn't present
+fn remove(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    let key = to_key(&args[1])?;
+    Ok(receiver(args)
+        .borrow_mut()
+        .remove(&key)
+        .unwrap_or(Value::Nil))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(s: &str) -> MapKey {
+        MapKey::Str(Rc::from(s))
+    }
+
+    #[test]
+    fn test_insertion_order() {
+        let mut map = LoxMap::new();
+        map.insert(key("b"), Value::Number(1.0));
+        map.insert(key("a"), Value::Number(2.0));
+        map.insert(key("c"), Value::Number(3.0));
+        map.insert(key("b"), Value::Number(4.0));
+
+        let keys: Vec<_> = map.keys().cloned().collect();
+        assert_eq!(keys, vec![key("b"), key("a"), key("c")]);
+        assert_eq!(map.get(&key("b")), Some(&Value::Number(4.0)));
+    }
+
+    #[test]
+    fn test_remove_keeps_index() {
+        let mut map = LoxMap::new();
+        map.insert(key("a"), Value::Number(1.0));
+        map.insert(key("b"), Value::Number(2.0));
+        map.insert(key("c"), Value::Number(3.0));
+
+        assert_eq!(map.remove(&key("a")), Some(Value::Number(1.0)));
+        assert_eq!(map.remove(&key("a")), None);
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get(&key("c")), Some(&Value::Number(3.0)));
+        assert_eq!(map.get(&key("b")), Some(&Value::Number(2.0)));
+
+        // closing up the gaps keeps the order and the index in step
+        for i in 0..10 {
+            map.insert(key(&i.to_string()), Value::Number(i as f64));
+        }
+        for i in 0..8 {
+            map.remove(&key(&i.to_string()));
+        }
+        map.remove(&key("b"));
+        map.insert(key("a"), Value::Nil);
+        let keys: Vec<_> = map.keys().cloned().collect();
+        assert_eq!(keys, vec![key("c"), key("8"), key("9"), key("a")]);
+        assert_eq!(map.get(&key("9")), Some(&Value::Number(9.0)));
+        assert_eq!(map.len(), 4);
+    }
+
+    #[test]
+    fn test_number_keys() {
+        assert_eq!(
+            MapKey::from_value(&Value::Number(0.0)),
+            MapKey::from_value(&Value::Number(-0.0))
+        );
+        assert_eq!(MapKey::from_value(&Value::list(vec![])), None);
+    }
+}
@@ -0,0 +1,150 @@
+//! The built-in `math` library
+
+use std::f64::consts;
+use std::rc::Rc;
+
+use super::function::{number_arg, NativeFn, NativeFunction};
+use super::interpreter::Interpreter;
+use super::module::Module;
+use super::value::Value;
+use crate::result::Result;
+
+/// Builds the `math` module, which is also defined as a global
+pub fn module() -> Module {
+    let functions: &[(&str, usize, NativeFn)] = &[
+        ("sqrt", 1, sqrt),
+        ("pow", 2, pow),
+        ("floor", 1, floor),
+        ("ceil", 1, ceil),
+        ("round", 1, round),
+        ("abs", 1, abs),
+        ("min", 2, min),
+        ("max", 2, max),
+        ("sin", 1, sin),
+        ("cos", 1, cos),
+        ("tan", 1, tan),
+        ("asin", 1, asin),
+        ("acos", 1, acos),
+        ("atan", 1, atan),
+        ("atan2", 2, atan2),
+        ("log", 1, log),
+        ("log10", 1, log10),
+        ("log2", 1, log2),
+        ("exp", 1, exp),
+        ("isNaN", 1, is_nan),
+        ("isInfinite", 1, is_infinite),
+    ];
+    let mut members: Vec<(&str, Value)> = functions
+        .iter()
+        .map(|&(name, arity, func)| {
+            (
+                name,
+                Value::Native(Rc::new(NativeFunction::new(name, arity, func))),
+            )
+        })
+        .collect();
+    members.push(("PI", Value::Number(consts::PI)));
+    members.push(("E", Value::Number(consts::E)));
+    Module::library("math", members)
+}
+
+/// Applies `f` to the only argument of `math.<name>`
+fn unary(name: &str, args: &[Value], f: fn(f64) -> f64) -> Result<Value> {
+    Ok(Value::Number(f(number_arg(name, args, 0)?)))
+}
+
+/// Applies `f` to both arguments of `math.<name>`
+fn binary(name: &str, args: &[Value], f: fn(f64, f64) -> f64) -> Result<Value> {
+    let a = number_arg(name, args, 0)?;
+    let b = number_arg(name, args, 1)?;
+    Ok(Value::Number(f(a, b)))
+}
+
+fn sqrt(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    unary("math.sqrt", args, f64::sqrt)
+}
+
+fn pow(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    binary("math.pow", args, f64::powf)
+}
+
+fn floor(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    unary("math.floor", args, f64::floor)
+}
+
+fn ceil(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    unary("math.ceil", args, f64::ceil)
+}
+
+/// Rounds half-way cases away from zero
+fn round(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    unary("math.round", args, f64::round)
+}
+
+fn abs(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    unary("math.abs", args, f64::abs)
+}
+
+fn min(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    binary("math.min", args, f64::min)
+}
+
+fn max(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    binary("math.max", args, f64::max)
+}
+
+fn sin(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    unary("math.sin", args, f64::sin)
+}
+
+fn cos(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    unary("math.cos", args, f64::cos)
+}
+
+fn tan(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    unary("math.tan", args, f64::tan)
+}
+
+fn asin(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    unary("math.asin", args, f64::asin)
+}
+
+fn acos(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    unary("math.acos", args, f64::acos)
+}
+
+fn atan(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    unary("math.atan", args, f64::atan)
+}
+
+/// The angle of the point (x, y), called as `atan2(y, x)`
+fn atan2(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    binary("math.atan2", args, f64::atan2)
+}
+
+/// The natural logarithm
+fn log(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    unary("math.log", args, f64::ln)
+}
+
+fn log10(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    unary("math.log10", args, f64::log10)
+}
+
+fn log2(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    unary("math.log2", args, f64::log2)
+}
+
+fn exp(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    unary("math.exp", args, f64::exp)
+}
+
+fn is_nan(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    Ok(Value::Bool(number_arg("math.isNaN", args, 0)?.is_nan()))
+}
+
+fn is_infinite(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    Ok(Value::Bool(
+        number_arg("math.isInfinite", args, 0)?.is_infinite(),
+    ))
+}
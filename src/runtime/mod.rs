@@ -0,0 +1,16 @@
+pub mod builtins;
+pub mod class;
+pub mod date;
+pub mod environment;
+pub mod filesystem;
+pub mod function;
+pub mod generator;
+pub mod interpreter;
+pub mod json;
+pub mod list;
+pub mod map;
+pub mod math;
+pub mod module;
+pub mod re;
+pub mod string;
+pub mod value;
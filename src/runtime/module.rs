@@ -0,0 +1,44 @@
+//! Scripts loaded by `import` statements
+
+use std::cell::RefCell;
+use std::iter;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use super::environment::Environment;
+use super::value::Value;
+
+/// A script loaded by `import`, with its own global scope
+#[derive(Debug)]
+pub struct Module {
+    /// the canonical path of the script, or the name of a library
+    pub path: PathBuf,
+    pub globals: Rc<RefCell<Environment>>,
+}
+
+impl Module {
+    pub fn new(path: PathBuf, globals: Rc<RefCell<Environment>>) -> Module {
+        Module { path, globals }
+    }
+
+    /// A built-in module, imported by its bare name
+    pub fn library(name: &str, members: Vec<(&str, Value)>) -> Module {
+        let mut globals = Environment::new();
+        for (member, value) in members {
+            globals.define(member, value);
+        }
+        Module::new(PathBuf::from(name), Rc::new(RefCell::new(globals)))
+    }
+}
+
+/// Finds the script `import "name"` refers to: relative to the directory
+/// of the importing script first, then in each directory of the search
+/// path
+pub fn resolve(name: &str, importer: Option<&Path>, search_path: &[PathBuf]) -> Option<PathBuf> {
+    let base = importer.and_then(Path::parent).unwrap_or_else(|| Path::new(""));
+    iter::once(base)
+        .chain(search_path.iter().map(PathBuf::as_path))
+        .map(|dir| dir.join(name))
+        .find(|path| path.is_file())
+        .and_then(|path| path.canonicalize().ok())
+}
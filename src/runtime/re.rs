@@ -0,0 +1,188 @@
+//! The built-in `re` library and compiled regular expressions.
+//! Positions in match objects count characters, like string methods do.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use regex::{Captures, Regex};
+
+use super::class::{LoxClass, LoxInstance};
+use super::function::{string_arg, NativeFn, NativeFunction};
+use super::interpreter::Interpreter;
+use super::map::{LoxMap, MapKey};
+use super::module::Module;
+use super::value::Value;
+use crate::result::{Error, Result};
+
+thread_local! {
+    /// the class of match objects
+    static MATCH: Rc<LoxClass> = Rc::new(LoxClass::new("Match", None, HashMap::new()));
+}
+
+/// Functions taking the pattern first, which are also the methods of
+/// compiled expressions. Each is instantiated twice, `METHOD` telling
+/// whether the pattern is the receiver and so left out of the argument
+/// numbers in errors.
+const FUNCTIONS: &[(&str, usize, NativeFn, NativeFn)] = &[
+    ("match", 2, match_start::<false>, match_start::<true>),
+    ("search", 2, search::<false>, search::<true>),
+    ("findAll", 2, find_all::<false>, find_all::<true>),
+    ("replace", 3, replace::<false>, replace::<true>),
+    ("split", 2, split::<false>, split::<true>),
+];
+
+/// Builds the `re` module, which is also defined as a global
+pub fn module() -> Module {
+    let compile = NativeFunction::new("compile", 1, compile);
+    let mut members = vec![("compile", Value::Native(Rc::new(compile)))];
+    members.extend(FUNCTIONS.iter().map(|&(name, arity, func, _)| {
+        let function = NativeFunction::new(name, arity, func);
+        (name, Value::Native(Rc::new(function)))
+    }));
+    members.push(("Match", Value::Class(MATCH.with(Rc::clone))));
+    Module::library("re", members)
+}
+
+/// Looks up a method of a compiled expression, e.g. `r.search(s)`
+pub fn method(name: &str) -> Option<NativeFunction> {
+    FUNCTIONS
+        .iter()
+        .find(|&&(function, ..)| function == name)
+        .map(|&(name, arity, _, method)| NativeFunction::new(name, arity - 1, method))
+}
+
+fn compile(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    let pattern = string_arg("re.compile", args, 0)?;
+    Ok(Value::Regex(Rc::new(new_regex(pattern)?)))
+}
+
+fn new_regex(pattern: &str) -> Result<Regex> {
+    Regex::new(pattern)
+        .map_err(|e| Error::Runtime(0, "Invalid regular expression".to_string(), e.to_string()))
+}
+
+/// The expression a function is called with, or the receiver of a method
+fn regex_arg(function: &str, args: &[Value]) -> Result<Rc<Regex>> {
+    match args[0] {
+        Value::Regex(ref regex) => Ok(regex.clone()),
+        Value::Str(ref pattern) => Ok(Rc::new(new_regex(pattern)?)),
+        ref arg => Err(Error::Runtime(
+            0,
+            format!("{}() expects a pattern as argument 1", function),
+            arg.repr(),
+        )),
+    }
+}
+
+/// The `index`th argument of a function taking the pattern first, which
+/// must be a string
+fn text_arg<'a, const METHOD: bool>(
+    function: &str,
+    args: &'a [Value],
+    index: usize,
+) -> Result<&'a str> {
+    let receiver = if METHOD { 1 } else { 0 };
+    string_arg(function, &args[receiver..], index - receiver)
+}
+
+/// The number of characters before `byte` in `text`
+fn char_offset(text: &str, byte: usize) -> f64 {
+    text[..byte].chars().count() as f64
+}
+
+fn string(s: &str) -> Value {
+    Value::Str(Rc::from(s))
+}
+
+/// A match object: `text`, `start` and `end` of the whole match, and
+/// `groups`, `spans` and `named` for the capture groups, nil where a
+/// group took no part in the match
+fn match_object(regex: &Regex, text: &str, captures: &Captures) -> Value {
+    let whole = captures.get(0).expect("group 0 is the whole match");
+    let mut groups = vec![];
+    let mut spans = vec![];
+    for group in captures.iter().skip(1) {
+        match group {
+            Some(group) => {
+                groups.push(string(group.as_str()));
+                spans.push(Value::list(vec![
+                    Value::Number(char_offset(text, group.start())),
+                    Value::Number(char_offset(text, group.end())),
+                ]));
+            }
+            None => {
+                groups.push(Value::Nil);
+                spans.push(Value::Nil);
+            }
+        }
+    }
+    let mut named = LoxMap::new();
+    for name in regex.capture_names().flatten() {
+        let group = captures
+            .name(name)
+            .map_or(Value::Nil, |g| string(g.as_str()));
+        named.insert(MapKey::Str(Rc::from(name)), group);
+    }
+
+    let mut instance = LoxInstance::new(MATCH.with(Rc::clone));
+    let fields = [
+        ("text", string(whole.as_str())),
+        ("start", Value::Number(char_offset(text, whole.start()))),
+        ("end", Value::Number(char_offset(text, whole.end()))),
+        ("groups", Value::list(groups)),
+        ("spans", Value::list(spans)),
+        ("named", Value::map(named)),
+    ];
+    for (name, value) in fields.iter() {
+        instance.fields.insert(name.to_string(), value.clone());
+    }
+    Value::Instance(Rc::new(RefCell::new(instance)))
+}
+
+/// The match at the start of the text, or nil
+fn match_start<const METHOD: bool>(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    let regex = regex_arg("re.match", args)?;
+    let text = text_arg::<METHOD>("re.match", args, 1)?;
+    // leftmost matching finds one at 0 if there is any
+    Ok(match regex.captures(text) {
+        Some(ref captures) if captures.get(0).is_some_and(|m| m.start() == 0) => {
+            match_object(&regex, text, captures)
+        }
+        _ => Value::Nil,
+    })
+}
+
+/// The first match anywhere in the text, or nil
+fn search<const METHOD: bool>(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    let regex = regex_arg("re.search", args)?;
+    let text = text_arg::<METHOD>("re.search", args, 1)?;
+    Ok(regex
+        .captures(text)
+        .map_or(Value::Nil, |captures| match_object(&regex, text, &captures)))
+}
+
+fn find_all<const METHOD: bool>(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    let regex = regex_arg("re.findAll", args)?;
+    let text = text_arg::<METHOD>("re.findAll", args, 1)?;
+    let matches = regex
+        .captures_iter(text)
+        .map(|captures| match_object(&regex, text, &captures))
+        .collect();
+    Ok(Value::list(matches))
+}
+
+/// Replaces every match, `$1` or `${name}` in the replacement stand for
+/// groups
+fn replace<const METHOD: bool>(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    let regex = regex_arg("re.replace", args)?;
+    let text = text_arg::<METHOD>("re.replace", args, 1)?;
+    let replacement = text_arg::<METHOD>("re.replace", args, 2)?;
+    Ok(string(&regex.replace_all(text, replacement)))
+}
+
+fn split<const METHOD: bool>(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    let regex = regex_arg("re.split", args)?;
+    let text = text_arg::<METHOD>("re.split", args, 1)?;
+    Ok(Value::list(regex.split(text).map(string).collect()))
+}
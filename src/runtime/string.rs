@@ -0,0 +1,142 @@
+//! Built-in string methods, which count in characters rather than bytes
+
+use std::rc::Rc;
+
+use super::function::{number_arg, string_arg, NativeFn, NativeFunction};
+use super::interpreter::Interpreter;
+use super::value::Value;
+use crate::result::{Error, Result};
+
+/// Looks up a built-in string method, e.g. `s.split(",")`
+pub fn method(name: &str) -> Option<NativeFunction> {
+    let (arity, func): (usize, NativeFn) = match name {
+        "len" => (0, len),
+        "substring" => (2, substring),
+        "indexOf" => (1, index_of),
+        "split" => (1, split),
+        "join" => (1, join),
+        "trim" => (0, trim),
+        "upper" => (0, upper),
+        "lower" => (0, lower),
+        "replace" => (2, replace),
+        "startsWith" => (1, starts_with),
+        "endsWith" => (1, ends_with),
+        "chars" => (0, chars),
+        _ => return None,
+    };
+    Some(NativeFunction::new(name, arity, func))
+}
+
+fn receiver(args: &[Value]) -> &str {
+    match args[0] {
+        Value::Str(ref s) => s,
+        _ => unreachable!("string methods are only bound to strings"),
+    }
+}
+
+fn string(s: &str) -> Value {
+    Value::Str(Rc::from(s))
+}
+
+fn len(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    Ok(Value::Number(receiver(args).chars().count() as f64))
+}
+
+/// The characters from `start` up to, but not including, `end`
+fn substring(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    let s = receiver(args);
+    let len = s.chars().count();
+    let start = number_arg("string.substring", &args[1..], 0)?;
+    let end = number_arg("string.substring", &args[1..], 1)?;
+    let in_range = |n: f64| n.fract() == 0.0 && n >= 0.0 && n <= len as f64;
+    if !in_range(start) || !in_range(end) || start > end {
+        return Err(Error::Runtime(
+            0,
+            "Substring out of range".to_string(),
+            format!("{}..{} of {} characters", start, end, len),
+        ));
+    }
+    let sub: String = s
+        .chars()
+        .skip(start as usize)
+        .take((end - start) as usize)
+        .collect();
+    Ok(string(&sub))
+}
+
+/// The position of the first occurrence of the argument, or -1
+fn index_of(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    let s = receiver(args);
+    let needle = string_arg("string.indexOf", &args[1..], 0)?;
+    let index = s
+        .find(needle)
+        .map_or(-1.0, |byte| s[..byte].chars().count() as f64);
+    Ok(Value::Number(index))
+}
+
+/// Splitting on an empty separator gives the characters
+fn split(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    let s = receiver(args);
+    let separator = string_arg("string.split", &args[1..], 0)?;
+    if separator.is_empty() {
+        return Ok(char_list(s));
+    }
+    Ok(Value::list(s.split(separator).map(string).collect()))
+}
+
+/// Joins the elements of a list with the receiver between them
+fn join(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    let list = match args[1] {
+        Value::List(ref list) => list,
+        ref arg => {
+            return Err(Error::Runtime(
+                0,
+                "string.join() expects a list as argument 1".to_string(),
+                arg.repr(),
+            ))
+        }
+    };
+    let parts: Vec<String> = list.borrow().iter().map(Value::to_string).collect();
+    Ok(string(&parts.join(receiver(args))))
+}
+
+fn trim(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    Ok(string(receiver(args).trim()))
+}
+
+fn upper(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    Ok(string(&receiver(args).to_uppercase()))
+}
+
+fn lower(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    Ok(string(&receiver(args).to_lowercase()))
+}
+
+/// Replaces every occurrence of the first argument with the second
+fn replace(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    let from = string_arg("string.replace", &args[1..], 0)?;
+    let to = string_arg("string.replace", &args[1..], 1)?;
+    Ok(string(&receiver(args).replace(from, to)))
+}
+
+fn starts_with(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    let prefix = string_arg("string.startsWith", &args[1..], 0)?;
+    Ok(Value::Bool(receiver(args).starts_with(prefix)))
+}
+
+fn ends_with(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    let suffix = string_arg("string.endsWith", &args[1..], 0)?;
+    Ok(Value::Bool(receiver(args).ends_with(suffix)))
+}
+
+fn chars(_: &mut Interpreter, args: &[Value]) -> Result<Value> {
+    Ok(char_list(receiver(args)))
+}
+
+fn char_list(s: &str) -> Value {
+    Value::list(
+        s.chars()
+            .map(|c| string(c.encode_utf8(&mut [0; 4])))
+            .collect(),
+    )
+}
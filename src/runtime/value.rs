@@ -0,0 +1,219 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+use regex::Regex;
+
+use super::class::{LoxClass, LoxInstance};
+use super::function::{LoxFunction, NativeFunction};
+use super::generator::Generator;
+use super::map::LoxMap;
+use super::module::Module;
+
+/// A Lox runtime value
+#[derive(Clone)]
+pub enum Value {
+    Nil,
+    Bool(bool),
+    Number(f64),
+    Str(Rc<str>),
+    List(Rc<RefCell<Vec<Value>>>),
+    Map(Rc<RefCell<LoxMap>>),
+    Function(Rc<LoxFunction>),
+    Native(Rc<NativeFunction>),
+    Class(Rc<LoxClass>),
+    Instance(Rc<RefCell<LoxInstance>>),
+    Module(Rc<Module>),
+    Regex(Rc<Regex>),
+    /// start, end and step of `range(...)`
+    Range(f64, f64, f64),
+    Generator(Rc<RefCell<Generator>>),
+}
+
+impl Value {
+    pub fn list(values: Vec<Value>) -> Value {
+        Value::List(Rc::new(RefCell::new(values)))
+    }
+
+    pub fn map(map: LoxMap) -> Value {
+        Value::Map(Rc::new(RefCell::new(map)))
+    }
+
+    /// `false` and `nil` are falsey, everything else is truthy
+    pub fn is_truthy(&self) -> bool {
+        match *self {
+            Value::Nil => false,
+            Value::Bool(b) => b,
+            _ => true,
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match *self {
+            Value::Nil => "nil",
+            Value::Bool(_) => "bool",
+            Value::Number(_) => "number",
+            Value::Str(_) => "string",
+            Value::List(_) => "list",
+            Value::Map(_) => "map",
+            Value::Function(_) | Value::Native(_) => "function",
+            Value::Class(_) => "class",
+            Value::Instance(_) => "instance",
+            Value::Module(_) => "module",
+            Value::Regex(_) => "regex",
+            Value::Range(..) => "range",
+            Value::Generator(_) => "generator",
+        }
+    }
+
+    /// Values are equal if they have the same type and content,
+    /// lists, maps and objects are compared by identity.
+    pub fn equals(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Nil, Value::Nil) => true,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::Str(a), Value::Str(b)) => a == b,
+            (Value::List(a), Value::List(b)) => Rc::ptr_eq(a, b),
+            (Value::Map(a), Value::Map(b)) => Rc::ptr_eq(a, b),
+            (Value::Function(a), Value::Function(b)) => Rc::ptr_eq(a, b),
+            (Value::Native(a), Value::Native(b)) => Rc::ptr_eq(a, b),
+            (Value::Class(a), Value::Class(b)) => Rc::ptr_eq(a, b),
+            (Value::Instance(a), Value::Instance(b)) => Rc::ptr_eq(a, b),
+            (Value::Module(a), Value::Module(b)) => Rc::ptr_eq(a, b),
+            (Value::Regex(a), Value::Regex(b)) => Rc::ptr_eq(a, b),
+            (Value::Range(a, b, c), Value::Range(x, y, z)) => a == x && b == y && c == z,
+            (Value::Generator(a), Value::Generator(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+
+    /// Like `Display`, but strings are quoted the way they are inside containers
+    pub fn repr(&self) -> String {
+        let mut s = String::new();
+        self.write(&mut s, true, &mut vec![])
+            .expect("writing to a String can't fail");
+        s
+    }
+
+    fn write<W: fmt::Write>(
+        &self,
+        f: &mut W,
+        quoted: bool,
+        seen: &mut Vec<*const ()>,
+    ) -> fmt::Result {
+        match *self {
+            Value::Nil => write!(f, "nil"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Number(n) => write!(f, "{}", format_number(n)),
+            Value::Str(ref s) if quoted => write!(f, "{:?}", s),
+            Value::Str(ref s) => write!(f, "{}", s),
+            Value::List(ref list) => {
+                let ptr = Rc::as_ptr(list) as *const ();
+                if seen.contains(&ptr) {
+                    return write!(f, "[...]");
+                }
+                seen.push(ptr);
+                write!(f, "[")?;
+                for (i, v) in list.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    v.write(f, true, seen)?;
+                }
+                seen.pop();
+                write!(f, "]")
+            }
+            Value::Map(ref map) => {
+                let ptr = Rc::as_ptr(map) as *const ();
+                if seen.contains(&ptr) {
+                    return write!(f, "{{...}}");
+                }
+                seen.push(ptr);
+                write!(f, "{{")?;
+                for (i, (k, v)) in map.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    k.to_value().write(f, true, seen)?;
+                    write!(f, ": ")?;
+                    v.write(f, true, seen)?;
+                }
+                seen.pop();
+                write!(f, "}}")
+            }
+            Value::Function(ref fun) => write!(f, "<fn {}>", fun.name()),
+            Value::Native(ref fun) => write!(f, "<native fn {}>", fun.name),
+            Value::Class(ref class) => write!(f, "{}", class.name),
+            Value::Instance(ref instance) => write!(f, "{} instance", instance.borrow().class.name),
+            Value::Module(ref module) => write!(f, "<module {}>", module.path.display()),
+            Value::Regex(ref regex) => write!(f, "<regex {}>", regex.as_str()),
+            Value::Range(start, end, step) => write!(
+                f,
+                "range({}, {}, {})",
+                format_number(start),
+                format_number(end),
+                format_number(step)
+            ),
+            Value::Generator(ref generator) => {
+                write!(f, "<generator {}>", generator.borrow().name())
+            }
+        }
+    }
+}
+
+/// Formats numbers the way Lox prints them: integral values have no
+/// fractional part and infinities are spelled out.
+pub fn format_number(n: f64) -> String {
+    if n.is_infinite() {
+        if n > 0.0 {
+            "Infinity".to_string()
+        } else {
+            "-Infinity".to_string()
+        }
+    } else {
+        format!("{}", n)
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Value) -> bool {
+        self.equals(other)
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write(f, false, &mut vec![])
+    }
+}
+
+impl fmt::Debug for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write(f, true, &mut vec![])
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Value {
+        Value::Bool(b)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(n: f64) -> Value {
+        Value::Number(n)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Value {
+        Value::Str(Rc::from(s))
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Value {
+        Value::Str(Rc::from(s))
+    }
+}